@@ -0,0 +1,365 @@
+//! Photonic Band Structure Calculation
+//!
+//! Sweeps the Bloch wave vector along the Γ–X–M–Γ path of a square unit
+//! cell, or along Γ–X–S–Y–Γ for the a × 2a rectangular cell of the
+//! photonic crystal preset (whose offset rows do not repeat in a square).
+//! At each k point the cell is excited with a broadband modulated
+//! Gaussian, several randomly placed probes record the ringdown, and the
+//! resonance frequencies are read off their averaged spectrum (TM modes).
+//!
+//! Wave vectors are given in units of 2π/a and frequencies in the usual
+//! normalized units ωa/2πc, where a is the cell size.
+//!
+//! Author: Mehmet Gümüş (github.com/SpaceEngineerSS)
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::fdtd::FDTDGrid;
+use crate::sources::{Probe, SourceFunction, SpectrumAnalyzer};
+
+/// Default number of probes recording each k point
+const DEFAULT_PROBES: usize = 5;
+
+/// Default seed for probe and source placement
+const DEFAULT_SEED: u32 = 0x5EED;
+
+/// Default upper edge of the excitation band (ωa/2πc)
+const DEFAULT_MAX_FREQUENCY: f32 = 1.0;
+
+/// Peaks weaker than the strongest one by more than this are discarded
+const PEAK_RANGE_DB: f32 = 30.0;
+
+/// Photonic band structure driver for a square or a × 2a unit cell
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct BandStructure {
+    cell_size: usize,
+    points_per_segment: usize,
+    steps: usize,
+    num_probes: usize,
+    seed: u32,
+    max_frequency: f32,
+
+    // Results (one entry per k point)
+    k_positions: Vec<f32>,
+    k_vectors: Vec<(f32, f32)>,
+    bands: Vec<Vec<f32>>,
+    symmetry_positions: Vec<f32>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl BandStructure {
+    /// Create a band structure driver
+    ///
+    /// # Arguments
+    /// * `cell_size` - Lattice constant a in grid cells (cell width)
+    /// * `points_per_segment` - k samples on each segment of the path
+    /// * `steps` - Recorded time steps per k point (rounded up to a power of 2)
+    ///
+    /// Cells under 4, zero points and fewer than 64 steps are raised to
+    /// those minimums (see `try_new`).
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(cell_size: usize, points_per_segment: usize, steps: usize) -> BandStructure {
+        BandStructure {
+            cell_size: cell_size.max(4),
+            points_per_segment: points_per_segment.max(1),
+            steps: steps.max(64).next_power_of_two(),
+            num_probes: DEFAULT_PROBES,
+            seed: DEFAULT_SEED,
+            max_frequency: DEFAULT_MAX_FREQUENCY,
+            k_positions: Vec::new(),
+            k_vectors: Vec::new(),
+            bands: Vec::new(),
+            symmetry_positions: Vec::new(),
+        }
+    }
+
+    /// Set number of randomly placed probes
+    pub fn set_probe_count(&mut self, count: usize) {
+        self.num_probes = count.max(1);
+    }
+
+    /// Set seed for probe and source placement
+    pub fn set_seed(&mut self, seed: u32) {
+        self.seed = seed;
+    }
+
+    /// Set upper edge of the excitation band (ωa/2πc)
+    pub fn set_max_frequency(&mut self, max_frequency: f32) {
+        self.max_frequency = max_frequency.max(0.05);
+    }
+
+    /// Compute the bands of the photonic crystal preset
+    ///
+    /// Runs its a × 2a periodic cell along Γ–X–S–Y–Γ; the cell holds two
+    /// lattice points, so the bands appear folded into that smaller zone.
+    pub fn compute(&mut self) {
+        let a = self.cell_size;
+        self.run(a, 2 * a, &RECTANGULAR_PATH, |grid| grid.load_preset(8));
+    }
+
+    /// Get number of k points on the path
+    pub fn get_k_count(&self) -> usize {
+        self.k_positions.len()
+    }
+
+    /// Get position of every k point along the path (units of 2π/a)
+    pub fn get_k_positions(&self) -> Vec<f32> {
+        self.k_positions.clone()
+    }
+
+    /// Get wave vector [kx, ky] of one k point (units of 2π/a)
+    pub fn get_k_vector(&self, k_index: usize) -> Vec<f32> {
+        self.k_vectors
+            .get(k_index)
+            .map_or(Vec::new(), |&(kx, ky)| vec![kx, ky])
+    }
+
+    /// Get resonance frequencies found at one k point (ωa/2πc, ascending)
+    pub fn get_frequencies(&self, k_index: usize) -> Vec<f32> {
+        self.bands.get(k_index).cloned().unwrap_or_default()
+    }
+
+    /// Get the band diagram as flattened (k position, frequency) pairs
+    pub fn get_band_dataset(&self) -> Vec<f32> {
+        let mut data = Vec::new();
+        for (&k, freqs) in self.k_positions.iter().zip(&self.bands) {
+            for &f in freqs {
+                data.push(k);
+                data.push(f);
+            }
+        }
+        data
+    }
+
+    /// Get path positions of the symmetry points of the last run
+    /// (Γ, X, M, Γ or Γ, X, S, Y, Γ; units of 2π/a)
+    pub fn get_symmetry_positions(&self) -> Vec<f32> {
+        self.symmetry_positions.clone()
+    }
+}
+
+impl BandStructure {
+    /// Create a band structure driver, rejecting the values `new` would raise
+    pub fn try_new(
+        cell_size: usize,
+        points_per_segment: usize,
+        steps: usize,
+    ) -> Result<BandStructure, String> {
+        if cell_size < 4 {
+            return Err(format!("unit cell of {} cells is too small", cell_size));
+        }
+        if points_per_segment == 0 {
+            return Err("at least one k point per segment is needed".to_string());
+        }
+        if steps < 64 {
+            return Err(format!("{} time steps are too few (minimum 64)", steps));
+        }
+        Ok(BandStructure::new(cell_size, points_per_segment, steps))
+    }
+
+    /// Compute the bands of a custom unit cell
+    ///
+    /// `setup` paints the cell materials on a fresh `cell_size` × `cell_size` grid;
+    /// the driver then takes over the boundaries (Bloch-periodic on both axes).
+    pub fn compute_with<F: FnOnce(&mut FDTDGrid)>(&mut self, setup: F) {
+        let a = self.cell_size;
+        self.run(a, a, &SQUARE_PATH, setup);
+    }
+
+    /// Sweep `corners` on a width × height cell (width = a)
+    fn run<F: FnOnce(&mut FDTDGrid)>(
+        &mut self,
+        width: usize,
+        height: usize,
+        corners: &[(f32, f32)],
+        setup: F,
+    ) {
+        let a = self.cell_size;
+        let mut grid = FDTDGrid::new(width, height);
+        setup(&mut grid);
+
+        // Normalized frequency ωa/2πc = f * a / (c Δt), with c = Δx = 1
+        let to_normalized = a as f32 / grid.get_courant();
+
+        // Modulated Gaussian centred in the band: spectrum falls to 1/e at 0 and f_max
+        let fc = 0.5 * self.max_frequency / to_normalized;
+        let tau = 1.0 / (std::f32::consts::PI * fc);
+        let t0 = 4.0 * tau;
+        let source = SourceFunction::new_modulated_gaussian(fc, t0, tau, 1.0);
+        let excitation_steps = (2.0 * t0).ceil() as usize;
+
+        // Random source and probe positions (fixed for the whole sweep)
+        let mut rng = self.seed.max(1);
+        let mut random_cell = || {
+            let x = xorshift(&mut rng) as usize % width;
+            let y = xorshift(&mut rng) as usize % height;
+            (x, y)
+        };
+        let (sx, sy) = random_cell();
+        let mut probes: Vec<Probe> = (0..self.num_probes)
+            .map(|_| {
+                let (x, y) = random_cell();
+                Probe::new(x, y, self.steps)
+            })
+            .collect();
+
+        let mut analyzer = SpectrumAnalyzer::new(self.steps);
+        let min_bin = 2;
+
+        self.k_vectors = k_path(corners, self.points_per_segment);
+        self.k_positions = path_positions(&self.k_vectors);
+        self.symmetry_positions = path_positions(corners);
+        self.bands.clear();
+
+        // Phase across the cell: k (units of 2π/a) times the cell extent
+        let (span_x, span_y) = (width as f32 / a as f32, height as f32 / a as f32);
+        for &(kx, ky) in &self.k_vectors {
+            let two_pi = 2.0 * std::f32::consts::PI;
+            grid.set_bloch_phase(two_pi * kx * span_x, two_pi * ky * span_y);
+            grid.reset();
+            for probe in probes.iter_mut() {
+                probe.clear();
+            }
+
+            for t in 0..excitation_steps + self.steps {
+                grid.add_field_at(sx, sy, source.evaluate(t as f32));
+                grid.step();
+                if t >= excitation_steps {
+                    for probe in probes.iter_mut() {
+                        probe.record(grid.ez(), width);
+                    }
+                }
+            }
+
+            let signals: Vec<Vec<f32>> = probes.iter().map(|p| p.get_samples()).collect();
+            analyzer.compute_averaged(&signals);
+
+            let size = analyzer.get_size() as f32;
+            let freqs: Vec<f32> = analyzer
+                .find_peak_bins(PEAK_RANGE_DB)
+                .into_iter()
+                .filter(|&bin| bin >= min_bin)
+                .map(|bin| analyzer.interpolate_peak(bin) / size * to_normalized)
+                .filter(|&f| f <= self.max_frequency)
+                .collect();
+            self.bands.push(freqs);
+        }
+    }
+}
+
+/// Γ, X, M, Γ of a square a × a cell (units of 2π/a)
+const SQUARE_PATH: [(f32, f32); 4] = [(0.0, 0.0), (0.5, 0.0), (0.5, 0.5), (0.0, 0.0)];
+
+/// Γ, X, S, Y, Γ of a rectangular a × 2a cell (units of 2π/a)
+const RECTANGULAR_PATH: [(f32, f32); 5] =
+    [(0.0, 0.0), (0.5, 0.0), (0.5, 0.25), (0.0, 0.25), (0.0, 0.0)];
+
+/// Sample a path through the given corners (units of 2π/a)
+fn k_path(corners: &[(f32, f32)], points_per_segment: usize) -> Vec<(f32, f32)> {
+    let segments = corners.len().saturating_sub(1);
+    let mut path = Vec::with_capacity(segments * points_per_segment + 1);
+
+    for seg in corners.windows(2) {
+        let ((x0, y0), (x1, y1)) = (seg[0], seg[1]);
+        for p in 0..points_per_segment {
+            let t = p as f32 / points_per_segment as f32;
+            path.push((x0 + t * (x1 - x0), y0 + t * (y1 - y0)));
+        }
+    }
+    path.extend(corners.last());
+    path
+}
+
+/// Cumulative distance along a k path
+fn path_positions(path: &[(f32, f32)]) -> Vec<f32> {
+    let mut positions = Vec::with_capacity(path.len());
+    let mut total = 0.0;
+    for (i, &(kx, ky)) in path.iter().enumerate() {
+        if i > 0 {
+            let (px, py) = path[i - 1];
+            total += ((kx - px).powi(2) + (ky - py).powi(2)).sqrt();
+        }
+        positions.push(total);
+    }
+    positions
+}
+
+/// Xorshift32 pseudo-random generator (deterministic, no dependencies)
+fn xorshift(state: &mut u32) -> u32 {
+    let mut x = *state;
+    x ^= x << 13;
+    x ^= x >> 17;
+    x ^= x << 5;
+    *state = x;
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_k_path() {
+        let path = k_path(&SQUARE_PATH, 4);
+        assert_eq!(path.len(), 13);
+        assert_eq!(path[4], (0.5, 0.0)); // X
+        assert_eq!(path[8], (0.5, 0.5)); // M
+
+        let positions = path_positions(&path);
+        assert!((positions[12] - (1.0 + std::f32::consts::FRAC_1_SQRT_2)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_checked_driver() {
+        assert!(BandStructure::try_new(2, 4, 1024).is_err());
+        assert!(BandStructure::try_new(16, 0, 1024).is_err());
+        assert!(BandStructure::try_new(16, 4, 32).is_err());
+        assert!(BandStructure::try_new(16, 4, 1024).is_ok());
+    }
+
+    #[test]
+    fn test_empty_lattice_bands() {
+        // In vacuum the lowest band is the light line |k|: 0.5 at X, 0.707 at M
+        let mut bands = BandStructure::new(16, 2, 2048);
+        bands.compute_with(|_| {});
+        assert_eq!(bands.get_k_count(), 7);
+
+        let lowest = |k: usize| bands.get_frequencies(k)[0];
+        assert!((lowest(2) - 0.5).abs() < 0.03, "X: {}", lowest(2));
+        assert!((lowest(4) - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.03);
+        assert!((lowest(1) - 0.25).abs() < 0.03);
+    }
+
+    #[test]
+    fn test_rectangular_cell_bands() {
+        // Vacuum in an a × 2a cell: light line |k| at X (0.5), S (0.559) and Y (0.25)
+        let mut bands = BandStructure::new(16, 2, 2048);
+        bands.run(16, 32, &RECTANGULAR_PATH, |_| {});
+        assert_eq!(bands.get_k_count(), 9);
+        assert_eq!(
+            bands.get_symmetry_positions(),
+            vec![0.0, 0.5, 0.75, 1.25, 1.5]
+        );
+        let lowest = |k: usize| bands.get_frequencies(k)[0];
+        assert!((lowest(2) - 0.5).abs() < 0.03, "X: {}", lowest(2));
+        assert!((lowest(4) - 0.559).abs() < 0.03, "S: {}", lowest(4));
+        assert!((lowest(6) - 0.25).abs() < 0.03, "Y: {}", lowest(6));
+
+        // The crystal preset slows the lowest band below the light line
+        bands.compute();
+        let x_point = bands.get_frequencies(2)[0];
+        assert!(x_point > 0.25 && x_point < 0.48, "X: {}", x_point);
+    }
+
+    #[test]
+    fn test_dielectric_scales_bands() {
+        // Uniform ε = 4 halves every frequency
+        let mut bands = BandStructure::new(16, 1, 2048);
+        bands.compute_with(|grid| grid.set_material_region(0, 0, 15, 15, 4.0, 0.0));
+
+        let x_point = bands.get_frequencies(1);
+        assert!((x_point[0] - 0.25).abs() < 0.02, "X: {:?}", x_point);
+        assert_eq!(bands.get_band_dataset().len() % 2, 0);
+    }
+}
@@ -0,0 +1,418 @@
+//! PhotonLab Batch Runner
+//!
+//! Runs a JSON scene file natively for its configured number of steps, or
+//! until the field energy has decayed (`run.decay`), and writes into the
+//! output directory:
+//! - `probes.csv`: field at every probe per step
+//! - `spectra.csv`: magnitude spectrum of every probe (dB)
+//! - `dft_<frequency>_amplitude.csv` / `_phase.csv`: DFT field maps
+//! - `field_final.csv`: out-of-plane field (Ez or Hz) after the last step
+//! - `materials.png`: material map (see `export_material_png`)
+//! - `scene.json` and `summary.json`: the scene as run and the run summary
+//!
+//! Field maps are CSV matrices with one row per y.
+
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use photonlab_core::{FDTDGrid, FieldDft, SceneFile, SpectrumAnalyzer};
+
+const USAGE: &str = "\
+Usage: photonlab <scene.json> [options]
+
+Options:
+  -o, --output <dir>    Output directory (default: <scene>_out next to the scene)
+  -n, --steps <n>       Maximum number of steps (overrides run.steps)
+      --decay <x>       Stop once the field energy falls below x times its peak
+                        (overrides run.decay)
+      --dft <f>         Also record a DFT field map at frequency f in cycles
+                        per step (repeatable)
+  -q, --quiet           No progress output
+  -h, --help            Show this help";
+
+/// Command-line options
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    scene: PathBuf,
+    output: Option<PathBuf>,
+    steps: Option<u64>,
+    decay: Option<f32>,
+    dft: Vec<f32>,
+    quiet: bool,
+}
+
+/// Outcome of a run
+#[derive(Debug)]
+struct Summary {
+    steps: u64,
+    converged: bool,
+    peak_energy: f32,
+    final_energy: f32,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let result = parse_args(&args).and_then(|options| run(&options));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("photonlab: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut scene = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("{} needs a value\n\n{}", name, USAGE))
+        };
+        match arg.as_str() {
+            "-o" | "--output" => options.output = Some(PathBuf::from(value(arg)?)),
+            "-n" | "--steps" => options.steps = Some(parse_number(arg, value(arg)?)?),
+            "--decay" => options.decay = Some(parse_number(arg, value(arg)?)?),
+            "--dft" => options.dft.push(parse_number(arg, value(arg)?)?),
+            "-q" | "--quiet" => options.quiet = true,
+            _ if arg.starts_with('-') => {
+                return Err(format!("unknown option '{}'\n\n{}", arg, USAGE));
+            }
+            _ if scene.is_none() => scene = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+    options.scene = scene.ok_or_else(|| format!("no scene file given\n\n{}", USAGE))?;
+    Ok(options)
+}
+
+fn parse_number<T: std::str::FromStr>(option: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for {}", value, option))
+}
+
+/// Load the scene, run it and write every output file
+fn run(options: &Options) -> Result<(), String> {
+    let json = fs::read_to_string(&options.scene)
+        .map_err(|e| format!("cannot read {}: {}", options.scene.display(), e))?;
+    let mut scene = SceneFile::from_json(&json)?;
+    if let Some(steps) = options.steps {
+        scene.setup.run.steps = steps;
+    }
+    if options.decay.is_some() {
+        scene.setup.run.decay = options.decay;
+    }
+    scene.setup.run.dft_frequencies.extend(&options.dft);
+    let mut grid = FDTDGrid::from_scene(&scene)?;
+
+    let output = options.output.clone().unwrap_or_else(|| {
+        let stem = options.scene.file_stem().unwrap_or_default();
+        let mut name = stem.to_os_string();
+        name.push("_out");
+        options.scene.with_file_name(name)
+    });
+    fs::create_dir_all(&output)
+        .map_err(|e| format!("cannot create {}: {}", output.display(), e))?;
+
+    let mut dfts: Vec<FieldDft> = scene
+        .setup
+        .run
+        .dft_frequencies
+        .iter()
+        .map(|&f| FieldDft::new(f, grid.ez().len()))
+        .collect();
+    let mut series: Vec<Vec<f32>> = vec![Vec::new(); scene.setup.probes.len()];
+    let summary = simulate(&mut grid, &mut dfts, &mut series, options.quiet)?;
+
+    write_outputs(&output, &grid, &dfts, &series, &summary)?;
+    if !options.quiet {
+        eprintln!(
+            "{} steps{}, results in {}",
+            summary.steps,
+            if summary.converged {
+                " (converged)"
+            } else {
+                ""
+            },
+            output.display()
+        );
+    }
+    Ok(())
+}
+
+/// Step the grid, recording probes and DFT maps, until the step limit or
+/// the decay criterion is reached
+fn simulate(
+    grid: &mut FDTDGrid,
+    dfts: &mut [FieldDft],
+    series: &mut [Vec<f32>],
+    quiet: bool,
+) -> Result<Summary, String> {
+    let run = grid.scene_setup().run.clone();
+    let interval = run.check_interval.max(1);
+    let mut summary = Summary {
+        steps: 0,
+        converged: false,
+        peak_energy: 0.0,
+        final_energy: 0.0,
+    };
+
+    while summary.steps < run.steps {
+        grid.step_scene(1);
+        summary.steps += 1;
+        for (samples, value) in series.iter_mut().zip(grid.get_probe_values()) {
+            samples.push(value);
+        }
+        let step = grid.get_time_step();
+        for dft in dfts.iter_mut() {
+            dft.accumulate(grid.ez(), step);
+        }
+
+        if summary.steps.is_multiple_of(interval) || summary.steps == run.steps {
+            if !grid.is_stable() {
+                return Err(format!("simulation diverged at step {}", summary.steps));
+            }
+            let energy = grid.get_total_energy();
+            summary.peak_energy = summary.peak_energy.max(energy);
+            if !quiet {
+                eprint!(
+                    "\rstep {}/{}  energy {:.4e}",
+                    summary.steps, run.steps, energy
+                );
+            }
+            if let Some(decay) = run.decay {
+                if summary.peak_energy > 0.0 && energy <= decay * summary.peak_energy {
+                    summary.converged = true;
+                    break;
+                }
+            }
+        }
+    }
+    if !quiet {
+        eprintln!();
+    }
+    summary.final_energy = grid.get_total_energy();
+    Ok(summary)
+}
+
+fn write_outputs(
+    output: &Path,
+    grid: &FDTDGrid,
+    dfts: &[FieldDft],
+    series: &[Vec<f32>],
+    summary: &Summary,
+) -> Result<(), String> {
+    let setup = grid.scene_setup();
+    let names: Vec<String> = setup
+        .probes
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            if p.name.is_empty() {
+                format!("probe_{}", i + 1)
+            } else {
+                p.name.clone()
+            }
+        })
+        .collect();
+    let units = grid.get_units();
+
+    if !series.is_empty() {
+        write_file(&output.join("probes.csv"), |out| {
+            writeln!(out, "step,time_s,{}", names.join(","))?;
+            for step in 0..summary.steps as usize {
+                let time = units.steps_to_seconds((step + 1) as f64);
+                write!(out, "{},{:e}", step + 1, time)?;
+                for samples in series {
+                    write!(out, ",{}", samples[step])?;
+                }
+                writeln!(out)?;
+            }
+            Ok(())
+        })?;
+
+        let mut analyzer = SpectrumAnalyzer::new(summary.steps.max(2) as usize);
+        let spectra: Vec<Vec<f32>> = series.iter().map(|s| analyzer.compute(s)).collect();
+        write_file(&output.join("spectra.csv"), |out| {
+            writeln!(out, "frequency,frequency_hz,{}", names.join(","))?;
+            for bin in 0..analyzer.get_spectrum_size() {
+                let frequency = analyzer.bin_to_frequency(bin);
+                write!(out, "{},{:e}", frequency, analyzer.bin_to_hz(bin, &units))?;
+                for spectrum in &spectra {
+                    write!(out, ",{}", spectrum[bin])?;
+                }
+                writeln!(out)?;
+            }
+            Ok(())
+        })?;
+    }
+
+    let width = grid.get_width();
+    for dft in dfts {
+        let name = format!("dft_{}", dft.get_frequency());
+        write_map(
+            &output.join(format!("{}_amplitude.csv", name)),
+            &dft.get_amplitude(),
+            width,
+        )?;
+        write_map(
+            &output.join(format!("{}_phase.csv", name)),
+            &dft.get_phase(),
+            width,
+        )?;
+    }
+    write_map(&output.join("field_final.csv"), grid.ez(), width)?;
+
+    let png = grid.export_material_png()?;
+    fs::write(output.join("materials.png"), png)
+        .map_err(|e| format!("cannot write materials.png: {}", e))?;
+    write_file(&output.join("scene.json"), |out| {
+        writeln!(out, "{}", grid.to_scene_json())
+    })?;
+
+    let report = serde_json::json!({
+        "steps": summary.steps,
+        "converged": summary.converged,
+        "time_s": grid.get_time_seconds(),
+        "dt_s": grid.get_dt_seconds(),
+        "cell_size_m": grid.get_cell_size(),
+        "peak_energy": summary.peak_energy,
+        "final_energy": summary.final_energy,
+        "probes": names,
+        "dft_frequencies": dfts.iter().map(FieldDft::get_frequency).collect::<Vec<_>>(),
+    });
+    write_file(&output.join("summary.json"), |out| {
+        writeln!(out, "{:#}", report)
+    })
+}
+
+/// Field map as CSV, one row per y
+fn write_map(path: &Path, values: &[f32], width: usize) -> Result<(), String> {
+    write_file(path, |out| {
+        for row in values.chunks(width) {
+            let line: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+            writeln!(out, "{}", line.join(","))?;
+        }
+        Ok(())
+    })
+}
+
+fn write_file(
+    path: &Path,
+    contents: impl FnOnce(&mut BufWriter<fs::File>) -> std::io::Result<()>,
+) -> Result<(), String> {
+    let fail = |e: std::io::Error| format!("cannot write {}: {}", path.display(), e);
+    let mut out = BufWriter::new(fs::File::create(path).map_err(fail)?);
+    contents(&mut out).map_err(fail)?;
+    out.flush().map_err(fail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse_args(&args(&[
+            "cavity.json",
+            "-n",
+            "500",
+            "--dft",
+            "0.05",
+            "--dft",
+            "0.1",
+            "-o",
+            "out",
+            "-q",
+        ]))
+        .unwrap();
+        assert_eq!(options.scene, PathBuf::from("cavity.json"));
+        assert_eq!(options.steps, Some(500));
+        assert_eq!(options.dft, vec![0.05, 0.1]);
+        assert_eq!(options.output, Some(PathBuf::from("out")));
+        assert!(options.quiet);
+
+        assert!(parse_args(&args(&[])).is_err());
+        assert!(parse_args(&args(&["a.json", "--steps"])).is_err());
+        assert!(parse_args(&args(&["a.json", "--steps", "many"])).is_err());
+        assert!(parse_args(&args(&["a.json", "--fast"])).is_err());
+    }
+
+    #[test]
+    fn test_run_writes_outputs() {
+        let dir = std::env::temp_dir().join(format!("photonlab-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let scene = dir.join("pulse.json");
+        fs::write(
+            &scene,
+            r#"{
+                "version": 1,
+                "grid": {"width": 40, "height": 30},
+                "boundaries": {
+                    "left": {"type": "cpml"}, "right": {"type": "cpml"},
+                    "bottom": {"type": "cpml"}, "top": {"type": "cpml"}
+                },
+                "shapes": [{"shape": {"type": "rect", "cx": 30, "cy": 15, "width": 4, "height": 10},
+                            "material": 1}],
+                "sources": [{"type": "point", "x": 10, "y": 15,
+                             "waveform": {"type": "gaussian", "t0": 15, "tau": 5}}],
+                "probes": [{"name": "center", "x": 20, "y": 15}],
+                "run": {"steps": 2000, "decay": 1e-3, "check_interval": 20}
+            }"#,
+        )
+        .unwrap();
+
+        let options = Options {
+            scene,
+            output: Some(dir.join("out")),
+            dft: vec![0.05],
+            quiet: true,
+            ..Options::default()
+        };
+        run(&options).unwrap();
+
+        let out = dir.join("out");
+        let summary: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(out.join("summary.json")).unwrap()).unwrap();
+        assert_eq!(summary["converged"], true);
+        let steps = summary["steps"].as_u64().unwrap();
+        assert!(steps < 2000 && steps.is_multiple_of(20));
+
+        let probes = fs::read_to_string(out.join("probes.csv")).unwrap();
+        assert!(probes.starts_with("step,time_s,center\n"));
+        assert_eq!(probes.lines().count() as u64, steps + 1);
+        assert!(fs::read_to_string(out.join("spectra.csv")).is_ok());
+
+        let field = fs::read_to_string(out.join("field_final.csv")).unwrap();
+        assert_eq!(field.lines().count(), 30);
+        assert_eq!(field.lines().next().unwrap().split(',').count(), 40);
+        assert!(out.join("dft_0.05_amplitude.csv").exists());
+        assert!(out.join("dft_0.05_phase.csv").exists());
+        assert!(out.join("materials.png").exists());
+
+        let saved = fs::read_to_string(out.join("scene.json")).unwrap();
+        assert_eq!(
+            SceneFile::from_json(&saved)
+                .unwrap()
+                .setup
+                .run
+                .dft_frequencies,
+            vec![0.05]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,333 @@
+//! Bitmap Masks of the Material Map
+//!
+//! Imports PNG and PGM images where each colour or gray level stands for a
+//! material, and exports the material map as an indexed image:
+//! - PNG with one palette entry per material ID (16-bit grayscale holding
+//!   the ID when more than 256 materials are in use), with the material
+//!   table stored in an iTXt chunk
+//! - PGM whose gray levels are the material IDs
+//!
+//! Pixel values are looked up through `ImportOptions` layer keys: colour
+//! images use "#rrggbb", grayscale images the decimal gray level ("128").
+//! Fully transparent pixels leave the grid untouched. Exported images hold
+//! the dominant material of interface cells.
+
+use std::io::Cursor;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Keyword of the PNG text chunk listing the exported materials
+pub const MATERIALS_KEYWORD: &str = "Materials";
+
+/// Decoded image with one value per pixel
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bitmap {
+    pub width: usize,
+    pub height: usize,
+    /// Gray level or 0xRRGGBB colour; None for transparent pixels
+    pub pixels: Vec<Option<u32>>,
+    pub gray: bool,
+}
+
+impl Bitmap {
+    /// Decode a PNG or PGM (P2/P5) image, detected from its signature
+    pub fn decode(data: &[u8]) -> Result<Bitmap, String> {
+        if data.starts_with(b"\x89PNG") {
+            decode_png(data)
+        } else if data.starts_with(b"P2") || data.starts_with(b"P5") {
+            decode_pgm(data)
+        } else {
+            Err("unsupported image (PNG and PGM are read)".to_string())
+        }
+    }
+
+    /// Layer key of a pixel value
+    pub fn key(&self, value: u32) -> String {
+        if self.gray {
+            value.to_string()
+        } else {
+            format!("#{:06x}", value)
+        }
+    }
+
+    /// Pixel at image coordinates (x, y), None outside or transparent
+    pub fn pixel(&self, x: f32, y: f32) -> Option<u32> {
+        let (x, y) = (x.floor(), y.floor());
+        if x < 0.0 || y < 0.0 || x >= self.width as f32 || y >= self.height as f32 {
+            return None;
+        }
+        self.pixels[y as usize * self.width + x as usize]
+    }
+
+    /// Distinct layer keys in order of first appearance
+    pub fn keys(&self) -> Vec<String> {
+        let mut values: Vec<u32> = Vec::new();
+        for value in self.pixels.iter().flatten() {
+            if !values.contains(value) {
+                values.push(*value);
+            }
+        }
+        values.into_iter().map(|v| self.key(v)).collect()
+    }
+}
+
+/// Get the colours (or gray levels) of a PNG/PGM mask as import layer keys
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn get_image_colors(data: &[u8]) -> Result<Vec<String>, String> {
+    Bitmap::decode(data).map(|bitmap| bitmap.keys())
+}
+
+fn decode_png(data: &[u8]) -> Result<Bitmap, String> {
+    let invalid = |e: png::DecodingError| format!("invalid PNG: {}", e);
+    let mut decoder = png::Decoder::new(Cursor::new(data));
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(invalid)?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer).map_err(invalid)?;
+    let (width, height) = (frame.width as usize, frame.height as usize);
+
+    let channels = frame.color_type.samples();
+    let wide = frame.bit_depth == png::BitDepth::Sixteen;
+    let bytes = if wide { 2 } else { 1 };
+    // Sub-byte gray depths are expanded to 8 bits
+    let sample = |row: &[u8], x: usize, c: usize| -> u32 {
+        let i = (x * channels + c) * bytes;
+        if wide {
+            u16::from_be_bytes([row[i], row[i + 1]]) as u32
+        } else {
+            row[i] as u32
+        }
+    };
+    let max = if wide { 0xffff } else { 0xff };
+    let gray = matches!(
+        frame.color_type,
+        png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha
+    );
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in buffer[..frame.buffer_size()].chunks_exact(frame.line_size) {
+        for x in 0..width {
+            let alpha = match channels {
+                2 => sample(row, x, 1),
+                4 => sample(row, x, 3),
+                _ => max,
+            };
+            let value = if gray {
+                sample(row, x, 0)
+            } else {
+                // Colours compare at 8 bits per channel
+                let channel = |c| {
+                    if wide {
+                        sample(row, x, c) >> 8
+                    } else {
+                        sample(row, x, c)
+                    }
+                };
+                (channel(0) << 16) | (channel(1) << 8) | channel(2)
+            };
+            pixels.push((alpha > 0).then_some(value));
+        }
+    }
+    Ok(Bitmap {
+        width,
+        height,
+        pixels,
+        gray,
+    })
+}
+
+fn decode_pgm(data: &[u8]) -> Result<Bitmap, String> {
+    let binary = data.starts_with(b"P5");
+    let mut position = 2;
+    let mut header = [0usize; 3];
+    for value in header.iter_mut() {
+        *value = pnm_number(data, &mut position).ok_or_else(|| "invalid PGM header".to_string())?;
+    }
+    let [width, height, max] = header;
+    if max == 0 || max > 0xffff {
+        return Err(format!("invalid PGM maximum gray value {}", max));
+    }
+    let count = width
+        .checked_mul(height)
+        .ok_or_else(|| "PGM image too large".to_string())?;
+
+    let values: Vec<u32> = if binary {
+        // Exactly one whitespace byte separates the header from the data
+        let start = position + 1;
+        let bytes = if max > 0xff { 2 } else { 1 };
+        let end = count
+            .checked_mul(bytes)
+            .and_then(|len| len.checked_add(start))
+            .ok_or_else(|| "PGM image too large".to_string())?;
+        let raster = data
+            .get(start..end)
+            .ok_or_else(|| "truncated PGM data".to_string())?;
+        raster
+            .chunks_exact(bytes)
+            .map(|b| b.iter().fold(0u32, |v, &b| (v << 8) | b as u32))
+            .collect()
+    } else {
+        (0..count)
+            .map(|_| pnm_number(data, &mut position).map(|v| v as u32))
+            .collect::<Option<_>>()
+            .ok_or_else(|| "truncated PGM data".to_string())?
+    };
+    Ok(Bitmap {
+        width,
+        height,
+        pixels: values.into_iter().map(Some).collect(),
+        gray: true,
+    })
+}
+
+/// Next decimal number of a PNM header or ASCII raster (skips '#' comments)
+fn pnm_number(data: &[u8], position: &mut usize) -> Option<usize> {
+    loop {
+        match data.get(*position)? {
+            b'#' => {
+                while data.get(*position).is_some_and(|&b| b != b'\n') {
+                    *position += 1;
+                }
+            }
+            b if b.is_ascii_whitespace() => *position += 1,
+            _ => break,
+        }
+    }
+    let start = *position;
+    while data.get(*position).is_some_and(u8::is_ascii_digit) {
+        *position += 1;
+    }
+    std::str::from_utf8(&data[start..*position])
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Display colour of a material in exported palettes (vacuum is black)
+pub fn material_color(id: u32) -> [u8; 3] {
+    if id == 0 {
+        return [0, 0, 0];
+    }
+    // Golden-angle hue steps keep neighbouring IDs apart
+    let hue = (id as f32 * 137.507_77).rem_euclid(360.0) / 60.0;
+    let (saturation, value) = (0.65, 0.95);
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    [r, g, b].map(|c| ((c + m) * 255.0).round() as u8)
+}
+
+/// Indexed PNG of a material map (palette index = material ID), or 16-bit
+/// grayscale if an ID does not fit in a palette; `table` goes into an iTXt chunk
+pub fn encode_png(
+    width: usize,
+    height: usize,
+    ids: &[u32],
+    table: &str,
+) -> Result<Vec<u8>, String> {
+    let failed = |e: png::EncodingError| format!("PNG encoding failed: {}", e);
+    let max_id = ids.iter().copied().max().unwrap_or(0);
+    let mut out = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut out, width as u32, height as u32);
+        let data: Vec<u8> = if max_id < 256 {
+            let palette: Vec<u8> = (0..=max_id).flat_map(material_color).collect();
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_palette(palette);
+            ids.iter().map(|&id| id as u8).collect()
+        } else if max_id <= 0xffff {
+            encoder.set_color(png::ColorType::Grayscale);
+            encoder.set_depth(png::BitDepth::Sixteen);
+            ids.iter()
+                .flat_map(|&id| (id as u16).to_be_bytes())
+                .collect()
+        } else {
+            return Err(format!("material ID {} does not fit in an image", max_id));
+        };
+        encoder
+            .add_itxt_chunk(MATERIALS_KEYWORD.to_string(), table.to_string())
+            .map_err(failed)?;
+        let mut writer = encoder.write_header().map_err(failed)?;
+        writer.write_image_data(&data).map_err(failed)?;
+        writer.finish().map_err(failed)?;
+    }
+    Ok(out)
+}
+
+/// Binary PGM of a material map (gray level = material ID)
+pub fn encode_pgm(width: usize, height: usize, ids: &[u32]) -> Vec<u8> {
+    let max_id = ids.iter().copied().max().unwrap_or(0).clamp(255, 0xffff);
+    let mut out = format!("P5\n{} {}\n{}\n", width, height, max_id).into_bytes();
+    for &id in ids {
+        let id = id.min(0xffff);
+        if max_id > 0xff {
+            out.extend_from_slice(&(id as u16).to_be_bytes());
+        } else {
+            out.push(id as u8);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pgm_round_trip() {
+        let ids = [0, 1, 2, 3, 300, 0];
+        let pgm = encode_pgm(3, 2, &ids);
+        assert!(pgm.starts_with(b"P5\n3 2\n300\n"));
+        let bitmap = Bitmap::decode(&pgm).unwrap();
+        assert_eq!((bitmap.width, bitmap.height), (3, 2));
+        assert_eq!(bitmap.pixel(1.5, 1.2), Some(300));
+        assert_eq!(bitmap.keys(), vec!["0", "1", "2", "3", "300"]);
+        assert_eq!(bitmap.pixel(3.0, 0.0), None);
+
+        let ascii = b"P2\n# comment\n2 2\n15\n0 15\n7 3\n";
+        let bitmap = Bitmap::decode(ascii).unwrap();
+        assert_eq!(bitmap.pixels, vec![Some(0), Some(15), Some(7), Some(3)]);
+        assert!(Bitmap::decode(b"P5\n4 4\n255\n\x00").is_err());
+        // 16-bit raster size overflows even though the pixel count does not
+        let half = usize::BITS / 2;
+        let header = format!(
+            "P5\n{} {}\n65535\n\0\0",
+            1usize << half,
+            1usize << (half - 1)
+        );
+        let huge = Bitmap::decode(header.as_bytes());
+        assert_eq!(huge.err().as_deref(), Some("PGM image too large"));
+        assert!(Bitmap::decode(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let ids = [0, 2, 2, 6];
+        let png = encode_png(2, 2, &ids, "2 Water").unwrap();
+        let bitmap = Bitmap::decode(&png).unwrap();
+        assert!(!bitmap.gray);
+        let color = |id| {
+            let [r, g, b] = material_color(id);
+            Some(u32::from_be_bytes([0, r, g, b]))
+        };
+        assert_eq!(bitmap.pixels, vec![color(0), color(2), color(2), color(6)]);
+        assert_eq!(bitmap.keys().len(), 3);
+
+        // Large IDs switch to 16-bit grayscale holding the IDs
+        let png = encode_png(2, 1, &[1, 1000], "").unwrap();
+        let bitmap = Bitmap::decode(&png).unwrap();
+        assert!(bitmap.gray);
+        assert_eq!(bitmap.pixels, vec![Some(1), Some(1000)]);
+        assert_ne!(material_color(1), material_color(2));
+    }
+}
@@ -0,0 +1,265 @@
+//! Boundary Condition Selection for FDTD Simulation
+//!
+//! Defines how the outer edges of the grid are treated:
+//! - Mur copy (first-order absorbing, cheap but leaky)
+//! - CPML (convolutional PML, strong absorption)
+//! - PEC wall (Ez = 0, perfect reflection)
+//! - PMC wall (tangential H = 0, in-phase reflection)
+//! - Periodic (field wraps around to the opposite side)
+//! - Bloch-periodic (wrap-around with a phase factor e^{ik·a})
+//!
+//! Each side of the grid is configured independently through `BoundarySpec`.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::cpml::CPMLParams;
+
+/// Boundary condition applied at a grid edge
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BoundaryType {
+    /// First-order absorbing boundary (copy of neighbouring cell)
+    Mur = 0,
+    /// Convolutional Perfectly Matched Layer (open boundary)
+    CPML = 1,
+    /// Perfect Electric Conductor wall
+    PEC = 2,
+    /// Perfect Magnetic Conductor wall
+    PMC = 3,
+    /// Periodic wrap-around (always applied to both opposite sides)
+    Periodic = 4,
+}
+
+impl BoundaryType {
+    /// Convert a numeric ID (from JavaScript) into a boundary type
+    /// Unknown IDs fall back to Mur
+    pub fn from_id(id: u8) -> BoundaryType {
+        match id {
+            1 => BoundaryType::CPML,
+            2 => BoundaryType::PEC,
+            3 => BoundaryType::PMC,
+            4 => BoundaryType::Periodic,
+            _ => BoundaryType::Mur,
+        }
+    }
+}
+
+/// Grid edge identifier
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoundarySide {
+    /// x = 0
+    Left = 0,
+    /// x = width - 1
+    Right = 1,
+    /// y = 0
+    Bottom = 2,
+    /// y = height - 1
+    Top = 3,
+}
+
+impl BoundarySide {
+    /// All four sides in storage order
+    pub const ALL: [BoundarySide; 4] = [
+        BoundarySide::Left,
+        BoundarySide::Right,
+        BoundarySide::Bottom,
+        BoundarySide::Top,
+    ];
+
+    /// The side across the grid from this one
+    pub fn opposite(self) -> BoundarySide {
+        match self {
+            BoundarySide::Left => BoundarySide::Right,
+            BoundarySide::Right => BoundarySide::Left,
+            BoundarySide::Bottom => BoundarySide::Top,
+            BoundarySide::Top => BoundarySide::Bottom,
+        }
+    }
+}
+
+/// Per-side boundary configuration
+///
+/// Periodic boundaries come in pairs: making one side periodic makes the
+/// opposite side periodic too, and replacing one side of a periodic pair
+/// gives the opposite side the same replacement.
+///
+/// A periodic axis may carry a Bloch phase k·a (radians): the field on
+/// one side equals the field on the other times e^{ik·a}.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundarySpec {
+    types: [BoundaryType; 4],
+    cpml: [CPMLParams; 4],
+    bloch_phase_x: f32,
+    bloch_phase_y: f32,
+}
+
+impl Default for BoundarySpec {
+    fn default() -> Self {
+        BoundarySpec::uniform(BoundaryType::Mur)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl BoundarySpec {
+    /// Create a spec with Mur ABC on every side
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new() -> BoundarySpec {
+        BoundarySpec::default()
+    }
+
+    /// Create a spec with the same boundary type on every side
+    /// CPML sides use the default layer parameters
+    pub fn uniform(boundary: BoundaryType) -> BoundarySpec {
+        BoundarySpec {
+            types: [boundary; 4],
+            cpml: [CPMLParams::default(); 4],
+            bloch_phase_x: 0.0,
+            bloch_phase_y: 0.0,
+        }
+    }
+
+    /// Set the boundary type of one side
+    pub fn set_side(&mut self, side: BoundarySide, boundary: BoundaryType) {
+        let opposite = side.opposite() as usize;
+        if boundary == BoundaryType::Periodic || self.types[opposite] == BoundaryType::Periodic {
+            self.types[opposite] = boundary;
+        }
+        self.types[side as usize] = boundary;
+    }
+
+    /// Get the boundary type of one side
+    pub fn get_side(&self, side: BoundarySide) -> BoundaryType {
+        self.types[side as usize]
+    }
+
+    /// Make one side an open CPML boundary with custom layer parameters
+    pub fn set_cpml(&mut self, side: BoundarySide, params: CPMLParams) {
+        self.set_side(side, BoundaryType::CPML);
+        self.cpml[side as usize] = params;
+    }
+
+    /// Get the CPML layer parameters of one side
+    /// (only used while the side is a CPML boundary)
+    pub fn get_cpml(&self, side: BoundarySide) -> CPMLParams {
+        self.cpml[side as usize]
+    }
+
+    /// Check whether the x axis wraps around (left and right periodic)
+    pub fn is_periodic_x(&self) -> bool {
+        self.types[BoundarySide::Left as usize] == BoundaryType::Periodic
+    }
+
+    /// Check whether the y axis wraps around (bottom and top periodic)
+    pub fn is_periodic_y(&self) -> bool {
+        self.types[BoundarySide::Bottom as usize] == BoundaryType::Periodic
+    }
+
+    /// Make the x axis Bloch-periodic with phase kx·a (radians)
+    pub fn set_bloch_x(&mut self, phase: f32) {
+        self.set_side(BoundarySide::Left, BoundaryType::Periodic);
+        self.bloch_phase_x = phase;
+    }
+
+    /// Make the y axis Bloch-periodic with phase ky·a (radians)
+    pub fn set_bloch_y(&mut self, phase: f32) {
+        self.set_side(BoundarySide::Bottom, BoundaryType::Periodic);
+        self.bloch_phase_y = phase;
+    }
+
+    /// Get the Bloch phase across the x axis (0 unless x is periodic)
+    pub fn get_bloch_x(&self) -> f32 {
+        if self.is_periodic_x() {
+            self.bloch_phase_x
+        } else {
+            0.0
+        }
+    }
+
+    /// Get the Bloch phase across the y axis (0 unless y is periodic)
+    pub fn get_bloch_y(&self) -> f32 {
+        if self.is_periodic_y() {
+            self.bloch_phase_y
+        } else {
+            0.0
+        }
+    }
+
+    /// Check whether any periodic axis carries a non-zero Bloch phase
+    /// (the fields are then complex-valued)
+    pub fn is_bloch(&self) -> bool {
+        self.get_bloch_x() != 0.0 || self.get_bloch_y() != 0.0
+    }
+}
+
+/// Get boundary name by ID
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn get_boundary_name(id: u8) -> String {
+    match id {
+        0 => "Mur ABC".to_string(),
+        1 => "CPML".to_string(),
+        2 => "PEC Wall".to_string(),
+        3 => "PMC Wall".to_string(),
+        4 => "Periodic".to_string(),
+        _ => "Unknown".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boundary_from_id() {
+        assert_eq!(BoundaryType::from_id(1), BoundaryType::CPML);
+        assert_eq!(BoundaryType::from_id(3), BoundaryType::PMC);
+        assert_eq!(BoundaryType::from_id(42), BoundaryType::Mur);
+    }
+
+    #[test]
+    fn test_periodic_sides_pair_up() {
+        let mut spec = BoundarySpec::new();
+        spec.set_side(BoundarySide::Top, BoundaryType::Periodic);
+        assert_eq!(spec.get_side(BoundarySide::Bottom), BoundaryType::Periodic);
+        assert!(spec.is_periodic_y());
+        assert!(!spec.is_periodic_x());
+
+        spec.set_side(BoundarySide::Bottom, BoundaryType::PEC);
+        assert_eq!(spec.get_side(BoundarySide::Top), BoundaryType::PEC);
+        assert!(!spec.is_periodic_y());
+    }
+
+    #[test]
+    fn test_per_side_cpml_params() {
+        let mut spec = BoundarySpec::uniform(BoundaryType::PEC);
+        spec.set_cpml(
+            BoundarySide::Right,
+            CPMLParams::new(12, 4.0, 1.0, 3.0, 0.02),
+        );
+        assert_eq!(spec.get_side(BoundarySide::Right), BoundaryType::CPML);
+        assert_eq!(spec.get_side(BoundarySide::Left), BoundaryType::PEC);
+        assert_eq!(spec.get_cpml(BoundarySide::Right).thickness, 12);
+    }
+
+    #[test]
+    fn test_bloch_phase() {
+        let mut spec = BoundarySpec::uniform(BoundaryType::CPML);
+        assert!(!spec.is_bloch());
+
+        spec.set_bloch_x(0.5);
+        assert!(spec.is_periodic_x());
+        assert!(spec.is_bloch());
+        assert_eq!(spec.get_bloch_y(), 0.0);
+
+        // Phase is ignored once the axis is no longer periodic
+        spec.set_side(BoundarySide::Right, BoundaryType::PEC);
+        assert_eq!(spec.get_bloch_x(), 0.0);
+        assert!(!spec.is_bloch());
+    }
+}
@@ -4,57 +4,173 @@
 //! wave reflections at the simulation edges. Uses stretched coordinate
 //! formulation with auxiliary differential equation (ADE) method.
 //!
+//! Each side carries its own layer (thickness and grading profile), so
+//! open and closed edges can be mixed freely.
+//!
 //! Reference: Roden & Gedney (2000) - Convolution PML (CPML)
 
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-/// CPML boundary thickness in cells
+use crate::boundary::{BoundarySide, BoundarySpec, BoundaryType};
+
+/// Default CPML boundary thickness in cells
 pub const CPML_THICKNESS: usize = 20;
 
-/// Maximum polynomial grading order
+/// Default polynomial grading order
 const CPML_ORDER: f32 = 3.0;
 
-/// Maximum sigma value for CPML (normalized)
+/// Default maximum sigma value for CPML (normalized)
 const SIGMA_MAX: f32 = 0.75;
 
-/// Maximum alpha value for CPML (for evanescent wave absorption)
+/// Default maximum alpha value for CPML (for evanescent wave absorption)
 const ALPHA_MAX: f32 = 0.05;
 
-/// Maximum kappa value (coordinate stretching factor)
-const KAPPA_MAX: f32 = 15.0;
+/// Default maximum kappa value (coordinate stretching factor)
+/// With the 20-cell cubic profile, κ_max = 15 reflects ~4% of the peak of
+/// a pulse launched 30 cells in front of the layer; κ_max = 5 under 0.1%.
+const KAPPA_MAX: f32 = 5.0;
+
+/// CPML layer thickness and grading profile for one side
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CPMLParams {
+    /// Layer thickness in cells (clamped to a quarter of the grid extent)
+    pub thickness: usize,
+    /// Polynomial grading order m (typical: 3-4)
+    pub order: f32,
+    /// Conductivity at the outer edge (normalized)
+    pub sigma_max: f32,
+    /// Coordinate stretching at the outer edge (>= 1)
+    pub kappa_max: f32,
+    /// Complex frequency shift at the inner interface (>= 0)
+    pub alpha_max: f32,
+}
+
+impl Default for CPMLParams {
+    fn default() -> Self {
+        CPMLParams {
+            thickness: CPML_THICKNESS,
+            order: CPML_ORDER,
+            sigma_max: SIGMA_MAX,
+            kappa_max: KAPPA_MAX,
+            alpha_max: ALPHA_MAX,
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl CPMLParams {
+    /// Create a custom CPML profile
+    /// Out-of-range values are clamped (see `try_new`).
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(
+        thickness: usize,
+        order: f32,
+        sigma_max: f32,
+        kappa_max: f32,
+        alpha_max: f32,
+    ) -> CPMLParams {
+        CPMLParams {
+            thickness: thickness.max(1),
+            order: order.max(0.0),
+            sigma_max: sigma_max.max(0.0),
+            kappa_max: kappa_max.max(1.0),
+            alpha_max: alpha_max.max(0.0),
+        }
+    }
+
+    /// Get the default CPML profile
+    pub fn default_profile() -> CPMLParams {
+        CPMLParams::default()
+    }
+}
+
+impl CPMLParams {
+    /// Create a profile, rejecting the values `new` would clamp
+    pub fn try_new(
+        thickness: usize,
+        order: f32,
+        sigma_max: f32,
+        kappa_max: f32,
+        alpha_max: f32,
+    ) -> Result<CPMLParams, String> {
+        let params = CPMLParams {
+            thickness,
+            order,
+            sigma_max,
+            kappa_max,
+            alpha_max,
+        };
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Check that the layer is at least one cell thick, κ_max ≥ 1 and the
+    /// grading order, σ_max and α_max are non-negative
+    pub fn validate(&self) -> Result<(), String> {
+        let non_negative = |v: f32| v.is_finite() && v >= 0.0;
+        if self.thickness == 0 {
+            return Err("CPML thickness must be at least one cell".to_string());
+        }
+        if !(non_negative(self.order)
+            && non_negative(self.sigma_max)
+            && non_negative(self.alpha_max))
+        {
+            return Err(format!(
+                "CPML order {}, sigma_max {} and alpha_max {} must not be negative",
+                self.order, self.sigma_max, self.alpha_max
+            ));
+        }
+        if !(self.kappa_max.is_finite() && self.kappa_max >= 1.0) {
+            return Err(format!(
+                "CPML kappa_max {} must be at least 1",
+                self.kappa_max
+            ));
+        }
+        Ok(())
+    }
+}
 
 /// CPML coefficient set for one direction
 #[derive(Clone)]
 pub struct CPMLCoeffs {
     /// b coefficient for recursive convolution
     pub b: Vec<f32>,
-    /// c coefficient for recursive convolution  
+    /// c coefficient for recursive convolution
     pub c: Vec<f32>,
     /// Kappa (coordinate stretching)
     pub kappa: Vec<f32>,
 }
 
 impl CPMLCoeffs {
-    /// Create CPML coefficients for a given number of layers
-    /// `dt` is the time step, `thickness` is number of CPML cells
-    pub fn new(thickness: usize, dt: f32) -> Self {
+    /// Create CPML coefficients for a graded profile
+    /// Entry k is sampled at a distance of `k + offset` cells from the outer edge
+    /// (offset 0.0 for E nodes, 0.5 for the staggered H nodes)
+    pub fn graded(params: &CPMLParams, dt: f32, offset: f32) -> Self {
+        let thickness = params.thickness;
+        let span = thickness.saturating_sub(1).max(1) as f32;
+
         let mut b = vec![0.0; thickness];
         let mut c = vec![0.0; thickness];
         let mut kappa = vec![1.0; thickness];
 
         for i in 0..thickness {
-            // Polynomial grading from edge (i=0) to interior (i=thickness-1)
+            // Polynomial grading from edge (depth=1) to interior (depth=0)
             // At edge: maximum absorption, at interior: minimum
-            let depth = (thickness - 1 - i) as f32 / (thickness - 1) as f32;
+            let depth = ((span - (i as f32 + offset)) / span).clamp(0.0, 1.0);
+            let grade = depth.powf(params.order);
 
             // Graded sigma (conductivity-like parameter)
-            let sigma = SIGMA_MAX * depth.powf(CPML_ORDER);
+            let sigma = params.sigma_max * grade;
 
             // Graded kappa (coordinate stretching)
-            let k = 1.0 + (KAPPA_MAX - 1.0) * depth.powf(CPML_ORDER);
+            let k = 1.0 + (params.kappa_max - 1.0) * grade;
 
             // Graded alpha (for evanescent waves)
-            let alpha = ALPHA_MAX * (1.0 - depth);
+            let alpha = params.alpha_max * (1.0 - depth);
 
             // CPML recursive coefficients
             // b = exp(-(sigma/kappa + alpha) * dt)
@@ -76,294 +192,281 @@ impl CPMLCoeffs {
     }
 }
 
+/// Absorbing layer along one side of the grid
+///
+/// Layer index k counts cells inward from the outer edge.
+/// Psi arrays are laid out as [k][j] for left/right and [i][k] for bottom/top.
+struct CPMLLayer {
+    thickness: usize,
+    coeffs_e: CPMLCoeffs,
+    coeffs_h: CPMLCoeffs,
+    psi_e: Vec<f32>,
+    psi_h: Vec<f32>,
+}
+
+impl CPMLLayer {
+    fn new(params: &CPMLParams, extent: usize, length: usize, dt: f32) -> CPMLLayer {
+        let thickness = params.thickness.min(extent / 4).max(1);
+        let params = CPMLParams {
+            thickness,
+            ..*params
+        };
+
+        CPMLLayer {
+            thickness,
+            coeffs_e: CPMLCoeffs::graded(&params, dt, 0.0),
+            coeffs_h: CPMLCoeffs::graded(&params, dt, 0.5),
+            psi_e: vec![0.0; thickness * length],
+            psi_h: vec![0.0; thickness * length],
+        }
+    }
+
+    fn reset(&mut self) {
+        self.psi_e.fill(0.0);
+        self.psi_h.fill(0.0);
+    }
+}
+
 /// CPML boundary handler for 2D FDTD
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct CPML {
-    thickness: usize,
-    #[allow(dead_code)] // Reserved for future grid resize support
     width: usize,
     height: usize,
 
-    // Coefficients for x and y directions
-    coeffs_e: CPMLCoeffs, // For E-field updates
-    coeffs_h: CPMLCoeffs, // For H-field updates
-
-    // Auxiliary (psi) fields for E-field update
-    // Only stored in CPML regions to save memory
-    // Left boundary: psi_ezx_left[thickness][height]
-    // Right boundary: psi_ezx_right[thickness][height]
-    // Bottom boundary: psi_ezy_bottom[width][thickness]
-    // Top boundary: psi_ezy_top[width][thickness]
-    psi_ezx_left: Vec<f32>,
-    psi_ezx_right: Vec<f32>,
-    psi_ezy_bottom: Vec<f32>,
-    psi_ezy_top: Vec<f32>,
-
-    // Auxiliary fields for H-field update
-    psi_hxy_left: Vec<f32>,
-    psi_hxy_right: Vec<f32>,
-    psi_hyx_bottom: Vec<f32>,
-    psi_hyx_top: Vec<f32>,
+    // One optional layer per side, indexed by BoundarySide
+    layers: [Option<CPMLLayer>; 4],
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl CPML {
-    /// Create new CPML boundaries for a grid
-    #[wasm_bindgen(constructor)]
+    /// Create new CPML boundaries on all four sides of a grid
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new(width: usize, height: usize, dt: f32) -> CPML {
-        let thickness = CPML_THICKNESS.min(width / 4).min(height / 4);
-
-        let coeffs_e = CPMLCoeffs::new(thickness, dt);
-        let coeffs_h = CPMLCoeffs::new(thickness, dt);
-
-        // Allocate psi arrays for each boundary region
-        let psi_ezx_left = vec![0.0; thickness * height];
-        let psi_ezx_right = vec![0.0; thickness * height];
-        let psi_ezy_bottom = vec![0.0; width * thickness];
-        let psi_ezy_top = vec![0.0; width * thickness];
-
-        let psi_hxy_left = vec![0.0; thickness * height];
-        let psi_hxy_right = vec![0.0; thickness * height];
-        let psi_hyx_bottom = vec![0.0; width * thickness];
-        let psi_hyx_top = vec![0.0; width * thickness];
-
-        CPML {
-            thickness,
+        CPML::from_spec(
             width,
             height,
-            coeffs_e,
-            coeffs_h,
-            psi_ezx_left,
-            psi_ezx_right,
-            psi_ezy_bottom,
-            psi_ezy_top,
-            psi_hxy_left,
-            psi_hxy_right,
-            psi_hyx_bottom,
-            psi_hyx_top,
-        }
+            dt,
+            &BoundarySpec::uniform(BoundaryType::CPML),
+        )
     }
 
-    /// Get CPML thickness
-    pub fn get_thickness(&self) -> usize {
-        self.thickness
+    /// Get CPML thickness of one side (0 when that side has no layer)
+    pub fn get_thickness(&self, side: BoundarySide) -> usize {
+        self.layers[side as usize]
+            .as_ref()
+            .map_or(0, |layer| layer.thickness)
     }
 
     /// Reset all psi arrays to zero
     pub fn reset(&mut self) {
-        self.psi_ezx_left.fill(0.0);
-        self.psi_ezx_right.fill(0.0);
-        self.psi_ezy_bottom.fill(0.0);
-        self.psi_ezy_top.fill(0.0);
-        self.psi_hxy_left.fill(0.0);
-        self.psi_hxy_right.fill(0.0);
-        self.psi_hyx_bottom.fill(0.0);
-        self.psi_hyx_top.fill(0.0);
+        for layer in self.layers.iter_mut().flatten() {
+            layer.reset();
+        }
     }
 }
 
 impl CPML {
-    /// Update E-field in left CPML region
-    /// Returns the CPML correction to add to the standard update
-    pub fn update_ez_left(&mut self, ez: &mut [f32], hy: &[f32], cb: &[f32], w: usize) {
-        let t = self.thickness;
-        let h = self.height;
+    /// Create CPML layers for every side marked as CPML in the spec
+    pub fn from_spec(width: usize, height: usize, dt: f32, spec: &BoundarySpec) -> CPML {
+        let mut layers = [None, None, None, None];
 
-        for j in 1..h {
-            for i in 1..t {
-                let idx = j * w + i;
-                let psi_idx = (i - 1) * h + j;
-
-                // Layer index (0 at edge, t-1 at interior)
-                let layer = i;
-
-                // Get CPML coefficients
-                let b = self.coeffs_e.b[layer];
-                let c = self.coeffs_e.c[layer];
-                let kappa = self.coeffs_e.kappa[layer];
-
-                // dHy/dx term
-                let dhy_dx = hy[idx] - hy[idx - 1];
-
-                // Update psi (auxiliary field)
-                self.psi_ezx_left[psi_idx] = b * self.psi_ezx_left[psi_idx] + c * dhy_dx;
-
-                // Apply CPML correction
-                // Standard: Ez += cb * dHy/dx
-                // CPML: Ez += cb * (dHy/dx / kappa + psi)
-                ez[idx] += cb[idx] * (dhy_dx * (1.0 / kappa - 1.0) + self.psi_ezx_left[psi_idx]);
+        for side in BoundarySide::ALL {
+            if spec.get_side(side) != BoundaryType::CPML {
+                continue;
             }
+            let params = spec.get_cpml(side);
+            layers[side as usize] = Some(match side {
+                BoundarySide::Left | BoundarySide::Right => {
+                    CPMLLayer::new(&params, width, height, dt)
+                }
+                BoundarySide::Bottom | BoundarySide::Top => {
+                    CPMLLayer::new(&params, height, width, dt)
+                }
+            });
+        }
+
+        CPML {
+            width,
+            height,
+            layers,
         }
     }
 
-    /// Update E-field in right CPML region
-    pub fn update_ez_right(&mut self, ez: &mut [f32], hy: &[f32], cb: &[f32], w: usize) {
-        let t = self.thickness;
+    /// Apply CPML corrections to the node field (Ez) after the standard update
+    ///
+    /// `cb` holds the per-cell curl coefficients of the node update.
+    /// `i_start`/`j_start` are 0 when the first column/row of Ez is updated
+    /// by the solver (periodic or PMC edge), 1 otherwise.
+    pub fn update_e(
+        &mut self,
+        ez: &mut [f32],
+        hx: &[f32],
+        hy: &[f32],
+        cb: &[f32],
+        i_start: usize,
+        j_start: usize,
+    ) {
+        let w = self.width;
         let h = self.height;
 
-        for j in 1..h {
-            for i in 0..t {
-                let grid_i = w - t + i;
-                let idx = j * w + grid_i;
-                let psi_idx = i * h + j;
-
-                // Layer index (t-1 at interior, 0 at edge)
-                let layer = t - 1 - i;
+        // Left: dHy/dx term, Ez column i = k
+        if let Some(layer) = self.layers[BoundarySide::Left as usize].as_mut() {
+            for k in 1..layer.thickness {
+                let b = layer.coeffs_e.b[k];
+                let c = layer.coeffs_e.c[k];
+                let kappa = layer.coeffs_e.kappa[k];
 
-                let b = self.coeffs_e.b[layer];
-                let c = self.coeffs_e.c[layer];
-                let kappa = self.coeffs_e.kappa[layer];
+                for j in j_start..h {
+                    let idx = j * w + k;
+                    let psi = &mut layer.psi_e[k * h + j];
 
-                let dhy_dx = hy[idx] - hy[idx - 1];
+                    let dhy_dx = hy[idx] - hy[idx - 1];
+                    *psi = b * *psi + c * dhy_dx;
 
-                self.psi_ezx_right[psi_idx] = b * self.psi_ezx_right[psi_idx] + c * dhy_dx;
-                ez[idx] += cb[idx] * (dhy_dx * (1.0 / kappa - 1.0) + self.psi_ezx_right[psi_idx]);
+                    // Standard: Ez += cb * dHy/dx
+                    // CPML: Ez += cb * (dHy/dx / kappa + psi)
+                    ez[idx] += cb[idx] * (dhy_dx * (1.0 / kappa - 1.0) + *psi);
+                }
             }
         }
-    }
-
-    /// Update E-field in bottom CPML region
-    pub fn update_ez_bottom(&mut self, ez: &mut [f32], hx: &[f32], cb: &[f32], w: usize) {
-        let t = self.thickness;
 
-        for j in 1..t {
-            for i in 1..w {
-                let idx = j * w + i;
-                let psi_idx = i * t + (j - 1);
-
-                let layer = j;
-
-                let b = self.coeffs_e.b[layer];
-                let c = self.coeffs_e.c[layer];
-                let kappa = self.coeffs_e.kappa[layer];
-
-                // -dHx/dy term
-                let dhx_dy = hx[idx] - hx[idx - w];
-
-                self.psi_ezy_bottom[psi_idx] = b * self.psi_ezy_bottom[psi_idx] + c * dhx_dy;
-                ez[idx] -= cb[idx] * (dhx_dy * (1.0 / kappa - 1.0) + self.psi_ezy_bottom[psi_idx]);
+        // Right: dHy/dx term, Ez column i = w-1-k
+        if let Some(layer) = self.layers[BoundarySide::Right as usize].as_mut() {
+            for k in 0..layer.thickness {
+                let b = layer.coeffs_e.b[k];
+                let c = layer.coeffs_e.c[k];
+                let kappa = layer.coeffs_e.kappa[k];
+                let i = w - 1 - k;
+
+                for j in j_start..h {
+                    let idx = j * w + i;
+                    let psi = &mut layer.psi_e[k * h + j];
+
+                    let dhy_dx = hy[idx] - hy[idx - 1];
+                    *psi = b * *psi + c * dhy_dx;
+                    ez[idx] += cb[idx] * (dhy_dx * (1.0 / kappa - 1.0) + *psi);
+                }
             }
         }
-    }
-
-    /// Update E-field in top CPML region
-    pub fn update_ez_top(&mut self, ez: &mut [f32], hx: &[f32], cb: &[f32], w: usize) {
-        let t = self.thickness;
-        let h = self.height;
-
-        for j in 0..t {
-            let grid_j = h - t + j;
-            for i in 1..w {
-                let idx = grid_j * w + i;
-                let psi_idx = i * t + j;
-
-                let layer = t - 1 - j;
-
-                let b = self.coeffs_e.b[layer];
-                let c = self.coeffs_e.c[layer];
-                let kappa = self.coeffs_e.kappa[layer];
 
-                let dhx_dy = hx[idx] - hx[idx - w];
+        // Bottom: -dHx/dy term, Ez row j = k
+        if let Some(layer) = self.layers[BoundarySide::Bottom as usize].as_mut() {
+            let t = layer.thickness;
+            for k in 1..t {
+                let b = layer.coeffs_e.b[k];
+                let c = layer.coeffs_e.c[k];
+                let kappa = layer.coeffs_e.kappa[k];
+
+                for i in i_start..w {
+                    let idx = k * w + i;
+                    let psi = &mut layer.psi_e[i * t + k];
+
+                    let dhx_dy = hx[idx] - hx[idx - w];
+                    *psi = b * *psi + c * dhx_dy;
+                    ez[idx] -= cb[idx] * (dhx_dy * (1.0 / kappa - 1.0) + *psi);
+                }
+            }
+        }
 
-                self.psi_ezy_top[psi_idx] = b * self.psi_ezy_top[psi_idx] + c * dhx_dy;
-                ez[idx] -= cb[idx] * (dhx_dy * (1.0 / kappa - 1.0) + self.psi_ezy_top[psi_idx]);
+        // Top: -dHx/dy term, Ez row j = h-1-k
+        if let Some(layer) = self.layers[BoundarySide::Top as usize].as_mut() {
+            let t = layer.thickness;
+            for k in 0..t {
+                let b = layer.coeffs_e.b[k];
+                let c = layer.coeffs_e.c[k];
+                let kappa = layer.coeffs_e.kappa[k];
+                let j = h - 1 - k;
+
+                for i in i_start..w {
+                    let idx = j * w + i;
+                    let psi = &mut layer.psi_e[i * t + k];
+
+                    let dhx_dy = hx[idx] - hx[idx - w];
+                    *psi = b * *psi + c * dhx_dy;
+                    ez[idx] -= cb[idx] * (dhx_dy * (1.0 / kappa - 1.0) + *psi);
+                }
             }
         }
     }
 
-    /// Update H-field in all CPML regions
-    pub fn update_h_boundaries(
-        &mut self,
-        hx: &mut [f32],
-        hy: &mut [f32],
-        ez: &[f32],
-        w: usize,
-        courant: f32,
-    ) {
-        let t = self.thickness;
+    /// Apply CPML corrections to the edge fields (Hx/Hy) after the standard update
+    /// `db` holds the per-cell curl coefficients of the edge update
+    pub fn update_h(&mut self, hx: &mut [f32], hy: &mut [f32], ez: &[f32], db: &[f32]) {
+        let w = self.width;
         let h = self.height;
 
-        // Left boundary - Hy correction
-        for j in 0..h {
-            for i in 0..t {
-                let idx = j * w + i;
-                let psi_idx = i * h + j;
+        // Left: Hy column i = k sits k+1/2 cells from the edge
+        if let Some(layer) = self.layers[BoundarySide::Left as usize].as_mut() {
+            for k in 0..layer.thickness {
+                let b = layer.coeffs_h.b[k];
+                let c = layer.coeffs_h.c[k];
+                let kappa = layer.coeffs_h.kappa[k];
 
-                if i + 1 < w {
-                    let layer = i;
-                    let b = self.coeffs_h.b[layer];
-                    let c = self.coeffs_h.c[layer];
-                    let kappa = self.coeffs_h.kappa[layer];
+                for j in 0..h {
+                    let idx = j * w + k;
+                    let psi = &mut layer.psi_h[k * h + j];
 
                     let dez_dx = ez[idx + 1] - ez[idx];
-
-                    self.psi_hxy_left[psi_idx] = b * self.psi_hxy_left[psi_idx] + c * dez_dx;
-                    hy[idx] +=
-                        courant * (dez_dx * (1.0 / kappa - 1.0) + self.psi_hxy_left[psi_idx]);
+                    *psi = b * *psi + c * dez_dx;
+                    hy[idx] += db[idx] * (dez_dx * (1.0 / kappa - 1.0) + *psi);
                 }
             }
         }
 
-        // Right boundary - Hy correction
-        for j in 0..h {
-            for i in 0..t {
-                let grid_i = w - t + i;
-                let idx = j * w + grid_i;
-                let psi_idx = i * h + j;
+        // Right: Hy column i = w-2-k sits k+1/2 cells from the edge
+        if let Some(layer) = self.layers[BoundarySide::Right as usize].as_mut() {
+            for k in 0..layer.thickness {
+                let b = layer.coeffs_h.b[k];
+                let c = layer.coeffs_h.c[k];
+                let kappa = layer.coeffs_h.kappa[k];
+                let i = w - 2 - k;
 
-                if grid_i + 1 < w {
-                    let layer = t - 1 - i;
-                    let b = self.coeffs_h.b[layer];
-                    let c = self.coeffs_h.c[layer];
-                    let kappa = self.coeffs_h.kappa[layer];
+                for j in 0..h {
+                    let idx = j * w + i;
+                    let psi = &mut layer.psi_h[k * h + j];
 
                     let dez_dx = ez[idx + 1] - ez[idx];
-
-                    self.psi_hxy_right[psi_idx] = b * self.psi_hxy_right[psi_idx] + c * dez_dx;
-                    hy[idx] +=
-                        courant * (dez_dx * (1.0 / kappa - 1.0) + self.psi_hxy_right[psi_idx]);
+                    *psi = b * *psi + c * dez_dx;
+                    hy[idx] += db[idx] * (dez_dx * (1.0 / kappa - 1.0) + *psi);
                 }
             }
         }
 
-        // Bottom boundary - Hx correction
-        for j in 0..t {
-            for i in 0..w {
-                let idx = j * w + i;
-                let psi_idx = i * t + j;
+        // Bottom: Hx row j = k sits k+1/2 cells from the edge
+        if let Some(layer) = self.layers[BoundarySide::Bottom as usize].as_mut() {
+            let t = layer.thickness;
+            for k in 0..t {
+                let b = layer.coeffs_h.b[k];
+                let c = layer.coeffs_h.c[k];
+                let kappa = layer.coeffs_h.kappa[k];
 
-                if j + 1 < h {
-                    let layer = j;
-                    let b = self.coeffs_h.b[layer];
-                    let c = self.coeffs_h.c[layer];
-                    let kappa = self.coeffs_h.kappa[layer];
+                for i in 0..w {
+                    let idx = k * w + i;
+                    let psi = &mut layer.psi_h[i * t + k];
 
                     let dez_dy = ez[idx + w] - ez[idx];
-
-                    self.psi_hyx_bottom[psi_idx] = b * self.psi_hyx_bottom[psi_idx] + c * dez_dy;
-                    hx[idx] -=
-                        courant * (dez_dy * (1.0 / kappa - 1.0) + self.psi_hyx_bottom[psi_idx]);
+                    *psi = b * *psi + c * dez_dy;
+                    hx[idx] -= db[idx] * (dez_dy * (1.0 / kappa - 1.0) + *psi);
                 }
             }
         }
 
-        // Top boundary - Hx correction
-        for j in 0..t {
-            let grid_j = h - t + j;
-            for i in 0..w {
-                let idx = grid_j * w + i;
-                let psi_idx = i * t + j;
+        // Top: Hx row j = h-2-k sits k+1/2 cells from the edge
+        if let Some(layer) = self.layers[BoundarySide::Top as usize].as_mut() {
+            let t = layer.thickness;
+            for k in 0..t {
+                let b = layer.coeffs_h.b[k];
+                let c = layer.coeffs_h.c[k];
+                let kappa = layer.coeffs_h.kappa[k];
+                let j = h - 2 - k;
 
-                if grid_j + 1 < h {
-                    let layer = t - 1 - j;
-                    let b = self.coeffs_h.b[layer];
-                    let c = self.coeffs_h.c[layer];
-                    let kappa = self.coeffs_h.kappa[layer];
+                for i in 0..w {
+                    let idx = j * w + i;
+                    let psi = &mut layer.psi_h[i * t + k];
 
                     let dez_dy = ez[idx + w] - ez[idx];
-
-                    self.psi_hyx_top[psi_idx] = b * self.psi_hyx_top[psi_idx] + c * dez_dy;
-                    hx[idx] -= courant * (dez_dy * (1.0 / kappa - 1.0) + self.psi_hyx_top[psi_idx]);
+                    *psi = b * *psi + c * dez_dy;
+                    hx[idx] -= db[idx] * (dez_dy * (1.0 / kappa - 1.0) + *psi);
                 }
             }
         }
@@ -376,7 +479,7 @@ mod tests {
 
     #[test]
     fn test_cpml_coeffs_creation() {
-        let coeffs = CPMLCoeffs::new(20, 0.5);
+        let coeffs = CPMLCoeffs::graded(&CPMLParams::default(), 0.5, 0.0);
         assert_eq!(coeffs.b.len(), 20);
         assert_eq!(coeffs.c.len(), 20);
         assert_eq!(coeffs.kappa.len(), 20);
@@ -388,6 +491,34 @@ mod tests {
     #[test]
     fn test_cpml_creation() {
         let cpml = CPML::new(512, 512, 0.5);
-        assert_eq!(cpml.get_thickness(), 20);
+        assert_eq!(cpml.get_thickness(BoundarySide::Left), 20);
+        assert_eq!(cpml.get_thickness(BoundarySide::Top), 20);
+    }
+
+    #[test]
+    fn test_cpml_from_spec() {
+        let mut spec = BoundarySpec::uniform(BoundaryType::PEC);
+        spec.set_cpml(BoundarySide::Left, CPMLParams::new(8, 4.0, 1.0, 3.0, 0.02));
+        spec.set_cpml(
+            BoundarySide::Top,
+            CPMLParams::new(100, 3.0, 0.75, 5.0, 0.05),
+        );
+
+        let cpml = CPML::from_spec(256, 128, 0.5, &spec);
+        assert_eq!(cpml.get_thickness(BoundarySide::Left), 8);
+        assert_eq!(cpml.get_thickness(BoundarySide::Right), 0);
+        // Clamped to a quarter of the grid height
+        assert_eq!(cpml.get_thickness(BoundarySide::Top), 32);
+    }
+
+    #[test]
+    fn test_checked_params() {
+        assert!(CPMLParams::try_new(0, 3.0, 0.75, 5.0, 0.05).is_err());
+        assert!(CPMLParams::try_new(8, 3.0, -1.0, 5.0, 0.05).is_err());
+        assert!(CPMLParams::try_new(8, 3.0, 0.75, 0.5, 0.05).is_err());
+        assert_eq!(
+            CPMLParams::try_new(8, 4.0, 1.0, 3.0, 0.02),
+            Ok(CPMLParams::new(8, 4.0, 1.0, 3.0, 0.02))
+        );
     }
 }
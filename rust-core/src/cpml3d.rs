@@ -0,0 +1,209 @@
+//! Convolutional PML for the 3D FDTD grid
+//!
+//! Same Roden–Gedney formulation as the 2D layers: each axis carries a
+//! graded slab at both ends, and every curl term along that axis keeps
+//! its own psi accumulator inside the slabs.
+//!
+//! With components indexed x=0, y=1, z=2 and axis a, b = a+1, c = a+2 (mod 3),
+//! the derivatives along a enter the curl equations as
+//! - E_c += cb · ∂H_b/∂a,  E_b -= cb · ∂H_c/∂a
+//! - H_b += db · ∂E_c/∂a,  H_c -= db · ∂E_b/∂a
+
+use crate::cpml::{CPMLCoeffs, CPMLParams};
+
+/// Corrected position along an axis with its (b, c, kappa) coefficients
+#[derive(Clone, Copy)]
+struct GradedCell {
+    pos: usize,
+    b: f32,
+    c: f32,
+    kappa: f32,
+}
+
+/// Absorbing slabs at both ends of one axis
+///
+/// Psi arrays are laid out as [slot][plane], where slot enumerates the
+/// graded positions and plane enumerates the cells of a slice normal to the axis.
+struct AxisLayer {
+    thickness: usize,
+    stride: usize,
+    plane: Vec<usize>, // Flat offsets of the cells with coordinate 0 along the axis
+    e_cells: Vec<GradedCell>,
+    h_cells: Vec<GradedCell>,
+    psi_e: [Vec<f32>; 2],
+    psi_h: [Vec<f32>; 2],
+}
+
+impl AxisLayer {
+    fn new(params: &CPMLParams, dims: [usize; 3], axis: usize, dt: f32) -> AxisLayer {
+        let n = dims[axis];
+        let thickness = params.thickness.min(n / 4).max(1);
+        let params = CPMLParams {
+            thickness,
+            ..*params
+        };
+        let coeffs_e = CPMLCoeffs::graded(&params, dt, 0.0);
+        let coeffs_h = CPMLCoeffs::graded(&params, dt, 0.5);
+        let cell = |coeffs: &CPMLCoeffs, pos: usize, k: usize| GradedCell {
+            pos,
+            b: coeffs.b[k],
+            c: coeffs.c[k],
+            kappa: coeffs.kappa[k],
+        };
+
+        // E derivatives sit on integer positions (k cells from the edge),
+        // H derivatives on half positions (k+1/2 cells from the edge)
+        let mut e_cells = Vec::with_capacity(2 * thickness);
+        let mut h_cells = Vec::with_capacity(2 * thickness);
+        for k in 0..thickness {
+            if k > 0 {
+                e_cells.push(cell(&coeffs_e, k, k));
+            }
+            e_cells.push(cell(&coeffs_e, n - 1 - k, k));
+            h_cells.push(cell(&coeffs_h, k, k));
+            h_cells.push(cell(&coeffs_h, n - 2 - k, k));
+        }
+
+        let strides = [1, dims[0], dims[0] * dims[1]];
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut plane = Vec::with_capacity(dims[u] * dims[v]);
+        for q in 0..dims[v] {
+            for p in 0..dims[u] {
+                plane.push(p * strides[u] + q * strides[v]);
+            }
+        }
+
+        let psi_e = vec![0.0; e_cells.len() * plane.len()];
+        let psi_h = vec![0.0; h_cells.len() * plane.len()];
+        AxisLayer {
+            thickness,
+            stride: strides[axis],
+            plane,
+            e_cells,
+            h_cells,
+            psi_e: [psi_e.clone(), psi_e],
+            psi_h: [psi_h.clone(), psi_h],
+        }
+    }
+
+    fn reset(&mut self) {
+        for psi in self.psi_e.iter_mut().chain(self.psi_h.iter_mut()) {
+            psi.fill(0.0);
+        }
+    }
+}
+
+/// CPML boundary handler for 3D FDTD (all six faces)
+pub struct CPML3D {
+    axes: [AxisLayer; 3],
+}
+
+impl CPML3D {
+    /// Create CPML slabs on all six faces of an nx × ny × nz grid
+    pub fn new(nx: usize, ny: usize, nz: usize, params: &CPMLParams, dt: f32) -> CPML3D {
+        let dims = [nx, ny, nz];
+        CPML3D {
+            axes: [
+                AxisLayer::new(params, dims, 0, dt),
+                AxisLayer::new(params, dims, 1, dt),
+                AxisLayer::new(params, dims, 2, dt),
+            ],
+        }
+    }
+
+    /// Get slab thickness along one axis (0 = x, 1 = y, 2 = z)
+    pub fn get_thickness(&self, axis: usize) -> usize {
+        self.axes.get(axis).map_or(0, |layer| layer.thickness)
+    }
+
+    /// Reset all psi arrays to zero
+    pub fn reset(&mut self) {
+        for layer in self.axes.iter_mut() {
+            layer.reset();
+        }
+    }
+
+    /// Apply CPML corrections to [Ex, Ey, Ez] after the standard E update
+    /// `cb` is the per-cell curl coefficient of the E update
+    pub fn update_e(&mut self, e: [&mut [f32]; 3], h: [&[f32]; 3], cb: &[f32]) {
+        for (a, layer) in self.axes.iter_mut().enumerate() {
+            let (b, c) = ((a + 1) % 3, (a + 2) % 3);
+            let s = layer.stride;
+            let n_plane = layer.plane.len();
+            let [psi_c, psi_b] = &mut layer.psi_e;
+
+            for (slot, cell) in layer.e_cells.iter().enumerate() {
+                let inv_kappa = 1.0 / cell.kappa - 1.0;
+                for (q, &base) in layer.plane.iter().enumerate() {
+                    let idx = base + cell.pos * s;
+                    let p = slot * n_plane + q;
+
+                    // E_c += cb * (dH_b/da / kappa + psi)
+                    let d = h[b][idx] - h[b][idx - s];
+                    psi_c[p] = cell.b * psi_c[p] + cell.c * d;
+                    e[c][idx] += cb[idx] * (d * inv_kappa + psi_c[p]);
+
+                    // E_b -= cb * (dH_c/da / kappa + psi)
+                    let d = h[c][idx] - h[c][idx - s];
+                    psi_b[p] = cell.b * psi_b[p] + cell.c * d;
+                    e[b][idx] -= cb[idx] * (d * inv_kappa + psi_b[p]);
+                }
+            }
+        }
+    }
+
+    /// Apply CPML corrections to [Hx, Hy, Hz] after the standard H update
+    /// `db` is the per-cell curl coefficient of the H update
+    pub fn update_h(&mut self, h: [&mut [f32]; 3], e: [&[f32]; 3], db: &[f32]) {
+        for (a, layer) in self.axes.iter_mut().enumerate() {
+            let (b, c) = ((a + 1) % 3, (a + 2) % 3);
+            let s = layer.stride;
+            let n_plane = layer.plane.len();
+            let [psi_b, psi_c] = &mut layer.psi_h;
+
+            for (slot, cell) in layer.h_cells.iter().enumerate() {
+                let inv_kappa = 1.0 / cell.kappa - 1.0;
+                for (q, &base) in layer.plane.iter().enumerate() {
+                    let idx = base + cell.pos * s;
+                    let p = slot * n_plane + q;
+
+                    // H_b += db * (dE_c/da / kappa + psi)
+                    let d = e[c][idx + s] - e[c][idx];
+                    psi_b[p] = cell.b * psi_b[p] + cell.c * d;
+                    h[b][idx] += db[idx] * (d * inv_kappa + psi_b[p]);
+
+                    // H_c -= db * (dE_b/da / kappa + psi)
+                    let d = e[b][idx + s] - e[b][idx];
+                    psi_c[p] = cell.b * psi_c[p] + cell.c * d;
+                    h[c][idx] -= db[idx] * (d * inv_kappa + psi_c[p]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpml3d_thickness_clamped() {
+        let cpml = CPML3D::new(100, 40, 12, &CPMLParams::default(), 0.5);
+        assert_eq!(cpml.get_thickness(0), 20);
+        assert_eq!(cpml.get_thickness(1), 10);
+        assert_eq!(cpml.get_thickness(2), 3);
+        assert_eq!(cpml.get_thickness(3), 0);
+    }
+
+    #[test]
+    fn test_cpml3d_graded_positions() {
+        let layer = AxisLayer::new(&CPMLParams::default(), [40, 8, 8], 0, 0.5);
+        // Left slab skips the wall node, right slab includes it
+        assert_eq!(layer.e_cells.len(), 2 * layer.thickness - 1);
+        assert_eq!(layer.h_cells.len(), 2 * layer.thickness);
+        assert!(layer.e_cells.iter().all(|c| c.pos >= 1 && c.pos <= 39));
+        assert!(layer.h_cells.iter().all(|c| c.pos <= 38));
+        assert_eq!(layer.plane.len(), 64);
+        assert_eq!(layer.stride, 1);
+    }
+}
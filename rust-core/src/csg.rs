@@ -0,0 +1,323 @@
+//! Constructive Solid Geometry over Shape Primitives
+//!
+//! A CSG node combines operand shapes (primitives or other nodes) with a
+//! boolean operation and may assign a material to everything it produces.
+//! Materials resolve from the innermost node that sets one, falling back to
+//! the material the whole expression is painted with:
+//! - Union: the last operand containing a point wins (later operands on top)
+//! - Intersection: points inside every operand, material of the first
+//! - Difference: points of the first operand outside all the others
+//!
+//! Air-hole lattices, slots and cutouts are single objects this way, with
+//! no reliance on painting order.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::raster::union_bounds;
+use crate::shapes::{FillRule, Shape};
+
+/// Boolean operation of a CSG node
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CsgOp {
+    Union = 0,
+    Intersection = 1,
+    /// First operand minus all the others
+    Difference = 2,
+}
+
+/// Boolean combination of shapes with an optional material
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CsgNode {
+    pub op: CsgOp,
+    pub operands: Vec<Shape>,
+    #[serde(rename = "material", default, skip_serializing_if = "Option::is_none")]
+    pub material_id: Option<u32>,
+}
+
+impl CsgNode {
+    /// Material at a point, or None outside the node
+    pub fn material_at(&self, x: f32, y: f32, inherited: u32) -> Option<u32> {
+        let material = self.material_id.unwrap_or(inherited);
+        let (first, rest) = self.operands.split_first()?;
+        match self.op {
+            CsgOp::Union => self
+                .operands
+                .iter()
+                .rev()
+                .find_map(|s| s.material_at(x, y, material)),
+            CsgOp::Intersection => {
+                if rest.iter().all(|s| s.contains(x, y)) {
+                    first.material_at(x, y, material)
+                } else {
+                    None
+                }
+            }
+            CsgOp::Difference => {
+                if rest.iter().any(|s| s.contains(x, y)) {
+                    None
+                } else {
+                    first.material_at(x, y, material)
+                }
+            }
+        }
+    }
+
+    /// Bounding box of the result (None if provably empty)
+    pub fn bounds(&self) -> Option<[f32; 4]> {
+        let (first, rest) = self.operands.split_first()?;
+        match self.op {
+            CsgOp::Union => self
+                .operands
+                .iter()
+                .fold(None, |b, s| union_bounds(b, s.bounds())),
+            CsgOp::Intersection => rest.iter().try_fold(first.bounds()?, |b, s| {
+                let o = s.bounds()?;
+                let i = [
+                    b[0].max(o[0]),
+                    b[1].max(o[1]),
+                    b[2].min(o[2]),
+                    b[3].min(o[3]),
+                ];
+                (i[0] <= i[2] && i[1] <= i[3]).then_some(i)
+            }),
+            CsgOp::Difference => first.bounds(),
+        }
+    }
+
+    /// Distinct materials the node can produce, in first-use order
+    pub fn materials(&self, inherited: u32, out: &mut Vec<u32>) {
+        let material = self.material_id.unwrap_or(inherited);
+        let produced = match self.op {
+            CsgOp::Union => &self.operands[..],
+            // Only the first operand's material survives
+            CsgOp::Intersection | CsgOp::Difference => &self.operands[..self.operands.len().min(1)],
+        };
+        for shape in produced {
+            shape.collect_materials(material, out);
+        }
+    }
+}
+
+impl Shape {
+    /// Boolean combination of shapes
+    pub fn csg(op: CsgOp, operands: Vec<Shape>) -> Shape {
+        Shape::Csg(Box::new(CsgNode {
+            op,
+            operands,
+            material_id: None,
+        }))
+    }
+
+    /// Assign a material to this shape inside a CSG expression
+    pub fn with_material(self, material_id: u32) -> Shape {
+        match self {
+            Shape::Csg(mut node) => {
+                node.material_id = Some(material_id);
+                Shape::Csg(node)
+            }
+            shape => Shape::Csg(Box::new(CsgNode {
+                op: CsgOp::Union,
+                operands: vec![shape],
+                material_id: Some(material_id),
+            })),
+        }
+    }
+
+    /// Material at a point when the shape is painted with `material_id`
+    /// (None outside the shape)
+    pub fn material_at(&self, x: f32, y: f32, material_id: u32) -> Option<u32> {
+        match self {
+            Shape::Csg(node) => node.material_at(x, y, material_id),
+            shape => shape.contains(x, y).then_some(material_id),
+        }
+    }
+
+    /// Distinct materials produced when painted with `material_id`
+    pub fn materials(&self, material_id: u32) -> Vec<u32> {
+        let mut out = Vec::new();
+        self.collect_materials(material_id, &mut out);
+        out
+    }
+
+    fn collect_materials(&self, material_id: u32, out: &mut Vec<u32>) {
+        match self {
+            Shape::Csg(node) => node.materials(material_id, out),
+            _ => {
+                if !out.contains(&material_id) {
+                    out.push(material_id);
+                }
+            }
+        }
+    }
+}
+
+/// Shape or CSG expression assembled from JavaScript
+///
+/// Combine primitives with `union`, `intersect` and `subtract`, then pass
+/// the result to `FDTDGrid::paint_geometry` or `FDTDGrid::add_geometry`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Geometry {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub shape: Shape,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Geometry {
+    /// Rectangle of size width × height centred at (cx, cy), rotated by `angle`
+    pub fn rect(cx: f32, cy: f32, width: f32, height: f32, angle: f32) -> Geometry {
+        let shape = Shape::Rect {
+            cx,
+            cy,
+            width,
+            height,
+            angle,
+        };
+        Geometry { shape }
+    }
+
+    /// Ellipse with semi-axes rx, ry centred at (cx, cy), rotated by `angle`
+    pub fn ellipse(cx: f32, cy: f32, rx: f32, ry: f32, angle: f32) -> Geometry {
+        let shape = Shape::Ellipse {
+            cx,
+            cy,
+            rx,
+            ry,
+            angle,
+        };
+        Geometry { shape }
+    }
+
+    /// Circle of the given radius centred at (cx, cy)
+    pub fn circle(cx: f32, cy: f32, radius: f32) -> Geometry {
+        Geometry::ellipse(cx, cy, radius, radius, 0.0)
+    }
+
+    /// Polygon from a flat [x0, y0, x1, y1, ...] vertex list
+    pub fn polygon(vertices: &[f32], fill_rule: FillRule) -> Geometry {
+        Geometry {
+            shape: Shape::polygon(vertices, fill_rule),
+        }
+    }
+
+    /// Union with another geometry (the other one is on top)
+    pub fn union(&self, other: &Geometry) -> Geometry {
+        self.combine(CsgOp::Union, other)
+    }
+
+    /// Intersection with another geometry
+    pub fn intersect(&self, other: &Geometry) -> Geometry {
+        self.combine(CsgOp::Intersection, other)
+    }
+
+    /// This geometry minus another one
+    pub fn subtract(&self, other: &Geometry) -> Geometry {
+        self.combine(CsgOp::Difference, other)
+    }
+
+    /// Same geometry with a material overriding the painted one
+    pub fn with_material(&self, material_id: u32) -> Geometry {
+        Geometry {
+            shape: self.shape.clone().with_material(material_id),
+        }
+    }
+
+    /// Move, rotate (radians) and scale about the bounding-box centre
+    pub fn transformed(&self, dx: f32, dy: f32, angle: f32, scale: f32) -> Geometry {
+        let mut shape = self.shape.clone();
+        if let Some(pivot) = shape.center() {
+            shape.transform(dx, dy, angle, scale, pivot);
+        }
+        Geometry { shape }
+    }
+
+    /// Get the bounding box [x_min, y_min, x_max, y_max] (empty if degenerate)
+    pub fn get_bounds(&self) -> Vec<f32> {
+        self.shape.bounds().map_or(Vec::new(), |b| b.to_vec())
+    }
+
+    /// Check if a point lies inside
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        self.shape.contains(x, y)
+    }
+}
+
+impl Geometry {
+    /// Append `other` as an operand, flattening chains of the same operation
+    fn combine(&self, op: CsgOp, other: &Geometry) -> Geometry {
+        let operands = match &self.shape {
+            Shape::Csg(node) if node.op == op && node.material_id.is_none() => {
+                let mut operands = node.operands.clone();
+                operands.push(other.shape.clone());
+                operands
+            }
+            shape => vec![shape.clone(), other.shape.clone()],
+        };
+        Geometry {
+            shape: Shape::csg(op, operands),
+        }
+    }
+}
+
+impl From<Shape> for Geometry {
+    fn from(shape: Shape) -> Geometry {
+        Geometry { shape }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boolean_operations() {
+        let slab = Geometry::rect(10.0, 5.0, 20.0, 10.0, 0.0);
+        let hole = Geometry::circle(10.0, 5.0, 3.0);
+
+        let cut = slab.subtract(&hole);
+        assert!(cut.contains(2.0, 5.0));
+        assert!(!cut.contains(10.0, 5.0));
+        assert!(!cut.contains(30.0, 5.0));
+
+        let lens = Geometry::circle(0.0, 0.0, 5.0).intersect(&Geometry::circle(6.0, 0.0, 5.0));
+        assert!(lens.contains(3.0, 0.0));
+        assert!(!lens.contains(-2.0, 0.0));
+        assert_eq!(lens.get_bounds(), vec![1.0, -5.0, 5.0, 5.0]);
+
+        let disjoint = Geometry::circle(0.0, 0.0, 1.0).intersect(&Geometry::circle(9.0, 0.0, 1.0));
+        assert!(disjoint.get_bounds().is_empty());
+
+        // Chained operations flatten into one node
+        let lattice = (0..4).fold(slab.clone(), |g, i| {
+            g.subtract(&Geometry::circle(2.0 + 5.0 * i as f32, 5.0, 1.0))
+        });
+        match &lattice.shape {
+            Shape::Csg(node) => assert_eq!(node.operands.len(), 5),
+            _ => panic!("expected a CSG node"),
+        }
+    }
+
+    #[test]
+    fn test_per_node_materials() {
+        // Silicon slab with a glass-filled slot and an air hole
+        let slab = Geometry::rect(10.0, 5.0, 20.0, 10.0, 0.0);
+        let slot = Geometry::rect(5.0, 5.0, 2.0, 10.0, 0.0).with_material(1);
+        let hole = Geometry::circle(15.0, 5.0, 2.0);
+        let device = slab.union(&slot).subtract(&hole);
+
+        assert_eq!(device.shape.material_at(10.0, 5.0, 6), Some(6));
+        assert_eq!(device.shape.material_at(5.0, 5.0, 6), Some(1));
+        assert_eq!(device.shape.material_at(15.0, 5.0, 6), None);
+        assert_eq!(device.shape.materials(6), vec![6, 1]);
+
+        // A node material applies where no inner node sets one
+        let glass = device.with_material(2);
+        assert_eq!(glass.shape.material_at(10.0, 5.0, 6), Some(2));
+        assert_eq!(glass.shape.material_at(5.0, 5.0, 6), Some(1));
+    }
+}
@@ -0,0 +1,110 @@
+//! Running DFT Field Monitor
+//!
+//! Accumulates the discrete Fourier transform of a whole field at one
+//! frequency while the simulation runs:
+//!     F(x) = Σ_n f(x, n) · e^{-i2πfn}
+//! The amplitude map 2|F|/N is the steady-state amplitude of a field that
+//! oscillates at f, and arg F its phase; for pulsed runs it is the spectral
+//! response at f. Sums are kept in f64 so long runs do not lose precision.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Frequency-domain map of a field at one frequency
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct FieldDft {
+    frequency: f32,
+    re: Vec<f64>,
+    im: Vec<f64>,
+    samples: u64,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl FieldDft {
+    /// Create a monitor for `len` cells at a normalized frequency (cycles per step)
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(frequency: f32, len: usize) -> FieldDft {
+        FieldDft {
+            frequency,
+            re: vec![0.0; len],
+            im: vec![0.0; len],
+            samples: 0,
+        }
+    }
+
+    /// Add the field at time step `step` (cells beyond the monitor are ignored)
+    pub fn accumulate(&mut self, field: &[f32], step: u64) {
+        let phase = -2.0 * std::f64::consts::PI * self.frequency as f64 * step as f64;
+        let (sin, cos) = phase.sin_cos();
+        for ((re, im), &value) in self.re.iter_mut().zip(&mut self.im).zip(field) {
+            *re += value as f64 * cos;
+            *im += value as f64 * sin;
+        }
+        self.samples += 1;
+    }
+
+    /// Get the normalized frequency
+    pub fn get_frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    /// Get the number of accumulated time steps
+    pub fn get_sample_count(&self) -> u64 {
+        self.samples
+    }
+
+    /// Get the amplitude map 2|F|/N (zeros before the first sample)
+    pub fn get_amplitude(&self) -> Vec<f32> {
+        let scale = 2.0 / self.samples.max(1) as f64;
+        self.re
+            .iter()
+            .zip(&self.im)
+            .map(|(re, im)| (re.hypot(*im) * scale) as f32)
+            .collect()
+    }
+
+    /// Get the phase map arg F in radians
+    pub fn get_phase(&self) -> Vec<f32> {
+        self.re
+            .iter()
+            .zip(&self.im)
+            .map(|(re, im)| im.atan2(*re) as f32)
+            .collect()
+    }
+
+    /// Clear the accumulated sums
+    pub fn reset(&mut self) {
+        self.re.fill(0.0);
+        self.im.fill(0.0);
+        self.samples = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dft_of_sinusoids() {
+        // Cell 0 oscillates at the monitored frequency, cell 1 at another one
+        let frequency = 0.05;
+        let mut dft = FieldDft::new(frequency, 2);
+        for n in 0..400u64 {
+            let t = n as f32;
+            let field = [
+                3.0 * (2.0 * std::f32::consts::PI * frequency * t).cos(),
+                (2.0 * std::f32::consts::PI * 0.125 * t).cos(),
+            ];
+            dft.accumulate(&field, n);
+        }
+        assert_eq!(dft.get_sample_count(), 400);
+        let amplitude = dft.get_amplitude();
+        assert!((amplitude[0] - 3.0).abs() < 1e-3);
+        assert!(amplitude[1] < 1e-3);
+        assert!(dft.get_phase()[0].abs() < 1e-3);
+
+        dft.reset();
+        assert_eq!(dft.get_amplitude(), vec![0.0, 0.0]);
+    }
+}
@@ -0,0 +1,217 @@
+//! Dispersive Media via Auxiliary Differential Equations (ADE)
+//!
+//! Every pole of a dispersive cell carries a polarization P obeying
+//! - P'' + γP' + ω0²P = ωp²E  (Lorentz with ωp² = Δε·ω0², Drude with ω0 = 0)
+//! - τP' + P = ΔεE            (Debye)
+//!
+//! P is advanced explicitly from E^n before the E update, and the E update
+//! then removes the polarization current:
+//! E^{n+1} = ca·E^n + cb·curl(H) - cp·Σ(P^{n+1} - P^n), cp = 1/(ε∞(1 + σΔt/2ε∞)).
+//!
+//! Only painted cells are tracked, so non-dispersive grids pay nothing.
+
+use crate::materials::Pole;
+
+/// Lookup marker for cells without poles
+const NO_CELL: u32 = u32::MAX;
+
+/// Distinct pole list shared by every cell painted with the same material
+struct PoleSet {
+    poles: Vec<Pole>,
+    coeffs: Vec<[f32; 3]>,
+}
+
+/// Polarization state of one dispersive cell
+///
+/// `state` is laid out as [field][pole][P^n, P^{n-1}], `delta` as [field].
+struct DispersiveCell {
+    idx: usize,
+    set: usize,
+    cp: f32,
+    state: Vec<f32>,
+    delta: Vec<f32>,
+}
+
+/// Dispersive cells of a grid with their polarization state
+///
+/// The fields passed to `advance`/`apply` are the electric components that
+/// live on each cell (e.g. [Ez] or [Ez, Ez_imag]); the state is resized and
+/// cleared whenever their number changes.
+pub struct DispersiveMedia {
+    dt: f32,
+    sets: Vec<PoleSet>,
+    cells: Vec<DispersiveCell>,
+    lookup: Vec<u32>,
+    fields: usize,
+}
+
+impl DispersiveMedia {
+    /// Create an empty media table for a grid with `size` cells
+    pub fn new(size: usize, dt: f32) -> DispersiveMedia {
+        DispersiveMedia {
+            dt,
+            sets: Vec::new(),
+            cells: Vec::new(),
+            lookup: vec![NO_CELL; size],
+            fields: 1,
+        }
+    }
+
+    /// Number of dispersive cells
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Make a cell dispersive with the given poles
+    /// `cp` is the polarization-current coefficient 1/(ε∞(1 + σΔt/2ε∞))
+    pub fn assign(&mut self, idx: usize, poles: &[Pole], cp: f32) {
+        self.remove(idx);
+        if poles.is_empty() || idx >= self.lookup.len() {
+            return;
+        }
+
+        let set = match self.sets.iter().position(|s| s.poles == poles) {
+            Some(set) => set,
+            None => {
+                let coeffs = poles.iter().map(|p| p.ade_coefficients(self.dt)).collect();
+                self.sets.push(PoleSet {
+                    poles: poles.to_vec(),
+                    coeffs,
+                });
+                self.sets.len() - 1
+            }
+        };
+
+        self.lookup[idx] = self.cells.len() as u32;
+        self.cells.push(DispersiveCell {
+            idx,
+            set,
+            cp,
+            state: vec![0.0; self.fields * poles.len() * 2],
+            delta: vec![0.0; self.fields],
+        });
+    }
+
+    /// Make a cell non-dispersive again
+    pub fn remove(&mut self, idx: usize) {
+        let Some(&slot) = self.lookup.get(idx) else {
+            return;
+        };
+        if slot == NO_CELL {
+            return;
+        }
+
+        self.lookup[idx] = NO_CELL;
+        self.cells.swap_remove(slot as usize);
+        if let Some(moved) = self.cells.get(slot as usize) {
+            self.lookup[moved.idx] = slot;
+        }
+    }
+
+    /// Change the time step, recomputing the pole coefficients
+    /// `cp` gives the new polarization-current coefficient of a cell index
+    pub fn set_dt(&mut self, dt: f32, cp: impl Fn(usize) -> f32) {
+        self.dt = dt;
+        for set in self.sets.iter_mut() {
+            set.coeffs = set.poles.iter().map(|p| p.ade_coefficients(dt)).collect();
+        }
+        for cell in self.cells.iter_mut() {
+            cell.cp = cp(cell.idx);
+        }
+    }
+
+    /// Zero all polarization state
+    pub fn reset(&mut self) {
+        for cell in self.cells.iter_mut() {
+            cell.state.fill(0.0);
+            cell.delta.fill(0.0);
+        }
+    }
+
+    /// Advance every pole from E^n (call before the E update)
+    pub fn advance(&mut self, fields: &[&[f32]]) {
+        if fields.len() != self.fields {
+            self.fields = fields.len();
+            for cell in self.cells.iter_mut() {
+                let poles = self.sets[cell.set].poles.len();
+                cell.state = vec![0.0; self.fields * poles * 2];
+                cell.delta = vec![0.0; self.fields];
+            }
+        }
+
+        for cell in self.cells.iter_mut() {
+            let coeffs = &self.sets[cell.set].coeffs;
+            for (f, field) in fields.iter().enumerate() {
+                let e = field[cell.idx];
+                let mut delta = 0.0;
+
+                for (p, c) in coeffs.iter().enumerate() {
+                    let base = (f * coeffs.len() + p) * 2;
+                    let (p_now, p_prev) = (cell.state[base], cell.state[base + 1]);
+                    let p_next = c[0] * p_now + c[1] * p_prev + c[2] * e;
+
+                    delta += p_next - p_now;
+                    cell.state[base] = p_next;
+                    cell.state[base + 1] = p_now;
+                }
+                cell.delta[f] = delta;
+            }
+        }
+    }
+
+    /// Subtract the polarization current from the freshly updated E fields
+    pub fn apply(&self, fields: &mut [&mut [f32]]) {
+        for cell in &self.cells {
+            for (field, &delta) in fields.iter_mut().zip(&cell.delta) {
+                field[cell.idx] -= cell.cp * delta;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assign_and_remove() {
+        let mut media = DispersiveMedia::new(16, 0.5);
+        let poles = [Pole::drude(0.5, 0.01)];
+        media.assign(3, &poles, 1.0);
+        media.assign(7, &poles, 1.0);
+        media.assign(9, &[Pole::debye(10.0, 4.0)], 1.0);
+        assert_eq!(media.len(), 3);
+        assert_eq!(media.sets.len(), 2);
+
+        media.remove(3);
+        assert_eq!(media.lookup[3], NO_CELL);
+        assert_ne!(media.lookup[7], NO_CELL);
+        assert_eq!(media.cells[media.lookup[9] as usize].idx, 9);
+
+        // Repainting with no poles clears the cell
+        media.assign(9, &[], 1.0);
+        assert_eq!(media.len(), 1);
+    }
+
+    #[test]
+    fn test_debye_relaxes_to_static_response() {
+        // Constant E: P → Δε·E
+        let mut media = DispersiveMedia::new(1, 0.5);
+        media.assign(0, &[Pole::debye(4.0, 3.0)], 1.0);
+        let e = [2.0];
+        for _ in 0..200 {
+            media.advance(&[&e]);
+        }
+        assert!((media.cells[0].state[0] - 8.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_set_dt_recomputes_coefficients() {
+        let pole = Pole::lorentz(2.0, 0.3, 0.05);
+        let mut media = DispersiveMedia::new(4, 0.5);
+        media.assign(1, &[pole], 0.25);
+        media.set_dt(0.25, |idx| idx as f32);
+        assert_eq!(media.sets[0].coeffs[0], pole.ade_coefficients(0.25));
+        assert_eq!(media.cells[0].cp, 1.0);
+    }
+}
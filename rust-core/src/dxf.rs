@@ -0,0 +1,386 @@
+//! DXF Drawing Import
+//!
+//! Reads closed outlines from the ENTITIES section of an ASCII DXF file:
+//! - LWPOLYLINE and POLYLINE (bulge arcs included), CIRCLE and ELLIPSE
+//! - LINE, ARC and open polylines, joined end to end into closed loops
+//!   within each layer (outlines that do not close are dropped)
+//!
+//! Every loop is keyed by its layer name and filled on its own; draw holes
+//! on a layer mapped to vacuum. DXF's y axis points up while the grid's
+//! points down, so drawings are mirrored on import:
+//!     grid = (x × scale + offset_x, offset_y − y × scale)
+//! Blocks (INSERT), splines and hatches are not read.
+
+use crate::import::{
+    apply, arc_points, compose, contours_shape, ellipse_shape, Affine, ImportedShape,
+};
+use crate::shapes::FillRule;
+
+/// Largest gap (in cells) between end points that still joins two pieces
+const JOIN_TOLERANCE: f32 = 1e-3;
+
+/// Mirror y so that drawings appear upright on the grid
+const FLIP_Y: Affine = [1.0, 0.0, 0.0, -1.0, 0.0, 0.0];
+
+/// One entity with its group codes
+struct Entity<'a> {
+    kind: &'a str,
+    groups: Vec<(i32, &'a str)>,
+}
+
+impl Entity<'_> {
+    /// First value of a numeric group code
+    fn number(&self, code: i32) -> Option<f64> {
+        self.groups
+            .iter()
+            .find(|(c, _)| *c == code)
+            .and_then(|(_, v)| v.parse().ok())
+    }
+
+    fn point(&self, x: i32, y: i32) -> Result<[f64; 2], String> {
+        match (self.number(x), self.number(y)) {
+            (Some(x), Some(y)) => Ok([x, y]),
+            _ => Err(format!("{} entity without coordinates", self.kind)),
+        }
+    }
+
+    fn layer(&self) -> String {
+        self.groups
+            .iter()
+            .find(|(c, _)| *c == 8)
+            .map_or("0", |(_, v)| v)
+            .to_string()
+    }
+
+    fn flags(&self) -> u32 {
+        self.number(70).map_or(0, |f| f as u32)
+    }
+}
+
+/// Closed outlines of a DXF drawing placed on the grid by `placement`
+/// (applied after the y mirror)
+pub fn parse_dxf(text: &str, placement: &Affine) -> Result<Vec<ImportedShape>, String> {
+    let m = compose(placement, &FLIP_Y);
+    let entities = read_entities(text)?;
+
+    let mut shapes = Vec::new();
+    let mut pieces: Vec<(String, Vec<[f32; 2]>)> = Vec::new();
+
+    let mut i = 0;
+    while i < entities.len() {
+        let entity = &entities[i];
+        i += 1;
+        // Paper-space entities are annotations, not geometry
+        if entity.number(67) == Some(1.0) {
+            continue;
+        }
+        let layer = entity.layer();
+        match entity.kind {
+            "LWPOLYLINE" => {
+                let mut vertices: Vec<([f64; 2], f64)> = Vec::new();
+                for &(code, value) in &entity.groups {
+                    if !matches!(code, 10 | 20 | 42) {
+                        continue;
+                    }
+                    let value: f64 = value
+                        .parse()
+                        .map_err(|_| format!("invalid LWPOLYLINE value '{}'", value))?;
+                    match (code, vertices.last_mut()) {
+                        (10, _) => vertices.push(([value, 0.0], 0.0)),
+                        (20, Some(vertex)) => vertex.0[1] = value,
+                        (42, Some(vertex)) => vertex.1 = value,
+                        _ => {}
+                    }
+                }
+                let closed = entity.flags() & 1 != 0;
+                let points = polyline_points(&m, &vertices, closed);
+                if closed {
+                    shapes.extend(outline(layer, points));
+                } else {
+                    pieces.push((layer, points));
+                }
+            }
+            "POLYLINE" => {
+                let mut vertices = Vec::new();
+                while i < entities.len() && entities[i].kind == "VERTEX" {
+                    let vertex = &entities[i];
+                    // Spline frame control points are not on the curve
+                    if vertex.flags() & 16 == 0 {
+                        vertices.push((vertex.point(10, 20)?, vertex.number(42).unwrap_or(0.0)));
+                    }
+                    i += 1;
+                }
+                if entities.get(i).is_some_and(|e| e.kind == "SEQEND") {
+                    i += 1;
+                }
+                // Polygon and polyface meshes are 3D surfaces
+                if entity.flags() & (16 | 64) != 0 {
+                    continue;
+                }
+                let closed = entity.flags() & 1 != 0;
+                let points = polyline_points(&m, &vertices, closed);
+                if closed {
+                    shapes.extend(outline(layer, points));
+                } else {
+                    pieces.push((layer, points));
+                }
+            }
+            "CIRCLE" => {
+                let radius = entity.number(40).unwrap_or(0.0);
+                if radius > 0.0 {
+                    let shape = ellipse_shape(&m, entity.point(10, 20)?, radius, radius, 0.0);
+                    shapes.push(ImportedShape { layer, shape });
+                }
+            }
+            "ELLIPSE" => {
+                let center = entity.point(10, 20)?;
+                let major = entity.point(11, 21)?;
+                let rx = major[0].hypot(major[1]);
+                let ry = rx * entity.number(40).unwrap_or(1.0);
+                let rotation = major[1].atan2(major[0]);
+                let start = entity.number(41).unwrap_or(0.0);
+                let end = entity.number(42).unwrap_or(std::f64::consts::TAU);
+                let sweep = positive_sweep(end - start);
+                if rx <= 0.0 || ry <= 0.0 {
+                    continue;
+                }
+                if (sweep - std::f64::consts::TAU).abs() < 1e-9 {
+                    let shape = ellipse_shape(&m, center, rx, ry, rotation);
+                    shapes.push(ImportedShape { layer, shape });
+                } else {
+                    let points = arc_points(&m, center, [rx, ry], rotation, start, sweep);
+                    pieces.push((layer, points));
+                }
+            }
+            "ARC" => {
+                let radius = entity.number(40).unwrap_or(0.0);
+                let start = entity.number(50).unwrap_or(0.0).to_radians();
+                let end = entity.number(51).unwrap_or(360.0).to_radians();
+                if radius > 0.0 {
+                    let center = entity.point(10, 20)?;
+                    let sweep = positive_sweep(end - start);
+                    let points = arc_points(&m, center, [radius, radius], 0.0, start, sweep);
+                    pieces.push((layer, points));
+                }
+            }
+            "LINE" => {
+                let points = vec![
+                    apply(&m, entity.point(10, 20)?),
+                    apply(&m, entity.point(11, 21)?),
+                ];
+                pieces.push((layer, points));
+            }
+            _ => {}
+        }
+    }
+
+    for (layer, points) in join_pieces(pieces) {
+        shapes.extend(outline(layer, points));
+    }
+    Ok(shapes)
+}
+
+/// Filled shape of a closed outline
+fn outline(layer: String, points: Vec<[f32; 2]>) -> Option<ImportedShape> {
+    let shape = contours_shape(&[points], FillRule::NonZero)?;
+    Some(ImportedShape { layer, shape })
+}
+
+/// Entities of the ENTITIES section
+fn read_entities(text: &str) -> Result<Vec<Entity<'_>>, String> {
+    let mut lines = text.lines().map(str::trim);
+    let mut groups = Vec::new();
+    while let Some(code) = lines.next() {
+        if code.is_empty() {
+            continue;
+        }
+        let code: i32 = code
+            .parse()
+            .map_err(|_| format!("invalid DXF group code '{}'", code))?;
+        let value = lines
+            .next()
+            .ok_or_else(|| "truncated DXF file".to_string())?;
+        groups.push((code, value));
+    }
+
+    let start = groups
+        .windows(2)
+        .position(|w| w[0] == (0, "SECTION") && w[1] == (2, "ENTITIES"))
+        .ok_or_else(|| "no ENTITIES section in DXF file".to_string())?;
+    let mut entities: Vec<Entity> = Vec::new();
+    for &(code, value) in &groups[start + 2..] {
+        if code == 0 {
+            if value == "ENDSEC" {
+                break;
+            }
+            entities.push(Entity {
+                kind: value,
+                groups: Vec::new(),
+            });
+        } else if let Some(entity) = entities.last_mut() {
+            entity.groups.push((code, value));
+        }
+    }
+    Ok(entities)
+}
+
+/// Sweep angle in (0, 2π] for a counter-clockwise arc
+fn positive_sweep(sweep: f64) -> f64 {
+    let sweep = sweep.rem_euclid(std::f64::consts::TAU);
+    if sweep <= 1e-12 {
+        std::f64::consts::TAU
+    } else {
+        sweep
+    }
+}
+
+/// Grid points of a polyline whose vertices carry a bulge: the tangent of
+/// a quarter of the arc angle to the next vertex (0 for straight segments)
+fn polyline_points(m: &Affine, vertices: &[([f64; 2], f64)], closed: bool) -> Vec<[f32; 2]> {
+    let Some(first) = vertices.first() else {
+        return Vec::new();
+    };
+    let segments = if closed {
+        vertices.len()
+    } else {
+        vertices.len() - 1
+    };
+    let mut points = vec![apply(m, first.0)];
+    for i in 0..segments {
+        let (a, bulge) = vertices[i];
+        let b = vertices[(i + 1) % vertices.len()].0;
+        let chord = [b[0] - a[0], b[1] - a[1]];
+        let length = chord[0].hypot(chord[1]);
+        if bulge == 0.0 || length == 0.0 {
+            points.push(apply(m, b));
+            continue;
+        }
+        let sweep = 4.0 * bulge.atan();
+        // Signed distance from the chord midpoint to the centre (left > 0)
+        let offset = 0.5 * length / (0.5 * sweep).tan();
+        let center = [
+            0.5 * (a[0] + b[0]) - chord[1] / length * offset,
+            0.5 * (a[1] + b[1]) + chord[0] / length * offset,
+        ];
+        let radius = (a[0] - center[0]).hypot(a[1] - center[1]);
+        let start = (a[1] - center[1]).atan2(a[0] - center[0]);
+        let arc = arc_points(m, center, [radius, radius], 0.0, start, sweep);
+        points.extend_from_slice(&arc[1..]);
+    }
+    points
+}
+
+/// Chain open pieces of the same layer into closed loops
+fn join_pieces(mut pieces: Vec<(String, Vec<[f32; 2]>)>) -> Vec<(String, Vec<[f32; 2]>)> {
+    let near = |a: [f32; 2], b: [f32; 2]| {
+        (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) <= JOIN_TOLERANCE * JOIN_TOLERANCE
+    };
+    pieces.retain(|(_, points)| points.len() >= 2);
+    pieces.reverse(); // Pop in file order
+
+    let mut loops = Vec::new();
+    while let Some((layer, mut chain)) = pieces.pop() {
+        loop {
+            let (first, end) = (chain[0], chain[chain.len() - 1]);
+            if chain.len() > 2 && near(first, end) {
+                loops.push((layer, chain));
+                break;
+            }
+            let next = pieces
+                .iter()
+                .rposition(|(l, p)| *l == layer && (near(p[0], end) || near(p[p.len() - 1], end)));
+            let Some(index) = next else {
+                break; // Never closes
+            };
+            let (_, mut piece) = pieces.remove(index);
+            if !near(piece[0], end) {
+                piece.reverse();
+            }
+            chain.extend_from_slice(&piece[1..]);
+        }
+    }
+    loops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::IDENTITY;
+
+    /// DXF text of an ENTITIES section from (code, value) pairs
+    fn dxf(groups: &[(i32, &str)]) -> String {
+        let mut text = String::from("0\nSECTION\n2\nENTITIES\n");
+        for (code, value) in groups {
+            text.push_str(&format!("{}\n{}\n", code, value));
+        }
+        text + "0\nENDSEC\n0\nEOF\n"
+    }
+
+    #[test]
+    fn test_dxf_polylines_and_circles() {
+        let text = dxf(&[
+            // Closed square with one side bulged outwards into a semicircle
+            (0, "LWPOLYLINE"),
+            (8, "Lens"),
+            (70, "1"),
+            (10, "0"),
+            (20, "0"),
+            (10, "10"),
+            (20, "0"),
+            (42, "1"),
+            (10, "10"),
+            (20, "10"),
+            (10, "0"),
+            (20, "10"),
+            (0, "CIRCLE"),
+            (8, "Mirror"),
+            (10, "30"),
+            (20, "5"),
+            (40, "2"),
+        ]);
+        let shapes = parse_dxf(&text, &IDENTITY).unwrap();
+        assert_eq!(shapes.len(), 2);
+        assert_eq!(shapes[0].layer, "Lens");
+        // y is mirrored: the square spans grid y in [-10, 0]
+        let lens = &shapes[0].shape;
+        assert!(lens.contains(5.0, -5.0));
+        assert!(lens.contains(14.5, -5.0));
+        assert!(!lens.contains(5.0, 5.0));
+        assert_eq!(shapes[1].shape.kind(), "ellipse");
+        assert!(shapes[1].shape.contains(30.0, -5.0));
+
+        assert!(parse_dxf("0\nSECTION\n2\nHEADER\n0\nENDSEC\n", &IDENTITY).is_err());
+        assert!(parse_dxf("zero\nSECTION\n", &IDENTITY).is_err());
+    }
+
+    #[test]
+    fn test_dxf_joined_outlines() {
+        // A D shape from a line and an arc, plus a dangling line
+        let text = dxf(&[
+            (0, "ARC"),
+            (8, "Reflector"),
+            (10, "0"),
+            (20, "0"),
+            (40, "10"),
+            (50, "270"),
+            (51, "90"),
+            (0, "LINE"),
+            (8, "Reflector"),
+            (10, "0"),
+            (20, "10"),
+            (11, "0"),
+            (21, "-10"),
+            (0, "LINE"),
+            (8, "Other"),
+            (10, "0"),
+            (20, "10"),
+            (11, "0"),
+            (21, "-10"),
+        ]);
+        let shapes = parse_dxf(&text, &IDENTITY).unwrap();
+        assert_eq!(shapes.len(), 1);
+        assert_eq!(shapes[0].layer, "Reflector");
+        assert!(shapes[0].shape.contains(5.0, 0.0));
+        assert!(!shapes[0].shape.contains(-5.0, 0.0));
+    }
+}
@@ -0,0 +1,889 @@
+//! 3D FDTD Grid Implementation
+//!
+//! Full-vector electromagnetic solver on a 3D Yee lattice (Ex, Ey, Ez,
+//! Hx, Hy, Hz). Shares the material and source subsystems with the 2D
+//! grid, and exposes 2D slices so the existing renderer can show any plane.
+//!
+//! The outer faces are PEC walls, optionally lined with a CPML layer.
+//! Dispersive materials run their ADE poles on all three E components.
+//!
+//! Author: Mehmet Gümüş (github.com/SpaceEngineerSS)
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::boundary::BoundaryType;
+use crate::cpml::CPMLParams;
+use crate::cpml3d::CPML3D;
+use crate::dispersion::DispersiveMedia;
+use crate::materials::{get_material_by_id, rescale_coefficients, Material};
+use crate::sources::Probe;
+
+/// Physical constants (normalized units)
+const DX: f32 = 1.0; // Grid spacing
+const DEFAULT_COURANT: f32 = 0.5; // Default Courant number S = cΔt/Δx
+const CFL_LIMIT: f32 = 0.577_350_26; // 3D stability limit 1/√3
+
+/// Field component selector for 3D grids
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FieldComponent {
+    Ex = 0,
+    Ey = 1,
+    Ez = 2,
+    Hx = 3,
+    Hy = 4,
+    Hz = 5,
+}
+
+/// Orientation of a 2D slice through a 3D grid
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SlicePlane {
+    /// Constant z, width = nx, height = ny
+    XY = 0,
+    /// Constant y, width = nx, height = nz
+    XZ = 1,
+    /// Constant x, width = ny, height = nz
+    YZ = 2,
+}
+
+/// 3D FDTD grid holding all six field components
+///
+/// Memory layout: flat arrays indexed (k * ny + j) * nx + i.
+/// Field positions follow Yee staggering:
+/// - Ex at (i+1/2, j, k), Ey at (i, j+1/2, k), Ez at (i, j, k+1/2)
+/// - Hx at (i, j+1/2, k+1/2), Hy at (i+1/2, j, k+1/2), Hz at (i+1/2, j+1/2, k)
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct FDTDGrid3D {
+    nx: usize,
+    ny: usize,
+    nz: usize,
+
+    // Electric field components
+    ex: Vec<f32>,
+    ey: Vec<f32>,
+    ez: Vec<f32>,
+
+    // Magnetic field components
+    hx: Vec<f32>,
+    hy: Vec<f32>,
+    hz: Vec<f32>,
+
+    // Material coefficients (shared by the three E / three H components of a cell)
+    ca: Vec<f32>,
+    cb: Vec<f32>,
+    da: Vec<f32>,
+    db: Vec<f32>,
+
+    // Boundary handling (PEC walls, optionally lined with CPML)
+    boundary: BoundaryType,
+    cpml_params: CPMLParams,
+    cpml: Option<CPML3D>,
+
+    // Polarization state of dispersive cells (allocated on first dispersive paint)
+    dispersion: Option<DispersiveMedia>,
+
+    // Courant number S = cΔt/Δx (Δt = S in normalized units)
+    courant: f32,
+
+    // Scratch buffer for zero-copy slice access
+    slice: Vec<f32>,
+
+    time_step: u64,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl FDTDGrid3D {
+    /// Create a new 3D grid (at least 4 cells along each axis)
+    /// Smaller sizes are enlarged (see `try_new`).
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(nx: usize, ny: usize, nz: usize) -> FDTDGrid3D {
+        let (nx, ny, nz) = (nx.max(4), ny.max(4), nz.max(4));
+        let size = nx * ny * nz;
+
+        FDTDGrid3D {
+            nx,
+            ny,
+            nz,
+            ex: vec![0.0; size],
+            ey: vec![0.0; size],
+            ez: vec![0.0; size],
+            hx: vec![0.0; size],
+            hy: vec![0.0; size],
+            hz: vec![0.0; size],
+            ca: vec![1.0; size],
+            cb: vec![DEFAULT_COURANT; size],
+            da: vec![1.0; size],
+            db: vec![DEFAULT_COURANT; size],
+            boundary: BoundaryType::PEC,
+            cpml_params: CPMLParams::default(),
+            cpml: None,
+            dispersion: None,
+            courant: DEFAULT_COURANT,
+            slice: Vec::new(),
+            time_step: 0,
+        }
+    }
+
+    /// Get grid size along x
+    pub fn get_nx(&self) -> usize {
+        self.nx
+    }
+
+    /// Get grid size along y
+    pub fn get_ny(&self) -> usize {
+        self.ny
+    }
+
+    /// Get grid size along z
+    pub fn get_nz(&self) -> usize {
+        self.nz
+    }
+
+    /// Get current simulation time step
+    pub fn get_time_step(&self) -> u64 {
+        self.time_step
+    }
+
+    /// Get Courant number (3D CFL limit is 1/√3)
+    pub fn get_courant(&self) -> f32 {
+        self.courant
+    }
+
+    /// Get the 3D CFL stability limit 1/√3 for the Courant number
+    pub fn get_cfl_limit() -> f32 {
+        CFL_LIMIT
+    }
+
+    /// Set the Courant number S = cΔt/Δx
+    ///
+    /// Must lie in (0, 1/√3]. Material coefficients, dispersive poles and the
+    /// CPML layer are recomputed for the new time step; fields are kept.
+    pub fn set_courant(&mut self, courant: f32) -> Result<(), String> {
+        if !(courant > 0.0 && courant <= CFL_LIMIT) {
+            return Err(format!(
+                "Courant number {} is outside the 3D CFL range (0, {:.4}]",
+                courant, CFL_LIMIT
+            ));
+        }
+
+        let old = self.courant;
+        self.courant = courant;
+        for (ca, cb) in self.ca.iter_mut().zip(self.cb.iter_mut()) {
+            (*ca, *cb) = rescale_coefficients(*ca, *cb, old, courant);
+        }
+        for (da, db) in self.da.iter_mut().zip(self.db.iter_mut()) {
+            (*da, *db) = rescale_coefficients(*da, *db, old, courant);
+        }
+        if let Some(media) = self.dispersion.as_mut() {
+            let cb = &self.cb;
+            media.set_dt(courant * DX, |idx| cb[idx] / courant);
+        }
+        self.rebuild_cpml();
+        Ok(())
+    }
+
+    /// Select the outer boundary
+    /// CPML lines the PEC walls with an absorbing layer; any other type
+    /// leaves bare PEC walls.
+    pub fn set_boundary_type(&mut self, boundary: BoundaryType) {
+        self.boundary = match boundary {
+            BoundaryType::CPML => BoundaryType::CPML,
+            _ => BoundaryType::PEC,
+        };
+        self.rebuild_cpml();
+    }
+
+    /// Get the outer boundary (CPML or PEC)
+    pub fn get_boundary_type(&self) -> BoundaryType {
+        self.boundary
+    }
+
+    /// Set CPML layer parameters (used on all six faces)
+    pub fn set_cpml_params(&mut self, params: &CPMLParams) {
+        self.cpml_params = *params;
+        self.rebuild_cpml();
+    }
+
+    /// Get CPML thickness along an axis (0 = x, 1 = y, 2 = z; 0 cells without CPML)
+    pub fn get_cpml_thickness(&self, axis: usize) -> usize {
+        self.cpml
+            .as_ref()
+            .map_or(0, |cpml| cpml.get_thickness(axis))
+    }
+
+    /// Perform one complete FDTD time step
+    pub fn step(&mut self) {
+        self.update_h();
+        self.update_e();
+        self.time_step += 1;
+    }
+
+    /// Run multiple time steps at once
+    pub fn step_n(&mut self, n: u32) {
+        for _ in 0..n {
+            self.step();
+        }
+    }
+
+    /// Update magnetic field components
+    ///
+    /// Implements:
+    /// Hx = da * Hx + db * (∂Ey/∂z - ∂Ez/∂y)
+    /// Hy = da * Hy + db * (∂Ez/∂x - ∂Ex/∂z)
+    /// Hz = da * Hz + db * (∂Ex/∂y - ∂Ey/∂x)
+    pub fn update_h(&mut self) {
+        let (nx, ny, nz) = (self.nx, self.ny, self.nz);
+        let sy = nx;
+        let sz = nx * ny;
+
+        for k in 0..nz - 1 {
+            for j in 0..ny - 1 {
+                for i in 0..nx {
+                    let idx = k * sz + j * sy + i;
+                    let curl =
+                        (self.ey[idx + sz] - self.ey[idx]) - (self.ez[idx + sy] - self.ez[idx]);
+                    self.hx[idx] = self.da[idx] * self.hx[idx] + self.db[idx] * curl;
+                }
+            }
+        }
+
+        for k in 0..nz - 1 {
+            for j in 0..ny {
+                for i in 0..nx - 1 {
+                    let idx = k * sz + j * sy + i;
+                    let curl =
+                        (self.ez[idx + 1] - self.ez[idx]) - (self.ex[idx + sz] - self.ex[idx]);
+                    self.hy[idx] = self.da[idx] * self.hy[idx] + self.db[idx] * curl;
+                }
+            }
+        }
+
+        for k in 0..nz {
+            for j in 0..ny - 1 {
+                for i in 0..nx - 1 {
+                    let idx = k * sz + j * sy + i;
+                    let curl =
+                        (self.ex[idx + sy] - self.ex[idx]) - (self.ey[idx + 1] - self.ey[idx]);
+                    self.hz[idx] = self.da[idx] * self.hz[idx] + self.db[idx] * curl;
+                }
+            }
+        }
+
+        if let Some(cpml) = self.cpml.as_mut() {
+            cpml.update_h(
+                [&mut self.hx, &mut self.hy, &mut self.hz],
+                [&self.ex, &self.ey, &self.ez],
+                &self.db,
+            );
+        }
+    }
+
+    /// Update electric field components
+    ///
+    /// Implements:
+    /// Ex = ca * Ex + cb * (∂Hz/∂y - ∂Hy/∂z)
+    /// Ey = ca * Ey + cb * (∂Hx/∂z - ∂Hz/∂x)
+    /// Ez = ca * Ez + cb * (∂Hy/∂x - ∂Hx/∂y)
+    pub fn update_e(&mut self) {
+        let (nx, ny, nz) = (self.nx, self.ny, self.nz);
+        let sy = nx;
+        let sz = nx * ny;
+
+        if let Some(media) = self.dispersion.as_mut() {
+            media.advance(&[&self.ex, &self.ey, &self.ez]);
+        }
+
+        for k in 1..nz - 1 {
+            for j in 1..ny - 1 {
+                for i in 0..nx - 1 {
+                    let idx = k * sz + j * sy + i;
+                    let curl =
+                        (self.hz[idx] - self.hz[idx - sy]) - (self.hy[idx] - self.hy[idx - sz]);
+                    self.ex[idx] = self.ca[idx] * self.ex[idx] + self.cb[idx] * curl;
+                }
+            }
+        }
+
+        for k in 1..nz - 1 {
+            for j in 0..ny - 1 {
+                for i in 1..nx - 1 {
+                    let idx = k * sz + j * sy + i;
+                    let curl =
+                        (self.hx[idx] - self.hx[idx - sz]) - (self.hz[idx] - self.hz[idx - 1]);
+                    self.ey[idx] = self.ca[idx] * self.ey[idx] + self.cb[idx] * curl;
+                }
+            }
+        }
+
+        for k in 0..nz - 1 {
+            for j in 1..ny - 1 {
+                for i in 1..nx - 1 {
+                    let idx = k * sz + j * sy + i;
+                    let curl =
+                        (self.hy[idx] - self.hy[idx - 1]) - (self.hx[idx] - self.hx[idx - sy]);
+                    self.ez[idx] = self.ca[idx] * self.ez[idx] + self.cb[idx] * curl;
+                }
+            }
+        }
+
+        if let Some(media) = self.dispersion.as_ref() {
+            media.apply(&mut [&mut self.ex, &mut self.ey, &mut self.ez]);
+        }
+
+        if let Some(cpml) = self.cpml.as_mut() {
+            cpml.update_e(
+                [&mut self.ex, &mut self.ey, &mut self.ez],
+                [&self.hx, &self.hy, &self.hz],
+                &self.cb,
+            );
+            self.apply_pec_walls();
+        }
+    }
+
+    /// Place a point pulse in Ez
+    pub fn place_pulse(&mut self, x: usize, y: usize, z: usize, amplitude: f32) {
+        if let Some(idx) = self.index(x, y, z) {
+            self.ez[idx] = amplitude;
+        }
+    }
+
+    /// Add a value to one field component at a point (soft source for an
+    /// external waveform, e.g. `SourceFunction::evaluate`)
+    pub fn add_field_at(
+        &mut self,
+        x: usize,
+        y: usize,
+        z: usize,
+        component: FieldComponent,
+        value: f32,
+    ) {
+        if let Some(idx) = self.index(x, y, z) {
+            self.field_mut(component)[idx] += value;
+        }
+    }
+
+    /// Add sinusoidal soft source to Ez at a point
+    pub fn add_soft_source(
+        &mut self,
+        x: usize,
+        y: usize,
+        z: usize,
+        frequency: f32,
+        amplitude: f32,
+    ) {
+        let t = self.time_step as f32;
+        let value = amplitude * (2.0 * std::f32::consts::PI * frequency * t).sin();
+        self.add_field_at(x, y, z, FieldComponent::Ez, value);
+    }
+
+    /// Get one field component at a point (0 outside the grid)
+    pub fn get_field_at(&self, x: usize, y: usize, z: usize, component: FieldComponent) -> f32 {
+        self.index(x, y, z)
+            .map_or(0.0, |idx| self.field(component)[idx])
+    }
+
+    /// Record one field component at (probe x, probe y, z) into a probe
+    pub fn record_probe(&self, probe: &mut Probe, z: usize, component: FieldComponent) {
+        probe.record_value(self.get_field_at(probe.get_x(), probe.get_y(), z, component));
+    }
+
+    /// Fill a box with a material (corners inclusive, clamped to the grid)
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_material_box(
+        &mut self,
+        x1: usize,
+        y1: usize,
+        z1: usize,
+        x2: usize,
+        y2: usize,
+        z2: usize,
+        material: &Material,
+    ) {
+        let coefficients = cell_coefficients(material, self.courant);
+        let x_range = x1.min(x2).min(self.nx - 1)..=x1.max(x2).min(self.nx - 1);
+        let y_range = y1.min(y2).min(self.ny - 1)..=y1.max(y2).min(self.ny - 1);
+        let z_range = z1.min(z2).min(self.nz - 1)..=z1.max(z2).min(self.nz - 1);
+
+        for z in z_range {
+            for y in y_range.clone() {
+                for x in x_range.clone() {
+                    let idx = (z * self.ny + y) * self.nx + x;
+                    self.set_material(idx, material, coefficients);
+                }
+            }
+        }
+    }
+
+    /// Set a single cell's material by ID
+    /// 0=Vacuum, 1=Glass, 2=Water, 3=Metal, 4=Absorber, 5=Crystal, 6=Silicon
+    pub fn set_cell_material(&mut self, x: usize, y: usize, z: usize, material_id: u32) {
+        if let Some(idx) = self.index(x, y, z) {
+            let material = get_material_by_id(material_id);
+            let coefficients = cell_coefficients(&material, self.courant);
+            self.set_material(idx, &material, coefficients);
+        }
+    }
+
+    /// Paint a filled sphere with the specified material
+    pub fn paint_sphere(&mut self, cx: i32, cy: i32, cz: i32, radius: i32, material_id: u32) {
+        let material = get_material_by_id(material_id);
+        let coefficients = cell_coefficients(&material, self.courant);
+        let r2 = radius * radius;
+        let range = |c: i32, n: usize| {
+            let lo = (c - radius).max(0);
+            let hi = (c + radius).min(n as i32 - 1);
+            lo..=hi
+        };
+
+        for z in range(cz, self.nz) {
+            for y in range(cy, self.ny) {
+                for x in range(cx, self.nx) {
+                    let (dx, dy, dz) = (x - cx, y - cy, z - cz);
+                    if dx * dx + dy * dy + dz * dz <= r2 {
+                        let idx = (z as usize * self.ny + y as usize) * self.nx + x as usize;
+                        self.set_material(idx, &material, coefficients);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Clear only material settings (keep fields)
+    pub fn clear_materials(&mut self) {
+        self.ca.fill(1.0);
+        self.cb.fill(self.courant);
+        self.da.fill(1.0);
+        self.db.fill(self.courant);
+        self.dispersion = None;
+    }
+
+    /// Get number of cells carrying dispersive poles
+    pub fn get_dispersive_cell_count(&self) -> usize {
+        self.dispersion.as_ref().map_or(0, |media| media.len())
+    }
+
+    /// Reset the simulation to initial state
+    pub fn reset(&mut self) {
+        for field in [
+            &mut self.ex,
+            &mut self.ey,
+            &mut self.ez,
+            &mut self.hx,
+            &mut self.hy,
+            &mut self.hz,
+        ] {
+            field.fill(0.0);
+        }
+        if let Some(cpml) = self.cpml.as_mut() {
+            cpml.reset();
+        }
+        if let Some(media) = self.dispersion.as_mut() {
+            media.reset();
+        }
+        self.time_step = 0;
+    }
+
+    /// Get sum of squared field components (proportional to energy in vacuum)
+    pub fn get_field_energy(&self) -> f32 {
+        [&self.ex, &self.ey, &self.ez, &self.hx, &self.hy, &self.hz]
+            .iter()
+            .map(|field| field.iter().map(|v| v * v).sum::<f32>())
+            .sum()
+    }
+
+    /// Get slice width in cells
+    pub fn get_slice_width(&self, plane: SlicePlane) -> usize {
+        match plane {
+            SlicePlane::XY | SlicePlane::XZ => self.nx,
+            SlicePlane::YZ => self.ny,
+        }
+    }
+
+    /// Get slice height in cells
+    pub fn get_slice_height(&self, plane: SlicePlane) -> usize {
+        match plane {
+            SlicePlane::XY => self.ny,
+            SlicePlane::XZ | SlicePlane::YZ => self.nz,
+        }
+    }
+
+    /// Copy a plane of one field component into a new row-major array
+    /// (same layout as the 2D grid: width × height, row by row)
+    pub fn get_slice(
+        &self,
+        plane: SlicePlane,
+        index: usize,
+        component: FieldComponent,
+    ) -> Vec<f32> {
+        let mut out = vec![0.0; self.get_slice_width(plane) * self.get_slice_height(plane)];
+        self.slice_into(plane, index, component, &mut out);
+        out
+    }
+
+    /// Extract a plane into the internal slice buffer (see `get_slice_ptr`)
+    /// Returns the number of values written
+    pub fn extract_slice(
+        &mut self,
+        plane: SlicePlane,
+        index: usize,
+        component: FieldComponent,
+    ) -> usize {
+        let mut slice = std::mem::take(&mut self.slice);
+        slice.resize(
+            self.get_slice_width(plane) * self.get_slice_height(plane),
+            0.0,
+        );
+        self.slice_into(plane, index, component, &mut slice);
+        self.slice = slice;
+        self.slice.len()
+    }
+
+    /// Get raw pointer to the last extracted slice for zero-copy WebGL access
+    ///
+    /// # Safety
+    /// The returned pointer is valid until the next `extract_slice` call.
+    #[cfg(feature = "wasm")]
+    pub fn get_slice_ptr(&self) -> *const f32 {
+        self.slice.as_ptr()
+    }
+}
+
+impl FDTDGrid3D {
+    /// Create a grid, rejecting sizes `new` would enlarge (under 4 cells)
+    pub fn try_new(nx: usize, ny: usize, nz: usize) -> Result<FDTDGrid3D, String> {
+        if nx < 4 || ny < 4 || nz < 4 {
+            return Err(format!("grid size {}x{}x{} is too small", nx, ny, nz));
+        }
+        nx.checked_mul(ny)
+            .and_then(|n| n.checked_mul(nz))
+            .ok_or_else(|| format!("grid size {}x{}x{} is too large", nx, ny, nz))?;
+        Ok(FDTDGrid3D::new(nx, ny, nz))
+    }
+
+    /// Last slice written by `extract_slice`
+    pub fn slice(&self) -> &[f32] {
+        &self.slice
+    }
+
+    /// Get one field component as a flat array ((k * ny + j) * nx + i)
+    pub fn field(&self, component: FieldComponent) -> &[f32] {
+        match component {
+            FieldComponent::Ex => &self.ex,
+            FieldComponent::Ey => &self.ey,
+            FieldComponent::Ez => &self.ez,
+            FieldComponent::Hx => &self.hx,
+            FieldComponent::Hy => &self.hy,
+            FieldComponent::Hz => &self.hz,
+        }
+    }
+
+    fn field_mut(&mut self, component: FieldComponent) -> &mut [f32] {
+        match component {
+            FieldComponent::Ex => &mut self.ex,
+            FieldComponent::Ey => &mut self.ey,
+            FieldComponent::Ez => &mut self.ez,
+            FieldComponent::Hx => &mut self.hx,
+            FieldComponent::Hy => &mut self.hy,
+            FieldComponent::Hz => &mut self.hz,
+        }
+    }
+
+    /// Write a plane of one field component into `out` (width × height, row-major)
+    /// Out-of-range plane indices produce zeros
+    pub fn slice_into(
+        &self,
+        plane: SlicePlane,
+        index: usize,
+        component: FieldComponent,
+        out: &mut [f32],
+    ) {
+        let width = self.get_slice_width(plane);
+        let height = self.get_slice_height(plane);
+        let field = self.field(component);
+        let depth = match plane {
+            SlicePlane::XY => self.nz,
+            SlicePlane::XZ => self.ny,
+            SlicePlane::YZ => self.nx,
+        };
+
+        for (n, value) in out.iter_mut().take(width * height).enumerate() {
+            if index >= depth {
+                *value = 0.0;
+                continue;
+            }
+            let (u, v) = (n % width, n / width);
+            let (x, y, z) = match plane {
+                SlicePlane::XY => (u, v, index),
+                SlicePlane::XZ => (u, index, v),
+                SlicePlane::YZ => (index, u, v),
+            };
+            *value = field[(z * self.ny + y) * self.nx + x];
+        }
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> Option<usize> {
+        if x < self.nx && y < self.ny && z < self.nz {
+            Some((z * self.ny + y) * self.nx + x)
+        } else {
+            None
+        }
+    }
+
+    /// Write one cell's coefficients and (un)register its dispersive poles
+    fn set_material(&mut self, idx: usize, material: &Material, coefficients: [f32; 4]) {
+        self.set_coefficients(idx, coefficients);
+        if material.is_dispersive() {
+            let (size, dt) = (self.ex.len(), self.courant * DX);
+            self.dispersion
+                .get_or_insert_with(|| DispersiveMedia::new(size, dt))
+                .assign(idx, material.poles(), self.cb[idx] / self.courant);
+        } else if let Some(media) = self.dispersion.as_mut() {
+            media.remove(idx);
+        }
+    }
+
+    fn set_coefficients(&mut self, idx: usize, coefficients: [f32; 4]) {
+        let [ca, cb, da, db] = coefficients;
+        self.ca[idx] = ca;
+        self.cb[idx] = cb;
+        self.da[idx] = da;
+        self.db[idx] = db;
+        if cb == 0.0 {
+            // PEC: force E to zero immediately
+            self.ex[idx] = 0.0;
+            self.ey[idx] = 0.0;
+            self.ez[idx] = 0.0;
+        }
+    }
+
+    fn rebuild_cpml(&mut self) {
+        self.cpml = match self.boundary {
+            BoundaryType::CPML => Some(CPML3D::new(
+                self.nx,
+                self.ny,
+                self.nz,
+                &self.cpml_params,
+                self.courant * DX,
+            )),
+            _ => None,
+        };
+    }
+
+    /// Zero tangential E on the outer faces (and the E samples that lie
+    /// half a cell outside the far faces) after the CPML correction
+    fn apply_pec_walls(&mut self) {
+        let (nx, ny, nz) = (self.nx, self.ny, self.nz);
+        let (sy, sz) = (nx, nx * ny);
+
+        // Each face zeroes its two tangential components; the far face also
+        // zeroes the normal one, whose sample lies outside the grid
+        for (k, far) in [(0, false), (nz - 1, true)] {
+            for idx in k * sz..(k + 1) * sz {
+                self.ex[idx] = 0.0;
+                self.ey[idx] = 0.0;
+                if far {
+                    self.ez[idx] = 0.0;
+                }
+            }
+        }
+
+        for (j, far) in [(0, false), (ny - 1, true)] {
+            for k in 0..nz {
+                for i in 0..nx {
+                    let idx = k * sz + j * sy + i;
+                    self.ex[idx] = 0.0;
+                    self.ez[idx] = 0.0;
+                    if far {
+                        self.ey[idx] = 0.0;
+                    }
+                }
+            }
+        }
+
+        for (i, far) in [(0, false), (nx - 1, true)] {
+            for k in 0..nz {
+                for j in 0..ny {
+                    let idx = k * sz + j * sy + i;
+                    self.ey[idx] = 0.0;
+                    self.ez[idx] = 0.0;
+                    if far {
+                        self.ex[idx] = 0.0;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Update coefficients [ca, cb, da, db] of a material in normalized units
+fn cell_coefficients(material: &Material, courant: f32) -> [f32; 4] {
+    let dt = courant * DX;
+    let (ca, cb) = material.electric_coefficients(dt, courant);
+    let (da, db) = material.magnetic_coefficients(dt, courant);
+    [ca, cb, da, db]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::{MaterialPresets, Pole};
+    use crate::sources::SourceFunction;
+
+    #[test]
+    fn test_grid3d_creation() {
+        let grid = FDTDGrid3D::new(20, 16, 12);
+        assert_eq!(grid.get_nx(), 20);
+        assert_eq!(grid.get_nz(), 12);
+        assert_eq!(grid.field(FieldComponent::Hz).len(), 20 * 16 * 12);
+        assert!(grid.get_courant() < 1.0 / 3.0_f32.sqrt());
+    }
+
+    #[test]
+    fn test_pulse_spreads_symmetrically() {
+        let mut grid = FDTDGrid3D::new(31, 31, 31);
+        grid.place_pulse(15, 15, 15, 1.0);
+        grid.step_n(8);
+
+        let ez = |x, y, z| grid.get_field_at(x, y, z, FieldComponent::Ez);
+        assert!(ez(15, 15, 15).is_finite());
+        assert!(ez(19, 15, 15).abs() > 1e-6);
+        assert!((ez(19, 15, 15) - ez(11, 15, 15)).abs() < 1e-6);
+        assert!((ez(15, 19, 15) - ez(19, 15, 15)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_cpml3d_absorbs() {
+        let run = |boundary| {
+            let mut grid = FDTDGrid3D::new(40, 40, 40);
+            grid.set_cpml_params(&CPMLParams::new(8, 3.0, 0.75, 5.0, 0.05));
+            grid.set_boundary_type(boundary);
+            // Zero-mean soft source: no static charge is left behind
+            let source = SourceFunction::new_modulated_gaussian(0.05, 30.0, 10.0, 1.0);
+            for t in 0..60 {
+                grid.add_field_at(20, 20, 20, FieldComponent::Ez, source.evaluate(t as f32));
+                grid.step();
+            }
+            let initial = grid.get_field_energy();
+            grid.step_n(200);
+            grid.get_field_energy() / initial
+        };
+
+        let pec = run(BoundaryType::PEC);
+        let cpml = run(BoundaryType::CPML);
+        assert!(pec > 0.5, "PEC box keeps its energy: {}", pec);
+        assert!(cpml < 0.01, "CPML residual energy: {}", cpml);
+    }
+
+    #[test]
+    fn test_metal_box_blocks_field() {
+        let mut grid = FDTDGrid3D::new(24, 24, 24);
+        grid.set_material_box(14, 4, 4, 18, 20, 20, &MaterialPresets::metal());
+        grid.place_pulse(8, 12, 12, 1.0);
+        grid.step_n(40);
+
+        for c in [FieldComponent::Ex, FieldComponent::Ey, FieldComponent::Ez] {
+            assert_eq!(grid.get_field_at(16, 12, 12, c), 0.0);
+        }
+        assert!(grid.get_field_at(8, 12, 14, FieldComponent::Ez).abs() > 0.0);
+    }
+
+    #[test]
+    fn test_slice_extraction() {
+        let mut grid = FDTDGrid3D::new(12, 10, 8);
+        grid.add_field_at(3, 7, 5, FieldComponent::Hy, 2.0);
+
+        let xy = grid.get_slice(SlicePlane::XY, 5, FieldComponent::Hy);
+        assert_eq!(xy.len(), 12 * 10);
+        assert_eq!(xy[7 * 12 + 3], 2.0);
+
+        let xz = grid.get_slice(SlicePlane::XZ, 7, FieldComponent::Hy);
+        assert_eq!(xz[5 * 12 + 3], 2.0);
+
+        assert_eq!(
+            grid.extract_slice(SlicePlane::YZ, 3, FieldComponent::Hy),
+            10 * 8
+        );
+        let yz = grid.slice();
+        assert_eq!(yz[5 * 10 + 7], 2.0);
+
+        let outside = grid.get_slice(SlicePlane::YZ, 99, FieldComponent::Hy);
+        assert!(outside.iter().all(|&v| v == 0.0));
+    }
+
+    #[test]
+    fn test_probe_records_3d_field() {
+        let mut grid = FDTDGrid3D::new(16, 16, 16);
+        let mut probe = Probe::new(10, 8, 32);
+        grid.place_pulse(8, 8, 8, 1.0);
+        for _ in 0..32 {
+            grid.step();
+            grid.record_probe(&mut probe, 8, FieldComponent::Ez);
+        }
+        assert!(probe.get_samples().iter().any(|v| v.abs() > 1e-4));
+    }
+
+    #[test]
+    fn test_courant_validation() {
+        let mut grid = FDTDGrid3D::new(12, 12, 12);
+        // Stable in 2D but not in 3D
+        let err = grid.set_courant(0.65).unwrap_err();
+        assert!(err.contains("3D CFL"));
+        assert_eq!(grid.get_courant(), DEFAULT_COURANT);
+
+        grid.set_material_box(2, 2, 2, 5, 5, 5, &MaterialPresets::glass());
+        grid.set_courant(0.3).unwrap();
+        let expected = cell_coefficients(&MaterialPresets::glass(), 0.3);
+        assert!((grid.cb[grid.index(3, 3, 3).unwrap()] - expected[1]).abs() < 1e-6);
+        assert!((grid.cb[0] - 0.3).abs() < 1e-6);
+
+        grid.place_pulse(6, 6, 6, 1.0);
+        grid.step_n(200);
+        assert!(grid.get_field_energy().is_finite());
+    }
+
+    #[test]
+    fn test_debye_relaxation_3d() {
+        // ε∞·E + P is conserved, so E relaxes to ε∞/εs of its initial value
+        // (the wall disturbance has not reached the centre after 20 steps)
+        let response = |material: &Material| {
+            let mut grid = FDTDGrid3D::new(45, 45, 45);
+            grid.set_material_box(0, 0, 0, 44, 44, 44, material);
+            for field in [&mut grid.ex, &mut grid.ey, &mut grid.ez] {
+                field.fill(1.0);
+            }
+            grid.step_n(20);
+            let count = grid.get_dispersive_cell_count();
+            (count, grid.get_field_at(22, 22, 22, FieldComponent::Ex))
+        };
+
+        let debye = Material::new(1.0, 1.0, 0.0).with_pole(Pole::debye(3.0, 5.0));
+        let (count, ex) = response(&debye);
+        assert_eq!(count, 45 * 45 * 45);
+        assert!((ex - 0.25).abs() < 2e-3, "{}", ex);
+
+        // Without poles the same box keeps the field
+        let (count, ex) = response(&Material::new(1.0, 1.0, 0.0));
+        assert_eq!(count, 0);
+        assert!((ex - 1.0).abs() < 1e-6, "{}", ex);
+    }
+
+    #[test]
+    fn test_drude_box_stays_stable() {
+        let mut grid = FDTDGrid3D::new(16, 16, 16);
+        let drude = Material::new(1.0, 1.0, 0.0).with_pole(Pole::drude(0.8, 0.05));
+        grid.set_material_box(4, 4, 4, 11, 11, 11, &drude);
+        assert_eq!(grid.get_dispersive_cell_count(), 512);
+        grid.place_pulse(8, 8, 8, 1.0);
+        grid.step_n(300);
+        assert!(grid.get_field_energy().is_finite());
+
+        grid.set_material_box(4, 4, 4, 7, 11, 11, &MaterialPresets::glass());
+        assert_eq!(grid.get_dispersive_cell_count(), 256);
+        grid.clear_materials();
+        assert_eq!(grid.get_dispersive_cell_count(), 0);
+    }
+}
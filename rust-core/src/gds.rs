@@ -0,0 +1,834 @@
+//! GDSII Layout Import
+//!
+//! Reads the binary GDSII stream format used by photonic and electronic IC
+//! layout tools: structures (cells) holding BOUNDARY, BOX and PATH elements
+//! plus SREF/AREF instances of other structures. The hierarchy is
+//! flattened on import, one layer/datatype at a time.
+//!
+//! Layout coordinates are database units (DBU), converted to metres with
+//! the library's UNITS record. An extraction window (µm) picks the part of
+//! the layout that is rasterized; its top-left corner lands on cell (0, 0)
+//! and layout y (pointing up) is mirrored onto the grid's downward y.
+//!
+//! Paths with round ends (pathtype 1) become round-capped strokes, other
+//! paths are outlined with mitred joins. TEXT and NODE elements are
+//! ignored, as are absolute magnification/rotation flags. OASIS files are
+//! not read; convert them to GDSII first (e.g. with KLayout).
+
+use std::collections::HashMap;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::csg::CsgOp;
+use crate::import::{apply, compose, contours_shape, linear_scale, Affine};
+use crate::raster::union_bounds;
+use crate::shapes::{FillRule, Shape};
+
+/// Deepest structure nesting followed (guards against reference cycles)
+const MAX_DEPTH: usize = 64;
+
+/// Metres per micrometre (window coordinates)
+const MICRON: f64 = 1e-6;
+
+// Record types used by the reader
+const HEADER: u8 = 0x00;
+const BGNLIB: u8 = 0x01;
+const UNITS: u8 = 0x03;
+const ENDLIB: u8 = 0x04;
+const BGNSTR: u8 = 0x05;
+const STRNAME: u8 = 0x06;
+const ENDSTR: u8 = 0x07;
+const BOUNDARY: u8 = 0x08;
+const PATH: u8 = 0x09;
+const SREF: u8 = 0x0A;
+const AREF: u8 = 0x0B;
+const TEXT: u8 = 0x0C;
+const LAYER: u8 = 0x0D;
+const DATATYPE: u8 = 0x0E;
+const WIDTH: u8 = 0x0F;
+const XY: u8 = 0x10;
+const ENDEL: u8 = 0x11;
+const SNAME: u8 = 0x12;
+const COLROW: u8 = 0x13;
+const NODE: u8 = 0x15;
+const STRANS: u8 = 0x1A;
+const MAG: u8 = 0x1B;
+const ANGLE: u8 = 0x1C;
+const PATHTYPE: u8 = 0x21;
+const BOX: u8 = 0x2D;
+const BOXTYPE: u8 = 0x2E;
+const BGNEXTN: u8 = 0x30;
+const ENDEXTN: u8 = 0x31;
+
+/// Geometry of a structure (coordinates in DBU)
+#[derive(Clone, Debug)]
+enum Element {
+    /// Closed polygon (BOUNDARY, or BOX with its boxtype as datatype)
+    Polygon {
+        layer: i16,
+        datatype: i16,
+        points: Vec<[f64; 2]>,
+    },
+    Path {
+        layer: i16,
+        datatype: i16,
+        points: Vec<[f64; 2]>,
+        width: f64,
+        pathtype: i16,
+        /// Extensions past the first and last point
+        extensions: [f64; 2],
+    },
+    /// Placement of `columns` × `rows` copies of another structure
+    Reference {
+        name: String,
+        /// Rotation, magnification and reflection
+        orientation: Affine,
+        origin: [f64; 2],
+        columns: u16,
+        rows: u16,
+        /// Displacement between neighbouring columns and rows
+        steps: [[f64; 2]; 2],
+    },
+}
+
+/// Element fields collected between its first record and ENDEL
+#[derive(Default)]
+struct Pending {
+    kind: u8,
+    layer: i16,
+    datatype: i16,
+    points: Vec<[f64; 2]>,
+    width: f64,
+    pathtype: i16,
+    extensions: [f64; 2],
+    name: String,
+    strans: u16,
+    magnification: Option<f64>,
+    angle: f64,
+    colrow: [u16; 2],
+}
+
+impl Pending {
+    fn finish(self) -> Result<Option<Element>, String> {
+        let element = match self.kind {
+            BOUNDARY | BOX => Element::Polygon {
+                layer: self.layer,
+                datatype: self.datatype,
+                points: self.points,
+            },
+            PATH => {
+                let half = 0.5 * self.width.abs();
+                let extensions = match self.pathtype {
+                    2 => [half, half],
+                    4 => self.extensions,
+                    _ => [0.0, 0.0],
+                };
+                Element::Path {
+                    layer: self.layer,
+                    datatype: self.datatype,
+                    points: self.points,
+                    width: self.width.abs(),
+                    pathtype: self.pathtype,
+                    extensions,
+                }
+            }
+            SREF | AREF => {
+                let origin = *self
+                    .points
+                    .first()
+                    .ok_or_else(|| format!("reference to {} without a position", self.name))?;
+                let (columns, rows) = (self.colrow[0].max(1), self.colrow[1].max(1));
+                let steps = if self.kind == AREF {
+                    let [_, column, row] = self.points[..] else {
+                        return Err(format!("array of {} needs three points", self.name));
+                    };
+                    [
+                        [
+                            (column[0] - origin[0]) / columns as f64,
+                            (column[1] - origin[1]) / columns as f64,
+                        ],
+                        [
+                            (row[0] - origin[0]) / rows as f64,
+                            (row[1] - origin[1]) / rows as f64,
+                        ],
+                    ]
+                } else {
+                    [[0.0; 2]; 2]
+                };
+                let magnification = self.magnification.unwrap_or(1.0);
+                let (sin, cos) = self.angle.to_radians().sin_cos();
+                // Reflection about x is applied before rotation
+                let mirror = if self.strans & 0x8000 != 0 { -1.0 } else { 1.0 };
+                let orientation = [
+                    magnification * cos,
+                    magnification * sin,
+                    -magnification * sin * mirror,
+                    magnification * cos * mirror,
+                    0.0,
+                    0.0,
+                ];
+                Element::Reference {
+                    name: self.name,
+                    orientation,
+                    origin,
+                    columns,
+                    rows,
+                    steps,
+                }
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(element))
+    }
+}
+
+/// Parsed GDSII library
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct GdsLibrary {
+    name: String,
+    /// Metres per database unit
+    database_unit: f64,
+    structures: Vec<(String, Vec<Element>)>,
+    index: HashMap<String, usize>,
+}
+
+/// Layer, cell, window and resolution of a layout import
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct GdsSelection {
+    layer: i16,
+    datatype: Option<i16>,
+    cell: Option<String>,
+    window: Option<[f64; 4]>,
+    cell_size: Option<f64>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl GdsSelection {
+    /// Select a layer and datatype (negative datatypes select all of them)
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(layer: i16, datatype: i16) -> GdsSelection {
+        GdsSelection {
+            layer,
+            datatype: (datatype >= 0).then_some(datatype),
+            cell: None,
+            window: None,
+            cell_size: None,
+        }
+    }
+
+    /// Import from a named cell instead of the library's top cell
+    pub fn set_cell(&mut self, name: &str) {
+        self.cell = Some(name.to_string());
+    }
+
+    /// Extract the window between two corners (µm); by default the
+    /// bounding box of the selected layer is used
+    pub fn set_window(&mut self, x0: f64, y0: f64, x1: f64, y1: f64) {
+        if [x0, y0, x1, y1].iter().all(|v| v.is_finite()) && x0 != x1 && y0 != y1 {
+            self.window = Some([x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)]);
+        }
+    }
+
+    /// Rasterize with this cell size (metres), which also becomes the
+    /// grid's cell size; by default the grid's current cell size is kept
+    /// Non-positive sizes are ignored.
+    pub fn set_cell_size(&mut self, metres: f64) {
+        if metres.is_finite() && metres > 0.0 {
+            self.cell_size = Some(metres);
+        }
+    }
+}
+
+impl GdsSelection {
+    /// Cell size requested for the import, if any (metres)
+    pub fn cell_size(&self) -> Option<f64> {
+        self.cell_size
+    }
+
+    fn matches(&self, layer: i16, datatype: i16) -> bool {
+        layer == self.layer && self.datatype.is_none_or(|d| d == datatype)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl GdsLibrary {
+    /// Parse a GDSII stream file
+    pub fn from_bytes(data: &[u8]) -> Result<GdsLibrary, String> {
+        let mut library = GdsLibrary {
+            name: String::new(),
+            database_unit: 0.0,
+            structures: Vec::new(),
+            index: HashMap::new(),
+        };
+        let mut structure: Option<(String, Vec<Element>)> = None;
+        let mut pending: Option<Pending> = None;
+        let mut position = 0;
+        let mut first = true;
+
+        while position + 4 <= data.len() {
+            let length = u16::from_be_bytes([data[position], data[position + 1]]) as usize;
+            let (record, payload) = (data[position + 2], position + 4);
+            if length < 4 || position + length > data.len() {
+                if first {
+                    return Err("not a GDSII file".to_string());
+                }
+                return Err(format!("corrupt GDSII record at byte {}", position));
+            }
+            let bytes = &data[payload..position + length];
+            position += length;
+            if first && record != HEADER {
+                return Err("not a GDSII file (no HEADER record)".to_string());
+            }
+            first = false;
+
+            match record {
+                BGNLIB | HEADER => {}
+                0x02 => library.name = ascii(bytes),
+                UNITS => {
+                    if bytes.len() < 16 {
+                        return Err("truncated UNITS record".to_string());
+                    }
+                    library.database_unit = real8(&bytes[8..16]);
+                }
+                ENDLIB => break,
+                BGNSTR => structure = Some((String::new(), Vec::new())),
+                STRNAME => {
+                    if let Some(s) = structure.as_mut() {
+                        s.0 = ascii(bytes);
+                    }
+                }
+                ENDSTR => {
+                    if let Some((name, elements)) = structure.take() {
+                        library.index.insert(name.clone(), library.structures.len());
+                        library.structures.push((name, elements));
+                    }
+                }
+                BOUNDARY | BOX | PATH | SREF | AREF | TEXT | NODE => {
+                    pending = Some(Pending {
+                        kind: record,
+                        ..Pending::default()
+                    });
+                }
+                ENDEL => {
+                    let element = match pending.take() {
+                        Some(p) => p.finish()?,
+                        None => None,
+                    };
+                    if let (Some(element), Some(s)) = (element, structure.as_mut()) {
+                        s.1.push(element);
+                    }
+                }
+                _ => {
+                    if let Some(p) = pending.as_mut() {
+                        read_field(p, record, bytes);
+                    }
+                }
+            }
+        }
+
+        if library.database_unit.is_nan() || library.database_unit <= 0.0 {
+            return Err("GDSII file without valid UNITS".to_string());
+        }
+        Ok(library)
+    }
+
+    /// Get the library name
+    pub fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Get the database unit in metres
+    pub fn get_database_unit(&self) -> f64 {
+        self.database_unit
+    }
+
+    /// Get the names of all cells
+    pub fn get_cell_names(&self) -> Vec<String> {
+        self.structures.iter().map(|(n, _)| n.clone()).collect()
+    }
+
+    /// Get the names of cells not placed inside any other cell
+    pub fn get_top_cells(&self) -> Vec<String> {
+        let referenced: Vec<&str> = self
+            .structures
+            .iter()
+            .flat_map(|(_, elements)| elements)
+            .filter_map(|e| match e {
+                Element::Reference { name, .. } => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        self.structures
+            .iter()
+            .map(|(n, _)| n.clone())
+            .filter(|n| !referenced.contains(&n.as_str()))
+            .collect()
+    }
+
+    /// Get the layers used anywhere in the library as "layer/datatype"
+    pub fn get_layers(&self) -> Vec<String> {
+        let mut layers: Vec<(i16, i16)> = self
+            .structures
+            .iter()
+            .flat_map(|(_, elements)| elements)
+            .filter_map(|e| match e {
+                Element::Polygon {
+                    layer, datatype, ..
+                }
+                | Element::Path {
+                    layer, datatype, ..
+                } => Some((*layer, *datatype)),
+                Element::Reference { .. } => None,
+            })
+            .collect();
+        layers.sort_unstable();
+        layers.dedup();
+        layers.iter().map(|(l, d)| format!("{}/{}", l, d)).collect()
+    }
+
+    /// Get the bounding box [x0, y0, x1, y1] (µm) of the selected layer in
+    /// the selected cell (empty if it has no geometry)
+    pub fn get_bounds(&self, selection: &GdsSelection) -> Result<Vec<f64>, String> {
+        Ok(self.bounds(selection)?.map_or(Vec::new(), |b| b.to_vec()))
+    }
+}
+
+impl GdsLibrary {
+    /// Shapes of the selected layer in grid coordinates for a cell size
+    /// (metres), clipped to the extraction window
+    pub fn extract(&self, selection: &GdsSelection, cell_size: f64) -> Result<Vec<Shape>, String> {
+        let window = match selection.window {
+            Some(window) => window,
+            None => match self.bounds(selection)? {
+                Some(bounds) => bounds,
+                None => return Ok(Vec::new()),
+            },
+        };
+        let s = self.database_unit / cell_size;
+        let cells = MICRON / cell_size;
+        // Window top-left corner on the outer corner of cell (0, 0)
+        let placement = [
+            s,
+            0.0,
+            0.0,
+            -s,
+            -window[0] * cells - 0.5,
+            window[3] * cells - 0.5,
+        ];
+        let (width, height) = (
+            (window[2] - window[0]) * cells,
+            (window[3] - window[1]) * cells,
+        );
+        let frame = [-0.5, -0.5, width as f32 - 0.5, height as f32 - 0.5];
+        let clip = Shape::Rect {
+            cx: 0.5 * (frame[0] + frame[2]),
+            cy: 0.5 * (frame[1] + frame[3]),
+            width: width as f32,
+            height: height as f32,
+            angle: 0.0,
+        };
+
+        let mut shapes = Vec::new();
+        for shape in self.flatten(selection, &placement)? {
+            let Some(b) = shape.bounds() else {
+                continue;
+            };
+            if b[0] >= frame[0] && b[1] >= frame[1] && b[2] <= frame[2] && b[3] <= frame[3] {
+                shapes.push(shape);
+            } else if b[0] < frame[2] && b[1] < frame[3] && b[2] > frame[0] && b[3] > frame[1] {
+                shapes.push(Shape::csg(CsgOp::Intersection, vec![shape, clip.clone()]));
+            }
+        }
+        Ok(shapes)
+    }
+
+    /// Bounding box (µm) of the selected layer
+    fn bounds(&self, selection: &GdsSelection) -> Result<Option<[f64; 4]>, String> {
+        let scale = self.database_unit / MICRON;
+        let placement = [scale, 0.0, 0.0, scale, 0.0, 0.0];
+        let bounds = self
+            .flatten(selection, &placement)?
+            .iter()
+            .fold(None, |b, s| union_bounds(b, s.bounds()));
+        Ok(bounds.map(|b| b.map(|v| v as f64)))
+    }
+
+    /// Selected geometry of the selected cell, hierarchy flattened
+    fn flatten(&self, selection: &GdsSelection, placement: &Affine) -> Result<Vec<Shape>, String> {
+        let cell = match &selection.cell {
+            Some(name) => self
+                .index
+                .get(name)
+                .copied()
+                .ok_or_else(|| format!("no cell named '{}'", name))?,
+            None => {
+                let tops = self.get_top_cells();
+                match &tops[..] {
+                    [top] => self.index[top],
+                    [] => return Err("GDSII library has no top cell".to_string()),
+                    _ => {
+                        return Err(format!(
+                            "several top cells ({}), choose one",
+                            tops.join(", ")
+                        ))
+                    }
+                }
+            }
+        };
+        let mut shapes = Vec::new();
+        self.collect(cell, selection, placement, 0, &mut shapes)?;
+        Ok(shapes)
+    }
+
+    fn collect(
+        &self,
+        cell: usize,
+        selection: &GdsSelection,
+        m: &Affine,
+        depth: usize,
+        out: &mut Vec<Shape>,
+    ) -> Result<(), String> {
+        if depth > MAX_DEPTH {
+            return Err("cell hierarchy too deep (reference cycle?)".to_string());
+        }
+        for element in &self.structures[cell].1 {
+            match element {
+                Element::Polygon {
+                    layer,
+                    datatype,
+                    points,
+                } if selection.matches(*layer, *datatype) => {
+                    let points: Vec<[f32; 2]> = points.iter().map(|&p| apply(m, p)).collect();
+                    out.extend(contours_shape(&[points], FillRule::NonZero));
+                }
+                Element::Path {
+                    layer,
+                    datatype,
+                    points,
+                    width,
+                    pathtype,
+                    extensions,
+                } if selection.matches(*layer, *datatype) => {
+                    if *pathtype == 1 {
+                        out.push(Shape::Stroke {
+                            points: points.iter().map(|&p| apply(m, p)).collect(),
+                            width: (width * linear_scale(m)) as f32,
+                        });
+                    } else {
+                        let outline = path_outline(points, 0.5 * width, *extensions);
+                        let outline: Vec<[f32; 2]> = outline.iter().map(|&p| apply(m, p)).collect();
+                        out.extend(contours_shape(&[outline], FillRule::NonZero));
+                    }
+                }
+                Element::Reference {
+                    name,
+                    orientation,
+                    origin,
+                    columns,
+                    rows,
+                    steps,
+                } => {
+                    // References to missing cells are skipped like in most viewers
+                    let Some(&child) = self.index.get(name) else {
+                        continue;
+                    };
+                    for column in 0..*columns {
+                        for row in 0..*rows {
+                            let (c, r) = (column as f64, row as f64);
+                            let offset = [
+                                origin[0] + c * steps[0][0] + r * steps[1][0],
+                                origin[1] + c * steps[0][1] + r * steps[1][1],
+                            ];
+                            let place = [1.0, 0.0, 0.0, 1.0, offset[0], offset[1]];
+                            let local = compose(&place, orientation);
+                            self.collect(child, selection, &compose(m, &local), depth + 1, out)?;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Store one element property record
+fn read_field(pending: &mut Pending, record: u8, bytes: &[u8]) {
+    let int16 = || {
+        bytes
+            .get(..2)
+            .map_or(0, |b| i16::from_be_bytes([b[0], b[1]]))
+    };
+    let int32 = |at: usize| {
+        bytes
+            .get(at..at + 4)
+            .map_or(0, |b| i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    match record {
+        LAYER => pending.layer = int16(),
+        DATATYPE | BOXTYPE => pending.datatype = int16(),
+        WIDTH => pending.width = int32(0) as f64,
+        PATHTYPE => pending.pathtype = int16(),
+        BGNEXTN => pending.extensions[0] = int32(0) as f64,
+        ENDEXTN => pending.extensions[1] = int32(0) as f64,
+        SNAME => pending.name = ascii(bytes),
+        STRANS => pending.strans = int16() as u16,
+        MAG if bytes.len() >= 8 => pending.magnification = Some(real8(&bytes[..8])),
+        ANGLE if bytes.len() >= 8 => pending.angle = real8(&bytes[..8]),
+        COLROW => {
+            let column = int16();
+            let row = bytes
+                .get(2..4)
+                .map_or(0, |b| i16::from_be_bytes([b[0], b[1]]));
+            pending.colrow = [column.max(0) as u16, row.max(0) as u16];
+        }
+        XY => {
+            pending.points = (0..bytes.len() / 8)
+                .map(|i| [int32(8 * i) as f64, int32(8 * i + 4) as f64])
+                .collect();
+        }
+        _ => {}
+    }
+}
+
+/// String record without NUL padding
+fn ascii(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+/// GDSII 8-byte real: sign bit, excess-64 base-16 exponent, 56-bit mantissa
+fn real8(bytes: &[u8]) -> f64 {
+    let sign = if bytes[0] & 0x80 != 0 { -1.0 } else { 1.0 };
+    let exponent = (bytes[0] & 0x7f) as i32 - 64;
+    let mantissa = bytes[1..8].iter().fold(0u64, |m, &b| (m << 8) | b as u64);
+    sign * mantissa as f64 / 2f64.powi(56) * 16f64.powi(exponent)
+}
+
+/// Outline of a flush-ended path of half-width `half` with mitred joins,
+/// extended past its ends by `extensions`
+fn path_outline(points: &[[f64; 2]], half: f64, extensions: [f64; 2]) -> Vec<[f64; 2]> {
+    let mut points: Vec<[f64; 2]> = points.to_vec();
+    points.dedup();
+    let n = points.len();
+    if n < 2 || half <= 0.0 {
+        return Vec::new();
+    }
+    let direction = |a: [f64; 2], b: [f64; 2]| {
+        let length = (b[0] - a[0]).hypot(b[1] - a[1]);
+        [(b[0] - a[0]) / length, (b[1] - a[1]) / length]
+    };
+    let start = direction(points[0], points[1]);
+    let end = direction(points[n - 2], points[n - 1]);
+    points[0] = [
+        points[0][0] - start[0] * extensions[0],
+        points[0][1] - start[1] * extensions[0],
+    ];
+    points[n - 1] = [
+        points[n - 1][0] + end[0] * extensions[1],
+        points[n - 1][1] + end[1] * extensions[1],
+    ];
+
+    let normal = |d: [f64; 2]| [-d[1], d[0]];
+    let mut left = Vec::with_capacity(n);
+    let mut right = Vec::with_capacity(n);
+    for i in 0..n {
+        let offset = if i == 0 {
+            normal(start).map(|v| v * half)
+        } else if i == n - 1 {
+            normal(end).map(|v| v * half)
+        } else {
+            let n1 = normal(direction(points[i - 1], points[i]));
+            let n2 = normal(direction(points[i], points[i + 1]));
+            let sum = [n1[0] + n2[0], n1[1] + n2[1]];
+            let length2 = sum[0] * sum[0] + sum[1] * sum[1];
+            // Mitre length half / cos(θ/2), capped for hairpin turns
+            if length2 > 0.125 {
+                sum.map(|v| v * 2.0 * half / length2)
+            } else {
+                n1.map(|v| v * half)
+            }
+        };
+        let p = points[i];
+        left.push([p[0] + offset[0], p[1] + offset[1]]);
+        right.push([p[0] - offset[0], p[1] - offset[1]]);
+    }
+    left.extend(right.into_iter().rev());
+    left
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fdtd::FDTDGrid;
+    use crate::units::DEFAULT_CELL_SIZE;
+
+    /// Encode a GDSII 8-byte real
+    fn real8_bytes(value: f64) -> Vec<u8> {
+        let mut exponent = 64;
+        let mut m = value.abs();
+        while m >= 1.0 {
+            m /= 16.0;
+            exponent += 1;
+        }
+        while m > 0.0 && m < 1.0 / 16.0 {
+            m *= 16.0;
+            exponent -= 1;
+        }
+        let mantissa = (m * 2f64.powi(56)).round() as u64;
+        let sign = if value < 0.0 { 0x80 } else { 0 };
+        let mut bytes = vec![sign | exponent as u8];
+        bytes.extend_from_slice(&mantissa.to_be_bytes()[1..]);
+        bytes
+    }
+
+    /// GDSII stream writer for test libraries
+    #[derive(Default)]
+    struct Writer(Vec<u8>);
+
+    impl Writer {
+        fn record(&mut self, record: u8, data_type: u8, payload: &[u8]) -> &mut Self {
+            let length = (payload.len() + 4) as u16;
+            self.0.extend_from_slice(&length.to_be_bytes());
+            self.0.extend_from_slice(&[record, data_type]);
+            self.0.extend_from_slice(payload);
+            self
+        }
+
+        fn int16(&mut self, record: u8, values: &[i16]) -> &mut Self {
+            let payload: Vec<u8> = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+            self.record(record, 2, &payload)
+        }
+
+        fn xy(&mut self, points: &[[i32; 2]]) -> &mut Self {
+            let payload: Vec<u8> = points
+                .iter()
+                .flatten()
+                .flat_map(|v| v.to_be_bytes())
+                .collect();
+            self.record(XY, 3, &payload)
+        }
+
+        fn text(&mut self, record: u8, text: &str) -> &mut Self {
+            let mut payload = text.as_bytes().to_vec();
+            if payload.len() % 2 == 1 {
+                payload.push(0);
+            }
+            self.record(record, 6, &payload)
+        }
+    }
+
+    /// Library with 1 nm database units: a 2 µm waveguide path on 1/0, a
+    /// ring cell of squares on 2/0 placed as a 3 × 1 array, and a box on 1/5
+    fn sample_library() -> Vec<u8> {
+        let mut w = Writer::default();
+        w.int16(HEADER, &[600]).int16(BGNLIB, &[0; 12]);
+        w.text(0x02, "TEST");
+        let mut units = real8_bytes(1e-3);
+        units.extend(real8_bytes(1e-9));
+        w.record(UNITS, 5, &units);
+
+        w.int16(BGNSTR, &[0; 12]).text(STRNAME, "SQUARE");
+        w.record(BOUNDARY, 0, &[])
+            .int16(LAYER, &[2])
+            .int16(DATATYPE, &[0]);
+        w.xy(&[[0, 0], [1000, 0], [1000, 1000], [0, 1000], [0, 0]]);
+        w.record(ENDEL, 0, &[]).record(ENDSTR, 0, &[]);
+
+        w.int16(BGNSTR, &[0; 12]).text(STRNAME, "TOP");
+        w.record(PATH, 0, &[])
+            .int16(LAYER, &[1])
+            .int16(DATATYPE, &[0]);
+        w.record(WIDTH, 3, &2000i32.to_be_bytes());
+        w.xy(&[[0, 5000], [10000, 5000], [10000, 15000]]);
+        w.record(ENDEL, 0, &[]);
+        w.record(BOX, 0, &[])
+            .int16(LAYER, &[1])
+            .int16(BOXTYPE, &[5]);
+        w.xy(&[[0, 0], [500, 0], [500, 500], [0, 500], [0, 0]]);
+        w.record(ENDEL, 0, &[]);
+        w.record(AREF, 0, &[]).text(SNAME, "SQUARE");
+        w.int16(COLROW, &[3, 1]);
+        w.xy(&[[20000, 0], [26000, 0], [20000, 2000]]);
+        w.record(ENDEL, 0, &[]);
+        w.record(ENDSTR, 0, &[]).record(ENDLIB, 0, &[]);
+        w.0
+    }
+
+    #[test]
+    fn test_read_library() {
+        let library = GdsLibrary::from_bytes(&sample_library()).unwrap();
+        assert_eq!(library.get_name(), "TEST");
+        assert!((library.get_database_unit() - 1e-9).abs() < 1e-20);
+        assert_eq!(library.get_top_cells(), vec!["TOP".to_string()]);
+        assert_eq!(library.get_layers(), vec!["1/0", "1/5", "2/0"]);
+
+        // The path's mitred corner reaches (11, 4) µm
+        let waveguide = GdsSelection::new(1, 0);
+        let bounds = library.get_bounds(&waveguide).unwrap();
+        let expected = [0.0, 4.0, 11.0, 15.0];
+        assert!(bounds
+            .iter()
+            .zip(expected)
+            .all(|(b, e)| (b - e).abs() < 1e-4));
+
+        // Array copies every 2 µm along x
+        let squares = GdsSelection::new(2, -1);
+        let bounds = library.get_bounds(&squares).unwrap();
+        assert!((bounds[0] - 20.0).abs() < 1e-4 && (bounds[2] - 25.0).abs() < 1e-4);
+
+        assert!(GdsLibrary::from_bytes(b"GDS?").is_err());
+        let mut selection = GdsSelection::new(1, 0);
+        selection.set_cell("MISSING");
+        assert!(library.get_bounds(&selection).is_err());
+    }
+
+    #[test]
+    fn test_extract_window() {
+        let library = GdsLibrary::from_bytes(&sample_library()).unwrap();
+        let mut selection = GdsSelection::new(1, 0);
+        // 0.5 µm cells over x in [5, 15] µm, y in [0, 10] µm
+        selection.set_window(5.0, 0.0, 15.0, 10.0);
+        let shapes = library.extract(&selection, 0.5e-6).unwrap();
+        assert_eq!(shapes.len(), 1);
+        let shape = &shapes[0];
+        // Horizontal arm at y = 5 µm is grid row 9.5; the vertical arm at x = 10 µm is column 9.5
+        assert!(shape.contains(2.0, 9.5));
+        assert!(shape.contains(9.5, 2.0));
+        assert!(!shape.contains(2.0, 2.0));
+        // Clipped at the window: nothing left of x = 5 µm
+        assert!(!shape.contains(-2.0, 9.5));
+    }
+
+    #[test]
+    fn test_import_into_grid() {
+        let library = GdsLibrary::from_bytes(&sample_library()).unwrap();
+        let mut grid = FDTDGrid::new(64, 64);
+        let silicon = grid.register_material("Silicon", 12.1, 1.0, 0.0, 0.0);
+        let mut selection = GdsSelection::new(1, 0);
+        selection.set_cell_size(0.25e-6);
+        assert_eq!(grid.import_gds(&library, &selection, silicon), Ok(1));
+        assert_eq!(grid.get_cell_size(), 0.25e-6);
+
+        // Default window is the path's bounding box: x in [0, 11], y in [4, 15] µm
+        assert_eq!(grid.get_material_at(20, 40), silicon);
+        assert_eq!(grid.get_material_at(42, 10), silicon);
+        assert_eq!(grid.get_material_at(20, 10), 0);
+        assert!(grid.undo());
+        assert_eq!(grid.get_material_at(20, 40), 0);
+        assert_eq!(grid.get_cell_size(), DEFAULT_CELL_SIZE);
+        assert!(grid.redo());
+        assert_eq!(grid.get_cell_size(), 0.25e-6);
+    }
+
+    #[test]
+    fn test_real8() {
+        for value in [1.0, -2.5, 1e-9, 1e-3, 123456.0] {
+            assert!((real8(&real8_bytes(value)) - value).abs() <= 1e-12 * value.abs());
+        }
+    }
+}
@@ -0,0 +1,351 @@
+//! Undo/Redo History for Grid Edits
+//!
+//! Every destructive edit of the material layout is stored as a compact
+//! diff: only the brush-layer cells whose material changed, each with its
+//! state before and after (material ID plus interface coverage), and the
+//! scene objects that were added, changed or removed, each with its object
+//! before and after. Update coefficients are derived from the material
+//! table, so they are rebuilt from the IDs instead of being stored.
+//!
+//! Undoable: brush painting, region and shape painting, drawing and image
+//! imports, scene shape edits (add, transform, material, z-order,
+//! visibility, remove, clear), `clear_materials` and scenario presets.
+//! Material table edits, sources and simulation settings other than the
+//! ones below are not part of the history.
+//!
+//! Edits that also switch grid-wide settings (boundaries of the photonic
+//! crystal cell preset, the cell size of a GDSII import) store those
+//! settings before and after, so undo returns the grid to the exact state
+//! the user had.
+//!
+//! The history keeps as many commands as fit in a memory budget, dropping
+//! the oldest ones first.
+
+use crate::boundary::BoundarySpec;
+use crate::scene::{Scene, SceneObject};
+
+/// Default memory budget of a grid's history (16 MiB)
+pub const DEFAULT_HISTORY_BUDGET: usize = 16 << 20;
+
+/// Rough footprint of a retained scene object (shape parameters included)
+const SCENE_OBJECT_BYTES: usize = 256;
+
+/// Material state of one cell (the shape painted "underneath" the scene)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellState {
+    /// Dominant material
+    pub material_id: u32,
+    /// Minority material of an interface cell (ignored when fraction is 0)
+    pub other: u32,
+    /// Area share of `other`
+    pub fraction: f32,
+}
+
+impl CellState {
+    /// Vacuum, as left by `clear_materials`
+    pub const VACUUM: CellState = CellState {
+        material_id: 0,
+        other: 0,
+        fraction: 0.0,
+    };
+
+    /// Flag the materials of this state in `used` (indexed by material ID)
+    pub fn mark_materials(&self, used: &mut [bool]) {
+        mark(used, self.material_id);
+        if self.fraction > 0.0 {
+            mark(used, self.other);
+        }
+    }
+}
+
+/// Flag one material ID (IDs beyond the table are ignored)
+fn mark(used: &mut [bool], material_id: u32) {
+    if let Some(flag) = used.get_mut(material_id as usize) {
+        *flag = true;
+    }
+}
+
+/// Before/after state of one edited cell
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellChange {
+    pub index: u32,
+    pub before: CellState,
+    pub after: CellState,
+}
+
+/// Before/after state of one edited scene object (None = not in the scene)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShapeChange {
+    pub id: u32,
+    pub before: Option<SceneObject>,
+    pub after: Option<SceneObject>,
+}
+
+/// Material layout wiped by `clear_materials`, kept for undo
+/// Cells are stored sparsely (non-vacuum cells only).
+#[derive(Clone, Debug)]
+pub struct ClearedState {
+    pub cells: Vec<(u32, CellState)>,
+    pub scene: Scene,
+    pub base: Option<Vec<(u32, CellState)>>,
+}
+
+/// Grid-wide settings an edit may change along with the cells
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridSettings {
+    pub boundary: BoundarySpec,
+    /// Cell size in metres
+    pub cell_size: f64,
+}
+
+/// Settings before and after an edit that changed them
+#[derive(Clone, Copy, Debug)]
+pub struct SettingsChange {
+    pub before: GridSettings,
+    pub after: GridSettings,
+}
+
+impl ClearedState {
+    /// Flag every material of the wiped layout
+    pub fn mark_materials(&self, used: &mut [bool]) {
+        let base = self.base.iter().flatten();
+        for (_, state) in self.cells.iter().chain(base) {
+            state.mark_materials(used);
+        }
+        self.scene.mark_materials(used);
+    }
+}
+
+/// One undoable edit
+#[derive(Clone, Debug)]
+pub struct Command {
+    /// Layout before the edit, when the edit started by clearing the grid;
+    /// `changes` are then relative to the cleared grid
+    pub cleared: Option<Box<ClearedState>>,
+    pub changes: Vec<CellChange>,
+    pub shapes: Vec<ShapeChange>,
+    pub settings: Option<Box<SettingsChange>>,
+}
+
+impl Command {
+    /// Approximate heap footprint in bytes
+    pub fn memory(&self) -> usize {
+        let cell = std::mem::size_of::<(u32, CellState)>();
+        let cleared = self.cleared.as_ref().map_or(0, |c| {
+            let base = c.base.as_ref().map_or(0, |b| b.len());
+            (c.cells.len() + base) * cell + c.scene.len() * SCENE_OBJECT_BYTES
+        });
+        let settings = self
+            .settings
+            .as_ref()
+            .map_or(0, |_| std::mem::size_of::<SettingsChange>());
+        std::mem::size_of::<Command>()
+            + self.changes.len() * std::mem::size_of::<CellChange>()
+            + self.shapes.len() * 2 * SCENE_OBJECT_BYTES
+            + cleared
+            + settings
+    }
+
+    /// Flag every material the command can restore
+    pub fn mark_materials(&self, used: &mut [bool]) {
+        for change in &self.changes {
+            change.before.mark_materials(used);
+            change.after.mark_materials(used);
+        }
+        for object in self
+            .shapes
+            .iter()
+            .flat_map(|c| c.before.iter().chain(&c.after))
+        {
+            for id in object.shape.materials(object.material_id) {
+                mark(used, id);
+            }
+        }
+        if let Some(cleared) = &self.cleared {
+            cleared.mark_materials(used);
+        }
+    }
+
+    /// Check if the command changes nothing
+    pub fn is_empty(&self) -> bool {
+        self.cleared.is_none()
+            && self.changes.is_empty()
+            && self.shapes.is_empty()
+            && self.settings.is_none()
+    }
+}
+
+/// Undo and redo stacks with a shared memory budget
+#[derive(Clone, Debug)]
+pub struct History {
+    undo: Vec<Command>,
+    redo: Vec<Command>,
+    budget: usize,
+}
+
+impl History {
+    /// Create an empty history with the given budget in bytes (0 disables it)
+    pub fn new(budget: usize) -> History {
+        History {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            budget,
+        }
+    }
+
+    /// Record a new edit; the redo stack is discarded
+    ///
+    /// Older commands are dropped to make room. A command that does not fit
+    /// on its own clears the history, since earlier diffs would no longer
+    /// line up with the grid.
+    pub fn push(&mut self, command: Command) {
+        if command.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        if command.memory() > self.budget {
+            self.undo.clear();
+            return;
+        }
+        self.undo.push(command);
+        self.trim();
+    }
+
+    /// Take the most recent command to undo
+    pub fn pop_undo(&mut self) -> Option<Command> {
+        self.undo.pop()
+    }
+
+    /// Take the most recently undone command to redo
+    pub fn pop_redo(&mut self) -> Option<Command> {
+        self.redo.pop()
+    }
+
+    /// Store an undone command for redo
+    pub fn push_redo(&mut self, command: Command) {
+        self.redo.push(command);
+    }
+
+    /// Store a redone command for undo (keeps the remaining redo stack)
+    pub fn push_undo(&mut self, command: Command) {
+        self.undo.push(command);
+        self.trim();
+    }
+
+    /// Number of commands that can be undone
+    pub fn undo_count(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Number of commands that can be redone
+    pub fn redo_count(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Flag every material a command on either stack can restore
+    pub fn mark_materials(&self, used: &mut [bool]) {
+        for command in self.undo.iter().chain(&self.redo) {
+            command.mark_materials(used);
+        }
+    }
+
+    /// Memory used by both stacks in bytes
+    pub fn memory(&self) -> usize {
+        self.undo
+            .iter()
+            .chain(&self.redo)
+            .map(Command::memory)
+            .sum()
+    }
+
+    /// Memory budget in bytes
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Change the memory budget, dropping old commands that no longer fit
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.trim();
+    }
+
+    /// Forget every command
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Drop redo commands, then the oldest undo commands, until within budget
+    fn trim(&mut self) {
+        while self.memory() > self.budget && !self.redo.is_empty() {
+            self.redo.remove(0);
+        }
+        let mut used = self.memory();
+        let mut drop = 0;
+        while used > self.budget && drop < self.undo.len() {
+            used -= self.undo[drop].memory();
+            drop += 1;
+        }
+        self.undo.drain(..drop);
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new(DEFAULT_HISTORY_BUDGET)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stroke(cells: u32) -> Command {
+        let state = |material_id| CellState {
+            material_id,
+            ..CellState::VACUUM
+        };
+        Command {
+            cleared: None,
+            changes: (0..cells)
+                .map(|index| CellChange {
+                    index,
+                    before: state(0),
+                    after: state(1),
+                })
+                .collect(),
+            shapes: Vec::new(),
+            settings: None,
+        }
+    }
+
+    #[test]
+    fn test_budget_drops_oldest() {
+        let size = stroke(100).memory();
+        let mut history = History::new(3 * size);
+        for _ in 0..5 {
+            history.push(stroke(100));
+        }
+        assert_eq!(history.undo_count(), 3);
+        assert!(history.memory() <= history.budget());
+
+        // Undo/redo moves commands between the stacks
+        let command = history.pop_undo().unwrap();
+        history.push_redo(command);
+        assert_eq!((history.undo_count(), history.redo_count()), (2, 1));
+
+        // A new edit discards the redo stack; empty edits are ignored
+        history.push(stroke(0));
+        assert_eq!(history.redo_count(), 1);
+        history.push(stroke(1));
+        assert_eq!(history.redo_count(), 0);
+
+        // An edit larger than the whole budget invalidates the history
+        history.push(stroke(1000));
+        assert_eq!(history.undo_count(), 0);
+
+        history.push(stroke(100));
+        history.set_budget(0);
+        assert_eq!(history.undo_count(), 0);
+    }
+}
@@ -0,0 +1,337 @@
+//! Import of External Drawings into Material Regions
+//!
+//! Drawings are read into closed shapes tagged with a layer key, already
+//! placed in grid coordinates: grid = drawing × scale + offset. An
+//! `ImportOptions` value carries the placement and maps layer keys to
+//! material IDs. Layer keys depend on the format:
+//! - SVG: fill colour, normalized to "#rrggbb"
+//! - DXF: layer name
+//! - PNG/PGM masks: pixel colour "#rrggbb", or gray level ("128")
+//!
+//! Curves are flattened into segments about one grid cell long, so the
+//! outline resolution follows the chosen scale.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::dxf::parse_dxf;
+use crate::shapes::{flatten, FillRule, Shape};
+use crate::svg::{parse_color, parse_svg};
+
+/// 2D affine map [a, b, c, d, e, f] taking (x, y) to
+/// (a·x + c·y + e, b·x + d·y + f), as in SVG `matrix()`
+pub type Affine = [f64; 6];
+
+/// Identity map
+pub const IDENTITY: Affine = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// Closed region of an imported drawing (grid coordinates)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedShape {
+    /// Layer key the material is looked up by
+    pub layer: String,
+    pub shape: Shape,
+}
+
+/// Placement and layer → material mapping of an imported drawing
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportOptions {
+    scale: f32,
+    offset_x: f32,
+    offset_y: f32,
+    layers: Vec<(String, u32)>,
+    default_material: Option<u32>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl ImportOptions {
+    /// Place drawing coordinates at grid = drawing × scale + offset (in cells)
+    /// Non-positive or non-finite scales fall back to 1.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(scale: f32, offset_x: f32, offset_y: f32) -> ImportOptions {
+        let finite = |v: f32| if v.is_finite() { v } else { 0.0 };
+        ImportOptions {
+            scale: if scale.is_finite() && scale > 0.0 {
+                scale
+            } else {
+                1.0
+            },
+            offset_x: finite(offset_x),
+            offset_y: finite(offset_y),
+            layers: Vec::new(),
+            default_material: None,
+        }
+    }
+
+    /// Paint shapes of a layer (fill colour, layer name, ...) with a material
+    pub fn map_layer(&mut self, layer: &str, material_id: u32) {
+        let key = layer_key(layer);
+        self.layers.retain(|(k, _)| *k != key);
+        self.layers.push((key, material_id));
+    }
+
+    /// Paint shapes of unmapped layers with a material (skipped by default)
+    pub fn set_default_material(&mut self, material_id: u32) {
+        self.default_material = Some(material_id);
+    }
+
+    /// Get the drawing-to-grid scale
+    pub fn get_scale(&self) -> f32 {
+        self.scale
+    }
+}
+
+impl ImportOptions {
+    /// Create import options, rejecting the values `new` would replace
+    pub fn try_new(scale: f32, offset_x: f32, offset_y: f32) -> Result<ImportOptions, String> {
+        if !(scale.is_finite() && scale > 0.0) {
+            return Err(format!("invalid import scale {}", scale));
+        }
+        if !(offset_x.is_finite() && offset_y.is_finite()) {
+            return Err(format!(
+                "invalid import offset ({}, {})",
+                offset_x, offset_y
+            ));
+        }
+        Ok(ImportOptions::new(scale, offset_x, offset_y))
+    }
+
+    /// Material for a layer (None if the layer is not imported)
+    pub fn material_for(&self, layer: &str) -> Option<u32> {
+        let key = layer_key(layer);
+        self.layers
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|&(_, id)| id)
+            .or(self.default_material)
+    }
+
+    /// Imported shapes paired with their materials (unmapped layers skipped)
+    pub fn materials<'a>(
+        &'a self,
+        shapes: &'a [ImportedShape],
+    ) -> impl Iterator<Item = (&'a Shape, u32)> + 'a {
+        shapes
+            .iter()
+            .filter_map(|s| Some((&s.shape, self.material_for(&s.layer)?)))
+    }
+
+    /// Drawing-to-grid map
+    pub fn placement(&self) -> Affine {
+        let s = self.scale as f64;
+        [s, 0.0, 0.0, s, self.offset_x as f64, self.offset_y as f64]
+    }
+}
+
+/// Case-insensitive layer key; colours compare by value ("red" = "#F00")
+fn layer_key(layer: &str) -> String {
+    parse_color(layer).unwrap_or_else(|| layer.trim().to_lowercase())
+}
+
+/// Get the fill colours of an SVG drawing (its layer keys), in order of use
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn get_svg_layers(svg: &str) -> Result<Vec<String>, String> {
+    parse_svg(svg, &IDENTITY).map(|shapes| layer_names(&shapes))
+}
+
+/// Get the layer names of a DXF drawing that hold closed outlines
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn get_dxf_layers(dxf: &str) -> Result<Vec<String>, String> {
+    parse_dxf(dxf, &IDENTITY).map(|shapes| layer_names(&shapes))
+}
+
+/// Map `m` applied after `n`
+pub fn compose(m: &Affine, n: &Affine) -> Affine {
+    [
+        m[0] * n[0] + m[2] * n[1],
+        m[1] * n[0] + m[3] * n[1],
+        m[0] * n[2] + m[2] * n[3],
+        m[1] * n[2] + m[3] * n[3],
+        m[0] * n[4] + m[2] * n[5] + m[4],
+        m[1] * n[4] + m[3] * n[5] + m[5],
+    ]
+}
+
+/// Map a drawing point to the grid
+pub fn apply(m: &Affine, p: [f64; 2]) -> [f32; 2] {
+    [
+        (m[0] * p[0] + m[2] * p[1] + m[4]) as f32,
+        (m[1] * p[0] + m[3] * p[1] + m[5]) as f32,
+    ]
+}
+
+/// Mean length scale of a map (square root of the area scale)
+pub fn linear_scale(m: &Affine) -> f64 {
+    (m[0] * m[3] - m[1] * m[2]).abs().sqrt()
+}
+
+/// Rotation angle and orientation (1, or -1 if mirrored) of a map that
+/// preserves shapes, or None if it shears or stretches
+fn similarity(m: &Affine) -> Option<(f64, f64)> {
+    let s = linear_scale(m);
+    let tolerance = 1e-9 * s.max(1.0);
+    let rotation = (m[0] - m[3]).abs() < tolerance && (m[1] + m[2]).abs() < tolerance;
+    let reflection = (m[0] + m[3]).abs() < tolerance && (m[1] - m[2]).abs() < tolerance;
+    let orientation = if rotation { 1.0 } else { -1.0 };
+    (s > 0.0 && (rotation || reflection)).then(|| (m[1].atan2(m[0]), orientation))
+}
+
+/// Points of an elliptical arc from angle `start` over `sweep` radians
+/// (rx, ry semi-axes rotated by `rotation`), start point included
+pub fn arc_points(
+    m: &Affine,
+    center: [f64; 2],
+    radii: [f64; 2],
+    rotation: f64,
+    start: f64,
+    sweep: f64,
+) -> Vec<[f32; 2]> {
+    let (sin, cos) = rotation.sin_cos();
+    let length = sweep.abs() * radii[0].abs().max(radii[1].abs()) * linear_scale(m);
+    flatten(length as f32, |t| {
+        let (s, c) = (start + sweep * t as f64).sin_cos();
+        let (u, v) = (radii[0] * c, radii[1] * s);
+        apply(
+            m,
+            [center[0] + u * cos - v * sin, center[1] + u * sin + v * cos],
+        )
+    })
+}
+
+/// Ellipse with semi-axes rx, ry rotated by `rotation`, mapped to the grid
+/// (a polygon if the map distorts it)
+pub fn ellipse_shape(m: &Affine, center: [f64; 2], rx: f64, ry: f64, rotation: f64) -> Shape {
+    match similarity(m) {
+        Some((angle, orientation)) => {
+            let [cx, cy] = apply(m, center);
+            let s = linear_scale(m);
+            Shape::Ellipse {
+                cx,
+                cy,
+                rx: (rx * s) as f32,
+                ry: (ry * s) as f32,
+                angle: (angle + orientation * rotation) as f32,
+            }
+        }
+        None => {
+            let mut points = arc_points(m, center, [rx, ry], rotation, 0.0, std::f64::consts::TAU);
+            points.pop(); // Same as the first point
+            Shape::Polygon {
+                points,
+                rule: FillRule::NonZero,
+            }
+        }
+    }
+}
+
+/// Axis-aligned rectangle [x, x + width] × [y, y + height] mapped to the grid
+pub fn rect_shape(m: &Affine, x: f64, y: f64, width: f64, height: f64) -> Shape {
+    match similarity(m) {
+        Some((angle, _)) => {
+            let [cx, cy] = apply(m, [x + 0.5 * width, y + 0.5 * height]);
+            let s = linear_scale(m);
+            Shape::Rect {
+                cx,
+                cy,
+                width: (width * s) as f32,
+                height: (height * s) as f32,
+                angle: angle as f32,
+            }
+        }
+        None => Shape::Polygon {
+            points: [
+                [x, y],
+                [x + width, y],
+                [x + width, y + height],
+                [x, y + height],
+            ]
+            .iter()
+            .map(|&p| apply(m, p))
+            .collect(),
+            rule: FillRule::NonZero,
+        },
+    }
+}
+
+/// One polygon from several closed contours (holes, islands)
+///
+/// Each contour is closed back to its first point and the contours are
+/// chained through those points. Every connecting edge is walked once in
+/// each direction, so it cancels out of the winding number and both fill
+/// rules see exactly the original contours.
+pub fn contours_shape(contours: &[Vec<[f32; 2]>], rule: FillRule) -> Option<Shape> {
+    let mut points = Vec::new();
+    for contour in contours.iter().filter(|c| c.len() >= 3) {
+        points.extend_from_slice(contour);
+        if contour.first() != contour.last() {
+            points.push(contour[0]);
+        }
+    }
+    (points.len() >= 3).then_some(Shape::Polygon { points, rule })
+}
+
+/// Distinct layer keys of imported shapes, in order of first use
+pub fn layer_names(shapes: &[ImportedShape]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for shape in shapes {
+        if !names.contains(&shape.layer) {
+            names.push(shape.layer.clone());
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_mapping() {
+        let mut options = ImportOptions::new(2.0, 10.0, 5.0);
+        options.map_layer("#FF0000", 3);
+        options.map_layer("Lens", 2);
+        assert_eq!(options.material_for("red"), Some(3));
+        assert_eq!(options.material_for("LENS"), Some(2));
+        assert_eq!(options.material_for("mirror"), None);
+        options.set_default_material(6);
+        assert_eq!(options.material_for("mirror"), Some(6));
+        assert_eq!(apply(&options.placement(), [1.0, 1.0]), [12.0, 7.0]);
+        assert_eq!(ImportOptions::new(-1.0, 0.0, 0.0).get_scale(), 1.0);
+    }
+
+    #[test]
+    fn test_placed_primitives() {
+        // Rotation by 90° keeps a rectangle a rectangle
+        let quarter = [0.0, 2.0, -2.0, 0.0, 5.0, 5.0];
+        match rect_shape(&quarter, 0.0, 0.0, 4.0, 2.0) {
+            Shape::Rect {
+                cx,
+                cy,
+                width,
+                angle,
+                ..
+            } => {
+                assert!((cx - 3.0).abs() < 1e-5 && (cy - 9.0).abs() < 1e-5);
+                assert_eq!(width, 8.0);
+                assert!((angle - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+            }
+            other => panic!("expected a rect, got {:?}", other),
+        }
+
+        // Shear turns a circle into a polygon that still covers its centre
+        let shear = [1.0, 0.0, 1.0, 1.0, 0.0, 0.0];
+        let ellipse = ellipse_shape(&shear, [10.0, 10.0], 5.0, 5.0, 0.0);
+        assert_eq!(ellipse.kind(), "polygon");
+        assert!(ellipse.contains(20.0, 10.0));
+        assert!(!ellipse.contains(10.0, 10.0));
+
+        // A square with a square hole
+        let outer = vec![[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]];
+        let inner = vec![[3.0, 3.0], [7.0, 3.0], [7.0, 7.0], [3.0, 7.0]];
+        let framed = contours_shape(&[outer, inner], FillRule::EvenOdd).unwrap();
+        assert!(framed.contains(1.0, 5.0));
+        assert!(!framed.contains(5.0, 5.0));
+        assert!(!framed.contains(11.0, 5.0));
+    }
+}
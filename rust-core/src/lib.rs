@@ -1,30 +1,65 @@
 //! PhotonLab Core - FDTD Electromagnetic Solver v2.0
 //!
-//! High-performance 2D electromagnetic field solver using the
+//! High-performance 2D and 3D electromagnetic field solver using the
 //! Finite-Difference Time-Domain (FDTD) method with Yee lattice algorithm.
 //!
-//! Compiled to WebAssembly for browser-based simulation.
+//! Compiled to WebAssembly for browser-based simulation. The JavaScript
+//! bindings are behind the default `wasm` feature; with
+//! `default-features = false` the crate is a plain Rust library without
+//! wasm-bindgen, where fields are read as slices and invalid arguments are
+//! reported through `Result` instead of being clamped.
 //!
 //! Author: Mehmet Gümüş (github.com/SpaceEngineerSS)
 
+mod bands;
+mod bitmap;
+mod boundary;
 mod cpml;
+mod cpml3d;
+mod csg;
+mod dft;
+mod dispersion;
+mod dxf;
 mod fdtd;
+mod fdtd3d;
+mod gds;
+mod history;
+mod import;
 mod materials;
+mod raster;
+mod refractive;
 mod scenarios;
+mod scene;
+mod scene_file;
+mod shapes;
 mod sources;
+mod svg;
+mod units;
 
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
 // Re-export FDTDGrid for JavaScript access
-pub use fdtd::FDTDGrid;
+pub use fdtd::{FDTDGrid, Polarization};
+
+// Re-export the 3D solver
+pub use fdtd3d::{FDTDGrid3D, FieldComponent, SlicePlane};
 
 // Re-export materials system
 pub use materials::{
-    get_material_by_id, get_material_name, Material, MaterialPresets, MaterialType,
+    get_material_by_id, get_material_name, Material, MaterialEntry, MaterialPresets, MaterialTable,
+    MaterialType, Pole, PoleType,
 };
 
-// Re-export CPML
-pub use cpml::CPML;
+// Re-export refractive-index import and dispersion fitting
+pub use refractive::{DispersionFit, RefractiveIndexData};
+
+// Re-export band structure driver
+pub use bands::BandStructure;
+
+// Re-export CPML and boundary selection
+pub use boundary::{get_boundary_name, BoundarySide, BoundarySpec, BoundaryType};
+pub use cpml::{CPMLParams, CPML};
 
 // Re-export scenarios
 pub use scenarios::{get_scenario_description, get_scenario_name, ScenarioId};
@@ -47,7 +82,30 @@ pub use sources::{
     Waveform,
 };
 
+// Re-export the running DFT field monitor
+pub use dft::FieldDft;
+
+// Re-export shape primitives, CSG and the retained scene
+pub use csg::{CsgNode, CsgOp, Geometry};
+pub use scene::{Scene, SceneObject};
+pub use shapes::{FillRule, Shape};
+
+// Re-export drawing (SVG/DXF), layout (GDSII) and bitmap mask import
+pub use bitmap::get_image_colors;
+pub use gds::{GdsLibrary, GdsSelection};
+pub use import::{get_dxf_layers, get_svg_layers, ImportOptions, ImportedShape};
+
+// Re-export the versioned scene file format
+pub use scene_file::{
+    Axis, BoundariesSpec, GridSpec, MaterialSpec, PresetSpec, ProbeSpec, RunSpec, SceneFile,
+    SceneSetup, ShapeSpec, SideSpec, SourceSpec, UnitsSpec, WaveformSpec, SCENE_VERSION,
+};
+
+// Re-export physical unit conversions
+pub use units::{UnitSystem, DEFAULT_CELL_SIZE, SPEED_OF_LIGHT};
+
 /// Initialize the Wasm module (call once at startup)
+#[cfg(feature = "wasm")]
 #[wasm_bindgen(start)]
 pub fn init() {
     // Set panic hook for better error messages
@@ -56,13 +114,14 @@ pub fn init() {
 }
 
 /// Get library version string
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub fn get_version() -> String {
     env!("CARGO_PKG_VERSION").to_string()
 }
 
 /// Get WebAssembly memory for zero-copy array access
 /// JavaScript can create Float32Array views over this memory
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
 pub fn get_wasm_memory() -> JsValue {
     wasm_bindgen::memory()
@@ -2,31 +2,240 @@
 //!
 //! Defines electromagnetic material properties and provides
 //! preset configurations for common materials.
+//!
+//! Dispersive materials add Drude, Lorentz and Debye poles on top of the
+//! high-frequency permittivity `epsilon_r` (ε∞).
 
+use num_complex::Complex;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
+use crate::units::SPEED_OF_LIGHT;
+
+/// Dispersion model of a single pole
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PoleType {
+    /// Free-electron response: χ = -ωp² / (ω² + iγω)
+    Drude = 0,
+    /// Damped resonance: χ = Δε·ω0² / (ω0² - ω² - iγω)
+    Lorentz = 1,
+    /// Relaxation: χ = Δε / (1 - iωτ)
+    Debye = 2,
+}
+
+/// One pole of a frequency-dependent permittivity
+///
+/// Frequencies are angular and, like times, expressed in normalized units
+/// (one time unit = Δx/c). Use `normalized` to convert SI parameters.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Pole {
+    /// Dispersion model
+    #[serde(rename = "type")]
+    pub pole_type: PoleType,
+    /// Permittivity step Δε (Lorentz, Debye)
+    #[serde(default)]
+    pub delta_eps: f32,
+    /// Plasma frequency ωp (Drude) or resonance frequency ω0 (Lorentz)
+    #[serde(default)]
+    pub omega: f32,
+    /// Damping / collision frequency γ (Drude, Lorentz)
+    #[serde(default)]
+    pub gamma: f32,
+    /// Relaxation time τ (Debye)
+    #[serde(default)]
+    pub tau: f32,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Pole {
+    /// Create a Drude pole with plasma frequency ωp and collision frequency γ
+    /// Negative frequencies are clamped to 0 (see `try_drude`).
+    pub fn drude(omega_p: f32, gamma: f32) -> Pole {
+        Pole {
+            pole_type: PoleType::Drude,
+            delta_eps: 0.0,
+            omega: omega_p.max(0.0),
+            gamma: gamma.max(0.0),
+            tau: 0.0,
+        }
+    }
+
+    /// Create a Lorentz pole with strength Δε, resonance ω0 and damping γ
+    /// Negative frequencies are clamped to 0 (see `try_lorentz`).
+    pub fn lorentz(delta_eps: f32, omega_0: f32, gamma: f32) -> Pole {
+        Pole {
+            pole_type: PoleType::Lorentz,
+            delta_eps,
+            omega: omega_0.max(0.0),
+            gamma: gamma.max(0.0),
+            tau: 0.0,
+        }
+    }
+
+    /// Create a Debye pole with strength Δε and relaxation time τ
+    /// Relaxation times below 1e-6 are raised to it (see `try_debye`).
+    pub fn debye(delta_eps: f32, tau: f32) -> Pole {
+        Pole {
+            pole_type: PoleType::Debye,
+            delta_eps,
+            omega: 0.0,
+            gamma: 0.0,
+            tau: tau.max(1e-6),
+        }
+    }
+
+    /// Convert a pole given in SI units (rad/s, s) to normalized units
+    /// for a cell size in metres
+    pub fn normalized(&self, cell_size: f32) -> Pole {
+        let time_unit = (cell_size as f64 / SPEED_OF_LIGHT) as f32; // seconds per normalized time unit
+        Pole {
+            omega: self.omega * time_unit,
+            gamma: self.gamma * time_unit,
+            tau: self.tau / time_unit,
+            ..*self
+        }
+    }
+}
+
+impl Pole {
+    /// Create a Drude pole, rejecting negative or non-finite frequencies
+    pub fn try_drude(omega_p: f32, gamma: f32) -> Result<Pole, String> {
+        let pole = Pole {
+            omega: omega_p,
+            gamma,
+            ..Pole::drude(0.0, 0.0)
+        };
+        pole.validate()?;
+        Ok(pole)
+    }
+
+    /// Create a Lorentz pole, rejecting negative or non-finite frequencies
+    pub fn try_lorentz(delta_eps: f32, omega_0: f32, gamma: f32) -> Result<Pole, String> {
+        let pole = Pole {
+            omega: omega_0,
+            gamma,
+            ..Pole::lorentz(delta_eps, 0.0, 0.0)
+        };
+        pole.validate()?;
+        Ok(pole)
+    }
+
+    /// Create a Debye pole, rejecting non-positive relaxation times
+    pub fn try_debye(delta_eps: f32, tau: f32) -> Result<Pole, String> {
+        let pole = Pole {
+            tau,
+            ..Pole::debye(delta_eps, 1.0)
+        };
+        pole.validate()?;
+        Ok(pole)
+    }
+
+    /// Check that Δε is finite, the frequencies non-negative and (Debye)
+    /// the relaxation time positive
+    pub fn validate(&self) -> Result<(), String> {
+        let non_negative = |v: f32| v.is_finite() && v >= 0.0;
+        if !self.delta_eps.is_finite() {
+            return Err(format!("pole strength {} must be finite", self.delta_eps));
+        }
+        if !non_negative(self.omega) || !non_negative(self.gamma) {
+            return Err(format!(
+                "pole frequencies {} and {} must not be negative",
+                self.omega, self.gamma
+            ));
+        }
+        if self.pole_type == PoleType::Debye && !(self.tau.is_finite() && self.tau > 0.0) {
+            return Err(format!("relaxation time {} must be positive", self.tau));
+        }
+        Ok(())
+    }
+
+    /// Susceptibility χ(ω) of this pole (e^{-iωt} convention, Im χ > 0 for loss)
+    pub fn susceptibility(&self, omega: f32) -> Complex<f32> {
+        let w = Complex::new(omega, 0.0);
+        let i = Complex::new(0.0, 1.0);
+        match self.pole_type {
+            PoleType::Drude => -(self.omega * self.omega) / (w * w + i * self.gamma * w),
+            PoleType::Lorentz => {
+                let w0 = self.omega * self.omega;
+                self.delta_eps * w0 / (w0 - w * w - i * self.gamma * w)
+            }
+            PoleType::Debye => self.delta_eps / (1.0 - i * w * self.tau),
+        }
+    }
+
+    /// Coefficients [c1, c2, c3] of the explicit polarization update
+    /// P^{n+1} = c1·P^n + c2·P^{n-1} + c3·E^n for time step `dt`
+    pub fn ade_coefficients(&self, dt: f32) -> [f32; 3] {
+        match self.pole_type {
+            PoleType::Debye => {
+                // τP' + P = ΔεE, trapezoidal in P
+                let denom = 2.0 * self.tau + dt;
+                [
+                    (2.0 * self.tau - dt) / denom,
+                    0.0,
+                    2.0 * self.delta_eps * dt / denom,
+                ]
+            }
+            PoleType::Drude | PoleType::Lorentz => {
+                // P'' + γP' + ω0²P = ωp²E, central differences at step n
+                let (w0_sq, wp_sq) = match self.pole_type {
+                    PoleType::Drude => (0.0, self.omega * self.omega),
+                    _ => {
+                        let w0_sq = self.omega * self.omega;
+                        (w0_sq, self.delta_eps * w0_sq)
+                    }
+                };
+                let denom = 1.0 + 0.5 * self.gamma * dt;
+                [
+                    (2.0 - w0_sq * dt * dt) / denom,
+                    (0.5 * self.gamma * dt - 1.0) / denom,
+                    wp_sq * dt * dt / denom,
+                ]
+            }
+        }
+    }
+}
+
 /// Material properties for electromagnetic simulation
-#[wasm_bindgen]
-#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Material {
     /// Relative permittivity (dielectric constant)
     /// ε_r = 1.0 for vacuum, 2.25 for glass, 78 for water
     pub epsilon_r: f32,
 
     /// Relative permeability (usually 1.0 for non-magnetic materials)
+    #[serde(default = "unit_permeability")]
     pub mu_r: f32,
 
     /// Electrical conductivity (S/m)
     /// σ = 0 for perfect dielectrics, high for metals
+    #[serde(default)]
     pub sigma: f32,
 
+    /// Magnetic conductivity (Ω/m)
+    /// σ_m = 0 for ordinary materials; σ_m/μ = σ/ε gives a matched absorber
+    #[serde(default)]
+    pub sigma_m: f32,
+
     /// Material type identifier for special handling
+    #[serde(rename = "type", default = "dielectric")]
     pub material_type: MaterialType,
+
+    /// Dispersive poles (empty for non-dispersive materials)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    poles: Vec<Pole>,
 }
 
 /// Material type for special handling in physics engine
-#[wasm_bindgen]
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum MaterialType {
     /// Normal dielectric material
     Dielectric = 0,
@@ -38,16 +247,38 @@ pub enum MaterialType {
     Source = 3,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Material {
     /// Create a new material with specified properties
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new(epsilon_r: f32, mu_r: f32, sigma: f32) -> Material {
         Material {
             epsilon_r: epsilon_r.max(0.01), // Prevent division by zero
             mu_r: mu_r.max(0.01),
             sigma: sigma.max(0.0),
+            sigma_m: 0.0,
             material_type: MaterialType::Dielectric,
+            poles: Vec::new(),
+        }
+    }
+
+    /// Create a material with magnetic loss
+    pub fn new_magnetic(epsilon_r: f32, mu_r: f32, sigma: f32, sigma_m: f32) -> Material {
+        Material {
+            sigma_m: sigma_m.max(0.0),
+            ..Material::new(epsilon_r, mu_r, sigma)
+        }
+    }
+
+    /// Create an impedance-matched absorber (σ_m/μ = σ/ε)
+    /// Its wave impedance equals that of a lossless medium with the same ε and μ,
+    /// so normally incident waves enter without reflection.
+    pub fn matched_absorber(epsilon_r: f32, mu_r: f32, sigma: f32) -> Material {
+        let base = Material::new(epsilon_r, mu_r, sigma);
+        Material {
+            sigma_m: base.sigma * base.mu_r / base.epsilon_r,
+            material_type: MaterialType::Absorber,
+            ..base
         }
     }
 
@@ -57,7 +288,9 @@ impl Material {
             epsilon_r: 1.0,
             mu_r: 1.0,
             sigma: 0.0,
+            sigma_m: 0.0,
             material_type: MaterialType::PEC,
+            poles: Vec::new(),
         }
     }
 
@@ -67,7 +300,9 @@ impl Material {
             epsilon_r: 1.0,
             mu_r: 1.0,
             sigma,
+            sigma_m: 0.0,
             material_type: MaterialType::Absorber,
+            poles: Vec::new(),
         }
     }
 
@@ -75,6 +310,147 @@ impl Material {
     pub fn is_pec(&self) -> bool {
         self.material_type == MaterialType::PEC
     }
+
+    /// Add a dispersive pole (epsilon_r then acts as ε∞)
+    pub fn add_pole(&mut self, pole: &Pole) {
+        self.poles.push(*pole);
+    }
+
+    /// Remove all dispersive poles
+    pub fn clear_poles(&mut self) {
+        self.poles.clear();
+    }
+
+    /// Get number of dispersive poles
+    pub fn get_pole_count(&self) -> usize {
+        self.poles.len()
+    }
+
+    /// Get one dispersive pole
+    pub fn get_pole(&self, index: usize) -> Option<Pole> {
+        self.poles.get(index).copied()
+    }
+
+    /// Check if this material has any dispersive poles
+    pub fn is_dispersive(&self) -> bool {
+        !self.poles.is_empty() && !self.is_pec()
+    }
+}
+
+impl Material {
+    /// Create a material, rejecting the values `new` would clamp
+    pub fn try_new(epsilon_r: f32, mu_r: f32, sigma: f32) -> Result<Material, String> {
+        let material = Material {
+            epsilon_r,
+            mu_r,
+            sigma,
+            ..Material::new(1.0, 1.0, 0.0)
+        };
+        material.validate()?;
+        Ok(material)
+    }
+
+    /// Check that ε and μ are positive, the conductivities non-negative
+    /// and every pole valid
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = |v: f32| v.is_finite() && v > 0.0;
+        let non_negative = |v: f32| v.is_finite() && v >= 0.0;
+        if !positive(self.epsilon_r) || !positive(self.mu_r) {
+            return Err(format!(
+                "permittivity {} and permeability {} must be positive",
+                self.epsilon_r, self.mu_r
+            ));
+        }
+        if !non_negative(self.sigma) || !non_negative(self.sigma_m) {
+            return Err(format!(
+                "conductivities {} and {} must not be negative",
+                self.sigma, self.sigma_m
+            ));
+        }
+        self.poles.iter().try_for_each(Pole::validate)
+    }
+
+    /// Add a dispersive pole (builder style)
+    pub fn with_pole(mut self, pole: Pole) -> Material {
+        self.poles.push(pole);
+        self
+    }
+
+    /// Get the dispersive poles
+    pub fn poles(&self) -> &[Pole] {
+        &self.poles
+    }
+
+    /// Complex relative permittivity ε(ω) = ε∞ + Σχ(ω) + iσ/ω (normalized ω)
+    pub fn permittivity(&self, omega: f32) -> Complex<f32> {
+        let mut eps = Complex::new(self.epsilon_r, 0.0);
+        for pole in &self.poles {
+            eps += pole.susceptibility(omega);
+        }
+        if omega > 0.0 {
+            eps += Complex::new(0.0, self.sigma / omega);
+        }
+        eps
+    }
+
+    /// E-field update coefficients (ca, cb) for time step `dt` and Courant number `courant`
+    ///
+    /// ca = (1 - σΔt/(2ε)) / (1 + σΔt/(2ε)), cb = (courant/ε) / (1 + σΔt/(2ε));
+    /// PEC gives (0, 0).
+    pub fn electric_coefficients(&self, dt: f32, courant: f32) -> (f32, f32) {
+        if self.is_pec() {
+            return (0.0, 0.0);
+        }
+        loss_coefficients(self.epsilon_r, self.sigma, dt, courant)
+    }
+
+    /// H-field update coefficients (da, db), the magnetic dual of `electric_coefficients`
+    pub fn magnetic_coefficients(&self, dt: f32, courant: f32) -> (f32, f32) {
+        loss_coefficients(self.mu_r, self.sigma_m, dt, courant)
+    }
+}
+
+/// Permeability of materials read without one
+fn unit_permeability() -> f32 {
+    1.0
+}
+
+/// Type of materials read without one
+fn dielectric() -> MaterialType {
+    MaterialType::Dielectric
+}
+
+/// Decay and curl coefficients of a lossy update with relative constant `rel` and loss `loss`
+fn loss_coefficients(rel: f32, loss: f32, dt: f32, courant: f32) -> (f32, f32) {
+    let rel = rel.max(0.01); // Prevent division by zero
+    let loss_term = loss * dt / (2.0 * rel);
+    let denominator = 1.0 + loss_term;
+    (
+        (1.0 - loss_term) / denominator,
+        (courant / rel) / denominator,
+    )
+}
+
+/// Rescale (decay, curl) coefficients from one Courant number to another
+///
+/// Inverts `loss_coefficients` for the stored relative constant and loss,
+/// assuming Δt ∝ courant (fixed Δx). PEC cells (0, 0) are left unchanged.
+pub(crate) fn rescale_coefficients(
+    decay: f32,
+    curl: f32,
+    old_courant: f32,
+    new_courant: f32,
+) -> (f32, f32) {
+    if curl == 0.0 {
+        return (decay, curl);
+    }
+    let loss_term = (1.0 - decay) / (1.0 + decay);
+    let new_loss_term = loss_term * new_courant / old_courant;
+    let denominator = 1.0 + new_loss_term;
+    (
+        (1.0 - new_loss_term) / denominator,
+        curl * (1.0 + loss_term) * (new_courant / old_courant) / denominator,
+    )
 }
 
 // ============================================================================
@@ -82,10 +458,10 @@ impl Material {
 // ============================================================================
 
 /// Material ID constants for JavaScript interop
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct MaterialPresets;
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl MaterialPresets {
     /// Get Vacuum material (ε=1, σ=0)
     pub fn vacuum() -> Material {
@@ -136,36 +512,236 @@ impl MaterialPresets {
     pub fn strong_absorber() -> Material {
         Material::absorber(2.0)
     }
+
+    /// Get Gold (Drude: ε∞ = 9.5, ωp = 1.36e16 rad/s, γ = 1.05e14 rad/s)
+    /// for a cell size in metres
+    pub fn gold(cell_size: f32) -> Material {
+        Material::new(9.5, 1.0, 0.0).with_pole(Pole::drude(1.36e16, 1.05e14).normalized(cell_size))
+    }
+
+    /// Get Silver (Drude: ε∞ = 3.7, ωp = 1.38e16 rad/s, γ = 2.73e13 rad/s)
+    /// for a cell size in metres
+    pub fn silver(cell_size: f32) -> Material {
+        Material::new(3.7, 1.0, 0.0).with_pole(Pole::drude(1.38e16, 2.73e13).normalized(cell_size))
+    }
+
+    /// Get dispersive Water (Debye: ε∞ = 5.2, εs = 78.4, τ = 8.27 ps)
+    /// for a cell size in metres
+    pub fn dispersive_water(cell_size: f32) -> Material {
+        Material::new(5.2, 1.0, 0.0).with_pole(Pole::debye(73.2, 8.27e-12).normalized(cell_size))
+    }
 }
 
+/// Named constructor of a built-in preset
+type Preset = (&'static str, fn() -> Material);
+
+/// Built-in presets addressable by ID, in ID order
+const PRESETS: [Preset; 7] = [
+    ("Vacuum", MaterialPresets::vacuum),
+    ("Glass", MaterialPresets::glass),
+    ("Water", MaterialPresets::water),
+    ("Metal", MaterialPresets::metal),
+    ("Absorber", MaterialPresets::absorber),
+    ("Crystal", MaterialPresets::crystal),
+    ("Silicon", MaterialPresets::silicon),
+];
+
 /// Get material by ID (for JavaScript interop)
 /// 0 = Vacuum, 1 = Glass, 2 = Water, 3 = Metal, 4 = Absorber, 5 = Crystal, 6 = Silicon
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub fn get_material_by_id(id: u32) -> Material {
-    match id {
-        0 => MaterialPresets::vacuum(),
-        1 => MaterialPresets::glass(),
-        2 => MaterialPresets::water(),
-        3 => MaterialPresets::metal(),
-        4 => MaterialPresets::absorber(),
-        5 => MaterialPresets::crystal(),
-        6 => MaterialPresets::silicon(),
-        _ => MaterialPresets::vacuum(),
-    }
+    PRESETS
+        .get(id as usize)
+        .map_or_else(MaterialPresets::vacuum, |(_, make)| make())
 }
 
 /// Get material name by ID
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub fn get_material_name(id: u32) -> String {
-    match id {
-        0 => "Vacuum".to_string(),
-        1 => "Glass".to_string(),
-        2 => "Water".to_string(),
-        3 => "Metal".to_string(),
-        4 => "Absorber".to_string(),
-        5 => "Crystal".to_string(),
-        6 => "Silicon".to_string(),
-        _ => "Unknown".to_string(),
+    PRESETS
+        .get(id as usize)
+        .map_or("Unknown", |(name, _)| name)
+        .to_string()
+}
+
+// ============================================================================
+// Material Table
+// ============================================================================
+
+/// Named entry of a material table
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct MaterialEntry {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub id: u32,
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub name: String,
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub material: Material,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl MaterialEntry {
+    /// Get the material ID (as used by `paint_*` calls)
+    pub fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    /// Get the material name
+    pub fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Get the material properties
+    pub fn get_material(&self) -> Material {
+        self.material.clone()
+    }
+}
+
+/// Materials referenced by a grid's per-cell material IDs
+///
+/// IDs 0-6 are the presets of `get_material_by_id`; user materials are
+/// appended after them and keep their ID for the lifetime of the table.
+#[derive(Clone, Debug)]
+pub struct MaterialTable {
+    entries: Vec<MaterialEntry>,
+    /// Unnamed entries added by `intern`, which may be recycled
+    interned: Vec<u32>,
+}
+
+impl MaterialTable {
+    /// Number of built-in preset entries
+    pub const PRESET_COUNT: u32 = PRESETS.len() as u32;
+
+    /// Create a table holding the built-in presets
+    pub fn new() -> MaterialTable {
+        let entries = PRESETS
+            .iter()
+            .enumerate()
+            .map(|(id, (name, make))| MaterialEntry {
+                id: id as u32,
+                name: name.to_string(),
+                material: make(),
+            })
+            .collect();
+        MaterialTable {
+            entries,
+            interned: Vec::new(),
+        }
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the table has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Check if an ID refers to an entry
+    pub fn contains(&self, id: u32) -> bool {
+        (id as usize) < self.entries.len()
+    }
+
+    /// Get the material of an entry
+    pub fn get(&self, id: u32) -> Option<&Material> {
+        self.entries.get(id as usize).map(|entry| &entry.material)
+    }
+
+    /// Get the name of an entry
+    pub fn name(&self, id: u32) -> Option<&str> {
+        self.entries
+            .get(id as usize)
+            .map(|entry| entry.name.as_str())
+    }
+
+    /// Iterate over the entries in ID order
+    pub fn iter(&self) -> impl Iterator<Item = &MaterialEntry> {
+        self.entries.iter()
+    }
+
+    /// Append a named material and return its ID
+    pub fn add(&mut self, name: &str, material: Material) -> u32 {
+        let id = self.entries.len() as u32;
+        self.entries.push(MaterialEntry {
+            id,
+            name: name.to_string(),
+            material,
+        });
+        id
+    }
+
+    /// Add a user material, or update the user entry with the same name
+    /// Presets are never overwritten; returns the entry's ID.
+    pub fn register(&mut self, name: &str, material: Material) -> u32 {
+        let existing = self.entries[Self::PRESET_COUNT as usize..]
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| entry.id);
+        match existing {
+            Some(id) => {
+                self.set(id, material);
+                id
+            }
+            None => self.add(name, material),
+        }
+    }
+
+    /// Find the ID of an entry by name (user entries shadow presets)
+    pub fn find_name(&self, name: &str) -> Option<u32> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.name == name)
+            .map(|entry| entry.id)
+    }
+
+    /// Replace the material of an existing entry (returns false for unknown IDs)
+    pub fn set(&mut self, id: u32, material: Material) -> bool {
+        match self.entries.get_mut(id as usize) {
+            Some(entry) => {
+                entry.material = material;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Find the first entry with exactly these properties
+    pub fn find(&self, material: &Material) -> Option<u32> {
+        self.entries
+            .iter()
+            .position(|entry| entry.material == *material)
+            .map(|id| id as u32)
+    }
+
+    /// ID of an entry with these properties, adding an unnamed one if needed
+    pub fn intern(&mut self, material: &Material) -> u32 {
+        self.intern_reusing(material, |_| true)
+    }
+
+    /// Like `intern`, but an unnamed entry for which `in_use` returns false
+    /// is overwritten instead of growing the table
+    pub fn intern_reusing(&mut self, material: &Material, in_use: impl Fn(u32) -> bool) -> u32 {
+        if let Some(id) = self.find(material) {
+            return id;
+        }
+        if let Some(&id) = self.interned.iter().find(|&&id| !in_use(id)) {
+            self.set(id, material.clone());
+            return id;
+        }
+        let name = format!("Custom {}", self.entries.len());
+        let id = self.add(&name, material.clone());
+        self.interned.push(id);
+        id
+    }
+}
+
+impl Default for MaterialTable {
+    fn default() -> Self {
+        MaterialTable::new()
     }
 }
 
@@ -188,4 +764,151 @@ mod tests {
         let glass = MaterialPresets::glass();
         assert!(!glass.is_pec());
     }
+
+    #[test]
+    fn test_update_coefficients() {
+        let glass = MaterialPresets::glass();
+        let (ca, cb) = glass.electric_coefficients(0.5, 0.5);
+        assert_eq!(ca, 1.0);
+        assert!((cb - 0.5 / 2.25).abs() < 1e-6);
+        assert_eq!(glass.magnetic_coefficients(0.5, 0.5), (1.0, 0.5));
+
+        assert_eq!(
+            MaterialPresets::metal().electric_coefficients(0.5, 0.5),
+            (0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_pole_susceptibility() {
+        // Debye: static limit Δε, Lorentz: static limit Δε, Drude: ε(ωp) = 0 without loss
+        assert!((Pole::debye(70.0, 5.0).susceptibility(0.0).re - 70.0).abs() < 1e-4);
+        assert!((Pole::lorentz(2.0, 0.3, 0.01).susceptibility(0.0).re - 2.0).abs() < 1e-4);
+
+        let metal = Material::new(1.0, 1.0, 0.0).with_pole(Pole::drude(0.5, 0.0));
+        assert!(metal.is_dispersive());
+        assert!(metal.permittivity(0.5).norm() < 1e-5);
+        assert!(metal.permittivity(0.25).re < 0.0);
+
+        // Lossy poles have positive imaginary susceptibility
+        assert!(Pole::lorentz(1.0, 0.3, 0.05).susceptibility(0.3).im > 0.0);
+        assert!(Pole::debye(10.0, 2.0).susceptibility(0.5).im > 0.0);
+    }
+
+    #[test]
+    fn test_dispersive_presets() {
+        let gold = MaterialPresets::gold(10e-9);
+        let pole = gold.get_pole(0).unwrap();
+        assert_eq!(pole.pole_type, PoleType::Drude);
+        // ωp·Δx/c ≈ 0.45 for a 10 nm cell
+        assert!((pole.omega - 0.4537).abs() < 1e-3);
+
+        let water = MaterialPresets::dispersive_water(1e-3);
+        assert!((water.permittivity(0.0).re - 78.4).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_rescale_coefficients() {
+        let material = Material::new_magnetic(4.0, 2.0, 0.3, 0.1);
+        let (ca, cb) = material.electric_coefficients(0.5, 0.5);
+        let (da, db) = material.magnetic_coefficients(0.5, 0.5);
+        let (ca2, cb2) = material.electric_coefficients(0.3, 0.3);
+        let (da2, db2) = material.magnetic_coefficients(0.3, 0.3);
+
+        let (ca_r, cb_r) = rescale_coefficients(ca, cb, 0.5, 0.3);
+        let (da_r, db_r) = rescale_coefficients(da, db, 0.5, 0.3);
+        assert!((ca_r - ca2).abs() < 1e-6 && (cb_r - cb2).abs() < 1e-6);
+        assert!((da_r - da2).abs() < 1e-6 && (db_r - db2).abs() < 1e-6);
+        assert_eq!(rescale_coefficients(0.0, 0.0, 0.5, 0.3), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_matched_absorber() {
+        let absorber = Material::matched_absorber(4.0, 2.0, 0.2);
+        assert!((absorber.sigma_m - 0.1).abs() < 1e-6);
+
+        // Equal decay per step on both updates
+        let (ca, _) = absorber.electric_coefficients(0.5, 0.5);
+        let (da, _) = absorber.magnetic_coefficients(0.5, 0.5);
+        assert!((ca - da).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_material_table() {
+        let mut table = MaterialTable::new();
+        assert_eq!(table.len(), MaterialTable::PRESET_COUNT as usize);
+        assert_eq!(table.name(2), Some("Water"));
+        assert_eq!(table.find(&MaterialPresets::metal()), Some(3));
+
+        let ferrite = Material::new(12.0, 50.0, 0.0);
+        let id = table.add("Ferrite", ferrite.clone());
+        assert_eq!(id, 7);
+        assert_eq!(table.intern(&ferrite), id);
+
+        // Unknown properties get a fresh entry
+        let custom = table.intern(&Material::new(3.0, 1.0, 0.1));
+        assert_eq!(custom, 8);
+        assert_eq!(table.name(custom), Some("Custom 8"));
+        assert!(table.set(custom, MaterialPresets::glass()));
+        assert!(!table.set(42, MaterialPresets::glass()));
+        assert!(table.get(42).is_none());
+
+        // Unused unnamed entries are recycled, named ones never
+        let film = Material::new(4.7, 1.0, 0.0);
+        assert_eq!(table.intern_reusing(&film, |id| id != custom), custom);
+        assert_eq!(table.get(custom), Some(&film));
+        let next = table.intern_reusing(&Material::new(5.3, 1.0, 0.0), |_| true);
+        assert_eq!(next, 9);
+    }
+
+    #[test]
+    fn test_register_material() {
+        let mut table = MaterialTable::new();
+        let ptfe = table.register("PTFE", Material::new(2.1, 1.0, 0.0));
+        let fr4 = table.register("FR-4", Material::new(4.4, 1.0, 0.002));
+        assert_eq!((ptfe, fr4), (7, 8));
+
+        // Re-registering a name updates the entry in place
+        assert_eq!(table.register("PTFE", Material::new(2.05, 1.0, 0.0)), ptfe);
+        assert_eq!(table.get(ptfe).unwrap().epsilon_r, 2.05);
+
+        // A preset name creates a user entry that shadows the preset
+        let glass = table.register("Glass", Material::new(2.4, 1.0, 0.0));
+        assert_eq!(glass, 9);
+        assert_eq!(table.find_name("Glass"), Some(glass));
+        assert_eq!(table.get(1).unwrap().epsilon_r, 2.25);
+        assert!(table.iter().enumerate().all(|(i, e)| e.id == i as u32));
+    }
+
+    #[test]
+    fn test_checked_material() {
+        // `new` clamps, `try_new` rejects
+        assert_eq!(Material::new(0.0, 1.0, -1.0).epsilon_r, 0.01);
+        assert!(Material::try_new(0.0, 1.0, 0.0).is_err());
+        assert!(Material::try_new(2.0, 1.0, -1.0).is_err());
+        assert!(Material::try_new(f32::NAN, 1.0, 0.0).is_err());
+        assert_eq!(
+            Material::try_new(2.0, 1.0, 0.1),
+            Ok(Material::new(2.0, 1.0, 0.1))
+        );
+        assert!(Material::new_magnetic(2.0, 1.0, 0.0, 0.5)
+            .validate()
+            .is_ok());
+
+        // Poles are checked the same way, also inside a material
+        assert_eq!(Pole::debye(5.0, 0.0).tau, 1e-6);
+        assert!(Pole::try_debye(5.0, 0.0).is_err());
+        assert!(Pole::try_drude(-0.5, 0.0).is_err());
+        assert!(Pole::try_lorentz(2.0, 0.3, f32::NAN).is_err());
+        assert_eq!(
+            Pole::try_lorentz(2.0, 0.3, 0.01),
+            Ok(Pole::lorentz(2.0, 0.3, 0.01))
+        );
+        let mut bad = Pole::drude(0.5, 0.0);
+        bad.gamma = -1.0;
+        assert!(Material::new(1.0, 1.0, 0.0)
+            .with_pole(bad)
+            .validate()
+            .is_err());
+    }
 }
@@ -0,0 +1,198 @@
+//! Rasterization Helpers for Painting Shapes onto the Grid
+//!
+//! Cells are unit squares centred on integer coordinates: cell (x, y)
+//! covers [x - 1/2, x + 1/2] × [y - 1/2, y + 1/2]. Binary painting tests the
+//! cell centre; subpixel painting estimates the covered fraction of the square.
+
+/// Cell (x, y) with the fraction of its area covered by a shape
+pub type CoveredCell = (usize, usize, f32);
+
+/// Samples per axis for coverage estimates (64 samples per cell)
+const SUBSAMPLES: usize = 8;
+
+/// Fraction of cell (x, y) inside a shape, from a regular grid of samples
+pub fn cell_coverage(x: usize, y: usize, inside: &impl Fn(f32, f32) -> bool) -> f32 {
+    let step = 1.0 / SUBSAMPLES as f32;
+    let x0 = x as f32 - 0.5 + 0.5 * step;
+    let y0 = y as f32 - 0.5 + 0.5 * step;
+
+    let mut hits = 0;
+    for j in 0..SUBSAMPLES {
+        for i in 0..SUBSAMPLES {
+            if inside(x0 + i as f32 * step, y0 + j as f32 * step) {
+                hits += 1;
+            }
+        }
+    }
+    hits as f32 / (SUBSAMPLES * SUBSAMPLES) as f32
+}
+
+/// Cell index range [x0, y0, x1, y1] of an inclusive rectangle between two
+/// corner cells (in any order), clipped to a width × height grid
+///
+/// Corners are i64 so that shapes far off the grid (or `cx + radius` near
+/// i32::MAX) cannot wrap around. Returns None when nothing is on the grid.
+pub fn clip_cells(corners: [i64; 4], width: usize, height: usize) -> Option<[usize; 4]> {
+    let [x1, y1, x2, y2] = corners;
+    let clip = |a: i64, b: i64, n: usize| -> Option<(usize, usize)> {
+        let lo = a.min(b).max(0);
+        let hi = a.max(b).min(n as i64 - 1);
+        (lo <= hi).then_some((lo as usize, hi as usize))
+    };
+    let (x0, x1) = clip(x1, x2, width)?;
+    let (y0, y1) = clip(y1, y2, height)?;
+    Some([x0, y0, x1, y1])
+}
+
+/// Cell index range [x0, y0, x1, y1] touched by a float bounding box
+/// [x_min, y_min, x_max, y_max], clipped to a width × height grid
+pub fn clip_bounds(bounds: [f32; 4], width: usize, height: usize) -> Option<[usize; 4]> {
+    let [x_min, y_min, x_max, y_max] = bounds;
+    // Also rejects NaN bounds
+    if !(x_min <= x_max && y_min <= y_max) {
+        return None;
+    }
+    let corners = [
+        (x_min - 0.5).ceil() as i64,
+        (y_min - 0.5).ceil() as i64,
+        (x_max + 0.5).floor() as i64,
+        (y_max + 0.5).floor() as i64,
+    ];
+    clip_cells(corners, width, height)
+}
+
+/// Smallest bounding box containing both boxes (either may be missing)
+pub fn union_bounds(a: Option<[f32; 4]>, b: Option<[f32; 4]>) -> Option<[f32; 4]> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some([
+            a[0].min(b[0]),
+            a[1].min(b[1]),
+            a[2].max(b[2]),
+            a[3].max(b[3]),
+        ]),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Partially or fully covered cells of an inclusive bounding box
+///
+/// The box is given in (possibly out-of-range) float coordinates and clipped
+/// to a width × height grid. Returns (x, y, coverage) with coverage > 0.
+pub fn coverage_cells(
+    bounds: [f32; 4],
+    width: usize,
+    height: usize,
+    inside: impl Fn(f32, f32) -> bool,
+) -> Vec<CoveredCell> {
+    let Some([x0, y0, x1, y1]) = clip_bounds(bounds, width, height) else {
+        return Vec::new();
+    };
+
+    let mut cells = Vec::new();
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            let coverage = cell_coverage(x, y, &inside);
+            if coverage > 0.0 {
+                cells.push((x, y, coverage));
+            }
+        }
+    }
+    cells
+}
+
+/// Cells (x, y, 1.0) of an integer shape inside an inclusive corner box
+pub fn integer_cells(
+    corners: [i64; 4],
+    width: usize,
+    height: usize,
+    inside: impl Fn(i64, i64) -> bool,
+) -> Vec<CoveredCell> {
+    let Some([x0, y0, x1, y1]) = clip_cells(corners, width, height) else {
+        return Vec::new();
+    };
+
+    let mut cells = Vec::new();
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            if inside(x as i64, y as i64) {
+                cells.push((x, y, 1.0));
+            }
+        }
+    }
+    cells
+}
+
+/// Cells of a shape: fractional coverage when `subpixel` is set, otherwise
+/// cells whose centre is inside (coverage 1.0)
+pub fn rasterize(
+    bounds: [f32; 4],
+    width: usize,
+    height: usize,
+    subpixel: bool,
+    inside: impl Fn(f32, f32) -> bool,
+) -> Vec<CoveredCell> {
+    if subpixel {
+        return coverage_cells(bounds, width, height, inside);
+    }
+    let Some([x0, y0, x1, y1]) = clip_bounds(bounds, width, height) else {
+        return Vec::new();
+    };
+
+    let mut cells = Vec::new();
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            if inside(x as f32, y as f32) {
+                cells.push((x, y, 1.0));
+            }
+        }
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circle_coverage_area() {
+        // Total coverage approximates the disc area
+        let (cx, cy, r) = (10.3, 9.8, 5.5);
+        let cells = coverage_cells([cx - r, cy - r, cx + r, cy + r], 32, 32, |x, y| {
+            (x - cx).powi(2) + (y - cy).powi(2) <= r * r
+        });
+        let area: f32 = cells.iter().map(|c| c.2).sum();
+        let exact = std::f32::consts::PI * r * r;
+        assert!((area - exact).abs() / exact < 0.01, "{}", area);
+        assert!(cells.iter().any(|c| c.2 < 1.0));
+    }
+
+    #[test]
+    fn test_half_plane_coverage() {
+        assert_eq!(cell_coverage(3, 3, &|x, _| x <= 3.0), 0.5);
+        assert_eq!(cell_coverage(3, 3, &|x, _| x <= 10.0), 1.0);
+        // Boxes outside the grid are clipped away
+        assert!(coverage_cells([-9.0, -9.0, -5.0, -5.0], 8, 8, |_, _| true).is_empty());
+    }
+
+    #[test]
+    fn test_clipping() {
+        // Corners in any order, partly off the top-left edge
+        assert_eq!(clip_cells([3, 2, -5, -7], 8, 8), Some([0, 0, 3, 2]));
+        assert_eq!(clip_cells([-9, -9, -1, 4], 8, 8), None);
+        let far = i32::MAX as i64;
+        assert_eq!(clip_cells([far - 1, 0, far + 1, 0], 8, 8), None);
+        assert_eq!(clip_cells([0, 0, far + 1, 0], 8, 1), Some([0, 0, 7, 0]));
+        // Empty grids have no cells at all
+        assert_eq!(clip_cells([0, 0, 0, 0], 0, 0), None);
+        assert!(rasterize([0.0, 0.0, 4.0, 4.0], 0, 5, false, |_, _| true).is_empty());
+
+        assert_eq!(clip_bounds([-0.4, 1.6, 2.4, 1.7], 8, 8), Some([0, 2, 2, 2]));
+        assert_eq!(clip_bounds([0.6, 0.6, 1.4, 0.7], 8, 8), Some([1, 1, 1, 1]));
+        assert_eq!(clip_bounds([1.2, 0.0, 1.3, 0.0], 8, 8), Some([1, 0, 1, 0]));
+        assert_eq!(clip_bounds([f32::NAN, 0.0, 1.0, 1.0], 8, 8), None);
+        assert_eq!(
+            clip_bounds([-1e30, -1e30, 1e30, 1e30], 8, 8),
+            Some([0, 0, 7, 7])
+        );
+    }
+}
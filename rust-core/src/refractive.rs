@@ -0,0 +1,683 @@
+//! Refractive-Index Import and Dispersion Fitting
+//!
+//! Loads tabulated n, k versus wavelength data in the refractiveindex.info
+//! CSV and YAML layouts (wavelengths in µm) and fits the complex
+//! permittivity ε(ω) = (n + ik)² over a chosen band with
+//! ε(ω) = ε∞ + Σ Lorentz(Δε, ω0, γ) [+ Drude(ωp, γ)]
+//! using Levenberg–Marquardt least squares.
+//!
+//! The fit is kept FDTD-friendly: ε∞ ≥ 1 (no faster-than-light background)
+//! and every pole parameter is positive (passive, causal poles).
+
+use num_complex::Complex;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::materials::{Material, Pole};
+use crate::units::SPEED_OF_LIGHT;
+
+/// Wavelength unit of imported files (µm)
+const MICRON: f64 = 1e-6;
+
+/// Log-parameter bounds of the fit (keeps exp() finite)
+const PARAM_LIMIT: f64 = 30.0;
+
+/// Tabulated complex refractive index n + ik versus wavelength
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug, Default)]
+pub struct RefractiveIndexData {
+    wavelengths: Vec<f64>, // Metres, ascending
+    n: Vec<f64>,
+    k: Vec<f64>,
+}
+
+/// Which quantity the rows of a data block carry after the wavelength
+#[derive(Clone, Copy, PartialEq)]
+enum Columns {
+    NK,
+    N,
+    K,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl RefractiveIndexData {
+    /// Build from samples (wavelengths in metres); k may be empty for lossless data
+    pub fn from_samples(
+        wavelengths: Vec<f64>,
+        n: Vec<f64>,
+        k: Vec<f64>,
+    ) -> Result<RefractiveIndexData, String> {
+        if wavelengths.len() != n.len() || !(k.is_empty() || k.len() == n.len()) {
+            return Err("wavelength, n and k arrays must have the same length".to_string());
+        }
+        let k = if k.is_empty() { vec![0.0; n.len()] } else { k };
+        let rows = wavelengths
+            .iter()
+            .zip(&n)
+            .zip(&k)
+            .map(|((&wl, &n), &k)| (wl, n, k))
+            .collect();
+        RefractiveIndexData::from_rows(rows)
+    }
+
+    /// Parse refractiveindex.info CSV: `wl,n,k` rows, or a `wl,n` block
+    /// followed by a `wl,k` block (wavelengths in µm, headers optional)
+    pub fn from_csv(text: &str) -> Result<RefractiveIndexData, String> {
+        let mut blocks: Vec<(Columns, Vec<Vec<f64>>)> = Vec::new();
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            match parse_numbers(line) {
+                Some(values) => {
+                    if blocks.is_empty() {
+                        let columns = if values.len() >= 3 {
+                            Columns::NK
+                        } else {
+                            Columns::N
+                        };
+                        blocks.push((columns, Vec::new()));
+                    }
+                    blocks.last_mut().unwrap().1.push(values);
+                }
+                None => blocks.push((header_columns(line), Vec::new())),
+            }
+        }
+        RefractiveIndexData::from_blocks(&blocks)
+    }
+
+    /// Parse a refractiveindex.info YAML entry with `tabulated nk`,
+    /// `tabulated n` and/or `tabulated k` data (wavelengths in µm)
+    pub fn from_yaml(text: &str) -> Result<RefractiveIndexData, String> {
+        let mut blocks: Vec<(Columns, Vec<Vec<f64>>)> = Vec::new();
+        let mut kind: Option<String> = None;
+        let mut data_indent: Option<usize> = None;
+
+        for line in text.lines() {
+            let trimmed = line.trim();
+            let indent = line.len() - line.trim_start().len();
+
+            if let Some(base) = data_indent {
+                if !trimmed.is_empty() && indent > base {
+                    let values = parse_numbers(trimmed)
+                        .ok_or_else(|| format!("invalid data row: '{}'", trimmed))?;
+                    blocks.last_mut().unwrap().1.push(values);
+                    continue;
+                }
+                if trimmed.is_empty() {
+                    continue;
+                }
+                data_indent = None;
+            }
+
+            let entry = trimmed.trim_start_matches("- ");
+            if let Some(value) = entry.strip_prefix("type:") {
+                kind = Some(value.trim().to_lowercase());
+            } else if entry.starts_with("data:") {
+                let columns = match kind.as_deref() {
+                    Some("tabulated nk") => Columns::NK,
+                    Some("tabulated n") => Columns::N,
+                    Some("tabulated k") => Columns::K,
+                    Some(other) => {
+                        return Err(format!(
+                            "unsupported data type '{}' (only tabulated data)",
+                            other
+                        ))
+                    }
+                    None => return Err("data block without a type".to_string()),
+                };
+                blocks.push((columns, Vec::new()));
+                data_indent = Some(indent);
+            }
+        }
+        RefractiveIndexData::from_blocks(&blocks)
+    }
+
+    /// Get number of samples
+    pub fn get_sample_count(&self) -> usize {
+        self.wavelengths.len()
+    }
+
+    /// Get shortest tabulated wavelength in metres
+    pub fn get_min_wavelength(&self) -> f64 {
+        self.wavelengths.first().copied().unwrap_or(0.0)
+    }
+
+    /// Get longest tabulated wavelength in metres
+    pub fn get_max_wavelength(&self) -> f64 {
+        self.wavelengths.last().copied().unwrap_or(0.0)
+    }
+
+    /// Get n at a wavelength in metres (linear interpolation, clamped)
+    pub fn get_n_at(&self, wavelength: f64) -> f64 {
+        interpolate(&self.wavelengths, &self.n, wavelength)
+    }
+
+    /// Get k at a wavelength in metres (linear interpolation, clamped)
+    pub fn get_k_at(&self, wavelength: f64) -> f64 {
+        interpolate(&self.wavelengths, &self.k, wavelength)
+    }
+
+    /// Fit Lorentz (and optionally one Drude) poles to the samples in
+    /// [lambda_min, lambda_max] (metres)
+    pub fn fit(
+        &self,
+        lambda_min: f64,
+        lambda_max: f64,
+        lorentz_poles: usize,
+        drude: bool,
+    ) -> Result<DispersionFit, String> {
+        let (lo, hi) = (lambda_min.min(lambda_max), lambda_min.max(lambda_max));
+        let samples: Vec<(f64, Complex<f64>)> = self
+            .wavelengths
+            .iter()
+            .zip(self.n.iter().zip(&self.k))
+            .filter(|(&wl, _)| wl >= lo && wl <= hi)
+            .map(|(&wl, (&n, &k))| {
+                let index = Complex::new(n, k);
+                (wavelength_to_omega(wl), index * index)
+            })
+            .collect();
+
+        let model = PoleModel {
+            lorentz: lorentz_poles,
+            drude,
+        };
+        if samples.len() < 2 || 2 * samples.len() < model.param_count() {
+            return Err(format!(
+                "{} samples in the band are too few for {} fit parameters",
+                samples.len(),
+                model.param_count()
+            ));
+        }
+        Ok(model.fit(&samples, lo, hi))
+    }
+}
+
+impl RefractiveIndexData {
+    /// Sorted data from (wavelength [m], n, k) rows
+    fn from_rows(mut rows: Vec<(f64, f64, f64)>) -> Result<RefractiveIndexData, String> {
+        rows.retain(|&(wl, n, k)| wl > 0.0 && n.is_finite() && k.is_finite());
+        if rows.is_empty() {
+            return Err("no refractive index samples".to_string());
+        }
+        rows.sort_by(|a, b| a.0.total_cmp(&b.0));
+        rows.dedup_by(|a, b| a.0 == b.0);
+        Ok(RefractiveIndexData {
+            wavelengths: rows.iter().map(|r| r.0).collect(),
+            n: rows.iter().map(|r| r.1).collect(),
+            k: rows.iter().map(|r| r.2).collect(),
+        })
+    }
+
+    /// Merge parsed n / k / nk blocks (µm) onto the wavelengths of the n data
+    fn from_blocks(blocks: &[(Columns, Vec<Vec<f64>>)]) -> Result<RefractiveIndexData, String> {
+        let mut n_rows: Vec<(f64, f64)> = Vec::new();
+        let mut k_rows: Vec<(f64, f64)> = Vec::new();
+        for (columns, rows) in blocks {
+            for row in rows {
+                let wl = row[0] * MICRON;
+                match (columns, row.len()) {
+                    (Columns::NK, 3..) => {
+                        n_rows.push((wl, row[1]));
+                        k_rows.push((wl, row[2]));
+                    }
+                    (Columns::N, 2..) => n_rows.push((wl, row[1])),
+                    (Columns::K, 2..) => k_rows.push((wl, row[1])),
+                    _ => return Err(format!("row {:?} has too few columns", row)),
+                }
+            }
+        }
+        if n_rows.is_empty() {
+            return Err("no n data found".to_string());
+        }
+
+        k_rows.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let (k_wl, k_val): (Vec<f64>, Vec<f64>) = k_rows.into_iter().unzip();
+        let rows = n_rows
+            .into_iter()
+            .map(|(wl, n)| (wl, n, interpolate(&k_wl, &k_val, wl)))
+            .collect();
+        RefractiveIndexData::from_rows(rows)
+    }
+}
+
+/// Result of a dispersion fit
+///
+/// Poles are stored in SI units (rad/s); `to_material` converts them for a
+/// grid with a given cell size.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct DispersionFit {
+    eps_inf: f64,
+    poles: Vec<Pole>,
+    rms_error: f64,
+    max_error: f64,
+    sample_count: usize,
+    lambda_min: f64,
+    lambda_max: f64,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl DispersionFit {
+    /// Get the fitted high-frequency permittivity ε∞
+    pub fn get_eps_inf(&self) -> f64 {
+        self.eps_inf
+    }
+
+    /// Get number of fitted poles
+    pub fn get_pole_count(&self) -> usize {
+        self.poles.len()
+    }
+
+    /// Get one fitted pole (SI units: rad/s)
+    pub fn get_pole(&self, index: usize) -> Option<Pole> {
+        self.poles.get(index).copied()
+    }
+
+    /// Get the RMS error |ñ_fit - ñ_data| over the fitted samples
+    pub fn get_rms_error(&self) -> f64 {
+        self.rms_error
+    }
+
+    /// Get the largest error |ñ_fit - ñ_data| over the fitted samples
+    pub fn get_max_error(&self) -> f64 {
+        self.max_error
+    }
+
+    /// Get number of samples used by the fit
+    pub fn get_sample_count(&self) -> usize {
+        self.sample_count
+    }
+
+    /// Get the fitted band [min, max] in metres
+    pub fn get_band(&self) -> Vec<f64> {
+        vec![self.lambda_min, self.lambda_max]
+    }
+
+    /// Fitted [n, k] at a wavelength in metres
+    pub fn index_at(&self, wavelength: f64) -> Vec<f64> {
+        let index = self.permittivity(wavelength_to_omega(wavelength)).sqrt();
+        vec![index.re, index.im]
+    }
+
+    /// Build a dispersive material for a grid with the given cell size (m)
+    pub fn to_material(&self, cell_size: f32) -> Material {
+        self.poles.iter().fold(
+            Material::new(self.eps_inf as f32, 1.0, 0.0),
+            |material, pole| material.with_pole(pole.normalized(cell_size)),
+        )
+    }
+}
+
+impl DispersionFit {
+    /// Fitted permittivity at angular frequency ω (rad/s)
+    pub fn permittivity(&self, omega: f64) -> Complex<f64> {
+        let w = Complex::new(omega, 0.0);
+        let i = Complex::new(0.0, 1.0);
+        self.poles
+            .iter()
+            .fold(Complex::new(self.eps_inf, 0.0), |eps, pole| {
+                let (w0, g) = (pole.omega as f64, pole.gamma as f64);
+                eps + match pole.pole_type {
+                    crate::materials::PoleType::Drude => -(w0 * w0) / (w * w + i * g * w),
+                    _ => pole.delta_eps as f64 * w0 * w0 / (w0 * w0 - w * w - i * g * w),
+                }
+            })
+    }
+}
+
+/// Lorentz/Drude model in log parameters, frequencies scaled by a reference ω
+///
+/// Layout: [ln(ε∞ - 1), (ln Δε, ln ω0, ln γ) per Lorentz pole, (ln ωp, ln γ)]
+struct PoleModel {
+    lorentz: usize,
+    drude: bool,
+}
+
+impl PoleModel {
+    fn param_count(&self) -> usize {
+        1 + 3 * self.lorentz + if self.drude { 2 } else { 0 }
+    }
+
+    /// Model permittivity at scaled frequency w
+    fn eval(&self, p: &[f64], w: f64) -> Complex<f64> {
+        let w = Complex::new(w, 0.0);
+        let i = Complex::new(0.0, 1.0);
+        let mut eps = Complex::new(1.0 + p[0].exp(), 0.0);
+        for l in 0..self.lorentz {
+            let (de, w0, g) = (p[1 + 3 * l].exp(), p[2 + 3 * l].exp(), p[3 + 3 * l].exp());
+            eps += de * w0 * w0 / (w0 * w0 - w * w - i * g * w);
+        }
+        if self.drude {
+            let base = 1 + 3 * self.lorentz;
+            let (wp, g) = (p[base].exp(), p[base + 1].exp());
+            eps -= wp * wp / (w * w + i * g * w);
+        }
+        eps
+    }
+
+    /// Weighted residuals [Re, Im] per sample (relative for |ε| > 1)
+    fn residuals(&self, p: &[f64], samples: &[(f64, Complex<f64>)], out: &mut Vec<f64>) {
+        out.clear();
+        for &(w, eps) in samples {
+            let diff = (self.eval(p, w) - eps) / eps.norm().max(1.0);
+            out.push(diff.re);
+            out.push(diff.im);
+        }
+    }
+
+    /// Starting points: poles above the band, and poles spread across it
+    fn initial_guesses(&self, samples: &[(f64, Complex<f64>)]) -> Vec<Vec<f64>> {
+        let w_lo = samples.iter().map(|s| s.0).fold(f64::INFINITY, f64::min);
+        let w_hi = samples.iter().map(|s| s.0).fold(0.0, f64::max);
+        let mean_re = samples.iter().map(|s| s.1.re).sum::<f64>() / samples.len() as f64;
+        let strength = ((mean_re - 1.0).max(0.2) / self.lorentz.max(1) as f64).ln();
+
+        let layouts: [Box<dyn Fn(usize) -> f64>; 2] = [
+            Box::new(|l| w_hi * 2.0 * 3.0f64.powi(l as i32)),
+            Box::new(|l| {
+                let t = (l as f64 + 0.5) / self.lorentz as f64;
+                (w_lo / 2.0) * (2.0 * w_hi / (w_lo / 2.0)).powf(t)
+            }),
+        ];
+        layouts
+            .iter()
+            .map(|w0_of| {
+                let mut p = vec![(0.5f64).ln()];
+                for l in 0..self.lorentz {
+                    let w0 = w0_of(l);
+                    p.extend([strength, w0.ln(), (0.05 * w0).ln()]);
+                }
+                if self.drude {
+                    let low = samples[samples.len() - 1];
+                    let wp = low.0 * (1.0 - low.1.re).max(1.0).sqrt();
+                    p.extend([wp.ln(), (0.05 * w_lo).ln()]);
+                }
+                p
+            })
+            .collect()
+    }
+
+    /// Fit scaled samples and convert the best result back to SI poles
+    fn fit(&self, samples: &[(f64, Complex<f64>)], lo: f64, hi: f64) -> DispersionFit {
+        // Work in units of the band's geometric-mean frequency
+        let w_ref = wavelength_to_omega((lo * hi).sqrt());
+        let scaled: Vec<(f64, Complex<f64>)> =
+            samples.iter().map(|&(w, e)| (w / w_ref, e)).collect();
+
+        let (params, _) = self
+            .initial_guesses(&scaled)
+            .into_iter()
+            .map(|p0| levenberg_marquardt(p0, |p, out| self.residuals(p, &scaled, out)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+
+        let mut poles = Vec::with_capacity(self.lorentz + 1);
+        for l in 0..self.lorentz {
+            let (de, w0, g) = (
+                params[1 + 3 * l].exp(),
+                params[2 + 3 * l].exp(),
+                params[3 + 3 * l].exp(),
+            );
+            poles.push(Pole::lorentz(
+                de as f32,
+                (w0 * w_ref) as f32,
+                (g * w_ref) as f32,
+            ));
+        }
+        if self.drude {
+            let base = 1 + 3 * self.lorentz;
+            let (wp, g) = (params[base].exp(), params[base + 1].exp());
+            poles.push(Pole::drude((wp * w_ref) as f32, (g * w_ref) as f32));
+        }
+
+        let mut fit = DispersionFit {
+            eps_inf: 1.0 + params[0].exp(),
+            poles,
+            rms_error: 0.0,
+            max_error: 0.0,
+            sample_count: samples.len(),
+            lambda_min: lo,
+            lambda_max: hi,
+        };
+
+        let errors: Vec<f64> = samples
+            .iter()
+            .map(|&(w, eps)| (fit.permittivity(w).sqrt() - eps.sqrt()).norm())
+            .collect();
+        fit.rms_error = (errors.iter().map(|e| e * e).sum::<f64>() / errors.len() as f64).sqrt();
+        fit.max_error = errors.iter().copied().fold(0.0, f64::max);
+        fit
+    }
+}
+
+/// Minimize |r(p)|² with a damped Gauss-Newton iteration; returns (p, cost)
+fn levenberg_marquardt(
+    mut p: Vec<f64>,
+    residuals: impl Fn(&[f64], &mut Vec<f64>),
+) -> (Vec<f64>, f64) {
+    let n = p.len();
+    let mut r = Vec::new();
+    let mut r_step = Vec::new();
+    residuals(&p, &mut r);
+    let mut cost: f64 = r.iter().map(|v| v * v).sum();
+    let mut lambda = 1e-3;
+
+    for _ in 0..400 {
+        // Forward-difference Jacobian, column-major
+        let m = r.len();
+        let mut jac = vec![0.0; m * n];
+        for j in 0..n {
+            let h = 1e-7 * (1.0 + p[j].abs());
+            let mut shifted = p.clone();
+            shifted[j] += h;
+            residuals(&shifted, &mut r_step);
+            for i in 0..m {
+                jac[j * m + i] = (r_step[i] - r[i]) / h;
+            }
+        }
+
+        let mut jtj = vec![0.0; n * n];
+        let mut jtr = vec![0.0; n];
+        for a in 0..n {
+            let col_a = &jac[a * m..(a + 1) * m];
+            jtr[a] = col_a.iter().zip(&r).map(|(x, y)| x * y).sum();
+            for b in 0..=a {
+                let col_b = &jac[b * m..(b + 1) * m];
+                let dot: f64 = col_a.iter().zip(col_b).map(|(x, y)| x * y).sum();
+                jtj[a * n + b] = dot;
+                jtj[b * n + a] = dot;
+            }
+        }
+
+        let mut improved = false;
+        while lambda < 1e12 {
+            let mut system = jtj.clone();
+            for d in 0..n {
+                system[d * n + d] += lambda * jtj[d * n + d].max(1e-12);
+            }
+            let rhs: Vec<f64> = jtr.iter().map(|v| -v).collect();
+            let Some(step) = solve(system, rhs) else {
+                lambda *= 4.0;
+                continue;
+            };
+
+            let trial: Vec<f64> = p
+                .iter()
+                .zip(&step)
+                .map(|(v, d)| (v + d).clamp(-PARAM_LIMIT, PARAM_LIMIT))
+                .collect();
+            residuals(&trial, &mut r_step);
+            let trial_cost: f64 = r_step.iter().map(|v| v * v).sum();
+            if trial_cost.is_finite() && trial_cost < cost {
+                let gain = cost - trial_cost;
+                p = trial;
+                std::mem::swap(&mut r, &mut r_step);
+                cost = trial_cost;
+                lambda = (lambda / 3.0).max(1e-12);
+                improved = gain > 1e-15 * cost.max(1e-30);
+                break;
+            }
+            lambda *= 4.0;
+        }
+        if !improved {
+            break;
+        }
+    }
+    (p, cost)
+}
+
+/// Solve a dense n × n system by Gaussian elimination with partial pivoting
+fn solve(mut a: Vec<f64>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot =
+            (col..n).max_by(|&x, &y| a[x * n + col].abs().total_cmp(&a[y * n + col].abs()))?;
+        if a[pivot * n + col].abs() < 1e-300 {
+            return None;
+        }
+        if pivot != col {
+            for k in 0..n {
+                a.swap(pivot * n + k, col * n + k);
+            }
+            b.swap(pivot, col);
+        }
+        for row in col + 1..n {
+            let factor = a[row * n + col] / a[col * n + col];
+            for k in col..n {
+                a[row * n + k] -= factor * a[col * n + k];
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    for col in (0..n).rev() {
+        let tail: f64 = (col + 1..n).map(|k| a[col * n + k] * b[k]).sum();
+        b[col] = (b[col] - tail) / a[col * n + col];
+    }
+    b.iter().all(|v| v.is_finite()).then_some(b)
+}
+
+/// Angular frequency (rad/s) of a free-space wavelength (m)
+fn wavelength_to_omega(wavelength: f64) -> f64 {
+    2.0 * std::f64::consts::PI * SPEED_OF_LIGHT / wavelength
+}
+
+/// Parse a row of numbers separated by commas, semicolons or whitespace
+fn parse_numbers(line: &str) -> Option<Vec<f64>> {
+    let values: Option<Vec<f64>> = line
+        .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.parse().ok())
+        .collect();
+    values.filter(|v| v.len() >= 2)
+}
+
+/// Columns announced by a CSV header such as `wl,n,k` or `wl,k`
+fn header_columns(line: &str) -> Columns {
+    let names: Vec<String> = line
+        .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.trim_matches('"').to_lowercase())
+        .collect();
+    match names.as_slice() {
+        [_, n, k, ..] if n == "n" && k == "k" => Columns::NK,
+        [_, k] if k == "k" => Columns::K,
+        _ => Columns::N,
+    }
+}
+
+/// Linear interpolation in ascending `xs`, clamped at the ends (0 if empty)
+fn interpolate(xs: &[f64], ys: &[f64], x: f64) -> f64 {
+    match xs.iter().position(|&v| v >= x) {
+        None => ys.last().copied().unwrap_or(0.0),
+        Some(0) => ys[0],
+        Some(i) => {
+            let t = (x - xs[i - 1]) / (xs[i] - xs[i - 1]);
+            ys[i - 1] + t * (ys[i] - ys[i - 1])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// BK7 Sellmeier index at a wavelength in µm
+    fn bk7(um: f64) -> f64 {
+        let l2 = um * um;
+        (1.0 + 1.039_612_12 * l2 / (l2 - 0.006_000_698_67)
+            + 0.231_792_344 * l2 / (l2 - 0.020_017_914_4)
+            + 1.010_469_45 * l2 / (l2 - 103.560_653))
+            .sqrt()
+    }
+
+    #[test]
+    fn test_csv_blocks() {
+        let csv = "wl,n\n0.5,1.5\n0.7,1.4\n\nwl,k\n0.5,0.0\n0.7,0.2\n";
+        let data = RefractiveIndexData::from_csv(csv).unwrap();
+        assert_eq!(data.get_sample_count(), 2);
+        assert!((data.get_min_wavelength() - 0.5e-6).abs() < 1e-15);
+        assert!((data.get_k_at(0.6e-6) - 0.1).abs() < 1e-12);
+
+        let csv = "0.5 1.5 0.01\n0.6 1.45 0.02\n";
+        let data = RefractiveIndexData::from_csv(csv).unwrap();
+        assert!((data.get_n_at(0.6e-6) - 1.45).abs() < 1e-12);
+        assert!(RefractiveIndexData::from_csv("wl,k\n0.5,0.1\n").is_err());
+    }
+
+    #[test]
+    fn test_yaml_tabulated() {
+        let yaml = "REFERENCES: test\nDATA:\n  - type: tabulated n\n    data: |\n        0.4 1.53\n        0.8 1.51\n  - type: tabulated k\n    data: |\n        0.4 1e-3\n        0.8 3e-3\nSPECS:\n    temperature: 20\n";
+        let data = RefractiveIndexData::from_yaml(yaml).unwrap();
+        assert_eq!(data.get_sample_count(), 2);
+        assert!((data.get_k_at(0.6e-6) - 2e-3).abs() < 1e-12);
+
+        let formula = "DATA:\n  - type: formula 2\n    coefficients: 0 1.0 0.006\n";
+        assert!(RefractiveIndexData::from_yaml(formula).is_err());
+    }
+
+    #[test]
+    fn test_fit_glass() {
+        let um: Vec<f64> = (0..60).map(|i| 0.4 + 1.2 * i as f64 / 59.0).collect();
+        let data = RefractiveIndexData::from_samples(
+            um.iter().map(|u| u * MICRON).collect(),
+            um.iter().map(|&u| bk7(u)).collect(),
+            Vec::new(),
+        )
+        .unwrap();
+
+        let fit = data.fit(0.4e-6, 1.6e-6, 2, false).unwrap();
+        assert_eq!(fit.get_pole_count(), 2);
+        assert!(fit.get_rms_error() < 1e-3, "{}", fit.get_rms_error());
+        assert!(fit.get_eps_inf() >= 1.0);
+        let n = fit.index_at(1.0e-6);
+        assert!((n[0] - bk7(1.0)).abs() < 2e-3);
+
+        let material = fit.to_material(20e-9);
+        assert_eq!(material.get_pole_count(), 2);
+    }
+
+    #[test]
+    fn test_fit_drude_metal() {
+        // Gold-like Drude response between 0.6 and 1.5 µm
+        let (eps_inf, wp, gamma) = (9.5, 1.36e16, 1.05e14);
+        let um: Vec<f64> = (0..40).map(|i| 0.6 + 0.9 * i as f64 / 39.0).collect();
+        let (mut n, mut k) = (Vec::new(), Vec::new());
+        for &u in &um {
+            let w = wavelength_to_omega(u * MICRON);
+            let eps = Complex::new(eps_inf, 0.0) - wp * wp / Complex::new(w * w, gamma * w);
+            let index = eps.sqrt();
+            n.push(index.re);
+            k.push(index.im);
+        }
+        let data = RefractiveIndexData::from_samples(um.iter().map(|u| u * MICRON).collect(), n, k)
+            .unwrap();
+
+        let fit = data.fit(0.6e-6, 1.5e-6, 0, true).unwrap();
+        assert!(fit.get_rms_error() < 1e-3, "{}", fit.get_rms_error());
+        let pole = fit.get_pole(0).unwrap();
+        assert!((pole.omega as f64 - wp).abs() / wp < 0.01);
+        assert!((fit.get_eps_inf() - eps_inf).abs() < 0.1);
+
+        // Too many parameters for the samples in a narrow band
+        assert!(data.fit(0.6e-6, 0.62e-6, 3, true).is_err());
+    }
+}
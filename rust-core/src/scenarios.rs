@@ -3,10 +3,15 @@
 //! Pre-built experiment configurations that demonstrate various
 //! electromagnetic phenomena like diffraction, waveguiding, and reflection.
 
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
+use crate::csg::CsgOp;
+use crate::raster::rasterize;
+use crate::shapes::Shape;
+
 /// Scenario preset IDs
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ScenarioId {
@@ -26,10 +31,12 @@ pub enum ScenarioId {
     Lens,
     /// Fresnel zone plate lens
     FresnelLens,
+    /// a × 2a offset-row unit cell of the photonic crystal preset (periodic boundaries)
+    PhotonicCrystalCell,
 }
 
 /// Get scenario name by ID
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub fn get_scenario_name(id: u8) -> String {
     match id {
         0 => "Empty Grid".to_string(),
@@ -40,12 +47,13 @@ pub fn get_scenario_name(id: u8) -> String {
         5 => "Photonic Crystal".to_string(),
         6 => "Lens".to_string(),
         7 => "Fresnel Lens".to_string(),
+        8 => "Photonic Crystal Cell".to_string(),
         _ => "Unknown".to_string(),
     }
 }
 
 /// Get scenario description
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub fn get_scenario_description(id: u8) -> String {
     match id {
         0 => "Empty vacuum grid".to_string(),
@@ -56,24 +64,69 @@ pub fn get_scenario_description(id: u8) -> String {
         5 => "Periodic dielectric structure".to_string(),
         6 => "Convex lens focusing".to_string(),
         7 => "Fresnel zone plate focusing".to_string(),
+        8 => "Photonic crystal preset tiled from one periodic cell".to_string(),
         _ => "".to_string(),
     }
 }
 
+/// Painted cell: (x, y, material_id, coverage)
+/// Coverage is 1.0 for whole cells and the covered area fraction for
+/// interface cells of smoothed curved shapes.
+pub type Cell = (usize, usize, u32, f32);
+
 /// Scenario builder - creates geometry for a given preset
 pub struct ScenarioBuilder {
     width: usize,
     height: usize,
+    subpixel: bool,
 }
 
 impl ScenarioBuilder {
     pub fn new(width: usize, height: usize) -> Self {
-        ScenarioBuilder { width, height }
+        ScenarioBuilder {
+            width,
+            height,
+            subpixel: false,
+        }
+    }
+
+    /// Emit fractional coverage for curved shapes instead of binary cells
+    pub fn with_subpixel(self, subpixel: bool) -> Self {
+        ScenarioBuilder { subpixel, ..self }
+    }
+
+    /// Cells of a shape inside an inclusive bounding box, with subpixel
+    /// coverage (interface cells) or binary centre sampling
+    fn shape_cells(
+        &self,
+        bounds: [f32; 4],
+        material_id: u32,
+        inside: impl Fn(f32, f32) -> bool,
+    ) -> Vec<Cell> {
+        rasterize(bounds, self.width, self.height, self.subpixel, inside)
+            .into_iter()
+            .map(|(x, y, coverage)| (x, y, material_id, coverage))
+            .collect()
+    }
+
+    /// Cells of a shape or CSG expression painted with `material_id`
+    /// (CSG nodes with their own material keep it)
+    fn geometry_cells(&self, shape: &Shape, material_id: u32) -> Vec<Cell> {
+        let Some(bounds) = shape.bounds() else {
+            return Vec::new();
+        };
+        let mut cells = Vec::new();
+        for material in shape.materials(material_id) {
+            cells.extend(self.shape_cells(bounds, material, |x, y| {
+                shape.material_at(x, y, material_id) == Some(material)
+            }));
+        }
+        cells
     }
 
     /// Build Double Slit experiment geometry
     /// Returns: Vec of (x, y, material_id) for cells to set
-    pub fn build_double_slit(&self) -> Vec<(usize, usize, u32)> {
+    pub fn build_double_slit(&self) -> Vec<Cell> {
         let mut cells = Vec::new();
         let w = self.width;
         let h = self.height;
@@ -95,7 +148,7 @@ impl ScenarioBuilder {
             if !in_slit1 && !in_slit2 {
                 // Metal wall (3 cells thick for visibility)
                 for dx in 0..3 {
-                    cells.push((wall_x + dx, y, 3)); // 3 = Metal
+                    cells.push((wall_x + dx, y, 3, 1.0)); // 3 = Metal
                 }
             }
         }
@@ -104,7 +157,7 @@ impl ScenarioBuilder {
     }
 
     /// Build Dielectric Waveguide (bent fiber)
-    pub fn build_waveguide(&self) -> Vec<(usize, usize, u32)> {
+    pub fn build_waveguide(&self) -> Vec<Cell> {
         let mut cells = Vec::new();
         let w = self.width;
         let h = self.height;
@@ -118,7 +171,7 @@ impl ScenarioBuilder {
             for dy in 0..core_width {
                 let y = y_center - core_width / 2 + dy;
                 if y < h {
-                    cells.push((x, y, core_material));
+                    cells.push((x, y, core_material, 1.0));
                 }
             }
         }
@@ -138,7 +191,7 @@ impl ScenarioBuilder {
                 let x = (bend_center_x as f32 + r as f32 * rad.sin()) as usize;
                 let y = (bend_center_y as f32 - r as f32 * rad.cos()) as usize;
                 if x < w && y < h {
-                    cells.push((x, y, core_material));
+                    cells.push((x, y, core_material, 1.0));
                 }
             }
         }
@@ -148,7 +201,7 @@ impl ScenarioBuilder {
             for dx in 0..core_width {
                 let x = bend_center_x + bend_radius - core_width / 2 + dx;
                 if x < w && y < h {
-                    cells.push((x, y, core_material));
+                    cells.push((x, y, core_material, 1.0));
                 }
             }
         }
@@ -157,7 +210,7 @@ impl ScenarioBuilder {
     }
 
     /// Build Parabolic Reflector
-    pub fn build_parabolic_reflector(&self) -> Vec<(usize, usize, u32)> {
+    pub fn build_parabolic_reflector(&self) -> Vec<Cell> {
         let mut cells = Vec::new();
         let w = self.width;
         let h = self.height;
@@ -177,7 +230,7 @@ impl ScenarioBuilder {
                 // Metal reflector (3 cells thick)
                 for dx in 0..3 {
                     if xi + dx < w {
-                        cells.push((xi + dx, y, 3)); // Metal
+                        cells.push((xi + dx, y, 3, 1.0)); // Metal
                     }
                 }
             }
@@ -187,7 +240,7 @@ impl ScenarioBuilder {
     }
 
     /// Build Total Internal Reflection prism
-    pub fn build_tir_prism(&self) -> Vec<(usize, usize, u32)> {
+    pub fn build_tir_prism(&self) -> Vec<Cell> {
         let mut cells = Vec::new();
         let w = self.width;
         let h = self.height;
@@ -205,7 +258,7 @@ impl ScenarioBuilder {
             let x_end = prism_left + ((prism_right - prism_left) as f32 * progress) as usize;
 
             for x in prism_left..x_end {
-                cells.push((x, y, 1)); // Glass
+                cells.push((x, y, 1, 1.0)); // Glass
             }
         }
 
@@ -213,50 +266,80 @@ impl ScenarioBuilder {
     }
 
     /// Build Photonic Crystal (periodic holes)
-    pub fn build_photonic_crystal(&self) -> Vec<(usize, usize, u32)> {
-        let mut cells = Vec::new();
+    /// The slab and its air holes form one CSG difference, so the holes do
+    /// not depend on being painted after the slab.
+    pub fn build_photonic_crystal(&self) -> Vec<Cell> {
         let w = self.width;
         let h = self.height;
 
-        // Background: Glass slab
+        // Background: Crystal (ε = 4.0) slab covering x in [100, w-100), y in [h/3, 2h/3)
         let slab_top = h / 3;
         let slab_bottom = h * 2 / 3;
-
-        for y in slab_top..slab_bottom {
-            for x in 100..(w - 100) {
-                cells.push((x, y, 5)); // Crystal (ε = 4.0)
-            }
-        }
-
-        // Periodic holes (vacuum)
+        let (x0, x1) = (100.0, w.saturating_sub(100) as f32);
+        let (y0, y1) = (slab_top as f32, slab_bottom as f32);
+        let slab = Shape::Rect {
+            cx: 0.5 * (x0 + x1) - 0.5,
+            cy: 0.5 * (y0 + y1) - 0.5,
+            width: x1 - x0,
+            height: y1 - y0,
+            angle: 0.0,
+        };
+
+        // Periodic holes (triangular lattice)
         let period = 20;
-        let hole_radius = 6;
-
+        let hole_radius = 6.0;
+        let mut operands = vec![slab];
         for row in 0..10 {
             for col in 0..20 {
                 let cx = 110 + col * period + (row % 2) * (period / 2);
                 let cy = slab_top + 10 + row * period;
-
-                // Cut circular holes
-                for dy in -(hole_radius as i32)..=(hole_radius as i32) {
-                    for dx in -(hole_radius as i32)..=(hole_radius as i32) {
-                        if dx * dx + dy * dy <= hole_radius as i32 * hole_radius as i32 {
-                            let x = (cx as i32 + dx) as usize;
-                            let y = (cy as i32 + dy) as usize;
-                            if x < w && y < h {
-                                cells.push((x, y, 0)); // Vacuum holes
-                            }
-                        }
-                    }
-                }
+                operands.push(Shape::Ellipse {
+                    cx: cx as f32,
+                    cy: cy as f32,
+                    rx: hole_radius,
+                    ry: hole_radius,
+                    angle: 0.0,
+                });
             }
         }
 
-        cells
+        self.geometry_cells(&Shape::csg(CsgOp::Difference, operands), 5)
+    }
+
+    /// Build one periodic cell of the `build_photonic_crystal` lattice
+    ///
+    /// The preset offsets every other row by half a period, so its smallest
+    /// rectangular repeat holds two holes: a period a wide and two rows (2a)
+    /// high. The cell spans the whole grid (a = width, row pitch = height / 2)
+    /// and is meant to be run with periodic or Bloch-periodic boundaries on
+    /// all sides, which tile it into the infinite lattice. Holes keep the
+    /// preset's r/a = 0.3 in ε = 4.0; on a width × 2·width grid the cell is
+    /// the preset's lattice exactly, other aspect ratios stretch the rows.
+    pub fn build_photonic_crystal_cell(&self) -> Vec<Cell> {
+        let (w, h) = (self.width as f32, self.height as f32);
+        let hole_radius = 0.3 * w.min(h / 2.0);
+        // One hole per row, the second shifted by half a period
+        let holes = [
+            (0.25 * w - 0.5, 0.25 * h - 0.5),
+            (0.75 * w - 0.5, 0.75 * h - 0.5),
+        ];
+        // Distance to the nearest periodic image of a hole
+        let wrap = |d: f32, period: f32| {
+            let d = d.rem_euclid(period);
+            d.min(period - d)
+        };
+
+        // Crystal (ε = 4.0) around the vacuum holes
+        let bounds = [0.0, 0.0, w - 1.0, h - 1.0];
+        self.shape_cells(bounds, 5, |x, y| {
+            holes.iter().all(|&(hx, hy)| {
+                wrap(x - hx, w).powi(2) + wrap(y - hy, h).powi(2) > hole_radius * hole_radius
+            })
+        })
     }
 
     /// Build Convex Lens
-    pub fn build_lens(&self) -> Vec<(usize, usize, u32)> {
+    pub fn build_lens(&self) -> Vec<Cell> {
         let mut cells = Vec::new();
         let w = self.width;
         let h = self.height;
@@ -266,6 +349,22 @@ impl ScenarioBuilder {
         let lens_radius = 150; // Curvature radius
         let lens_thickness = 30;
 
+        if self.subpixel {
+            let (r, half) = (lens_radius as f32, lens_thickness as f32 / 2.0);
+            let (y_min, y_max) = ((h / 4) as f32 - 0.5, (h * 3 / 4) as f32 - 0.5);
+            let bounds = [
+                lens_x as f32 - half - r,
+                y_min,
+                lens_x as f32 + half + r,
+                y_max,
+            ];
+            return self.shape_cells(bounds, 1, |x, y| {
+                let dy = y - h as f32 / 2.0;
+                let sag = r - (r * r - dy * dy).max(0.0).sqrt();
+                y >= y_min && y < y_max && (x - lens_x as f32).abs() <= half + sag
+            });
+        }
+
         for y in (h / 4)..(h * 3 / 4) {
             let dy = (y as f32) - (h as f32 / 2.0);
 
@@ -278,7 +377,7 @@ impl ScenarioBuilder {
 
             for x in (left_edge as usize)..(right_edge as usize) {
                 if x < w {
-                    cells.push((x, y, 1)); // Glass
+                    cells.push((x, y, 1, 1.0)); // Glass
                 }
             }
         }
@@ -288,7 +387,7 @@ impl ScenarioBuilder {
 
     /// Build Fresnel Zone Plate Lens
     /// Uses concentric dielectric rings to focus waves
-    pub fn build_fresnel_lens(&self) -> Vec<(usize, usize, u32)> {
+    pub fn build_fresnel_lens(&self) -> Vec<Cell> {
         let mut cells = Vec::new();
         let w = self.width;
         let h = self.height;
@@ -303,6 +402,17 @@ impl ScenarioBuilder {
         let focal_length: f32 = 200.0;
         let lambda: f32 = 20.0; // Wavelength matches typical source frequency
 
+        if self.subpixel {
+            let x_min = center_x as f32 - 0.5;
+            let x_max = x_min + plate_thickness as f32;
+            let bounds = [x_min, 0.0, x_max, h as f32 - 1.0];
+            return self.shape_cells(bounds, 1, |x, y| {
+                let r = (y - center_y as f32).abs();
+                let n = (r * r / (focal_length * lambda)).floor() as i32;
+                n % 2 == 0 && n < 20 && r < h as f32 / 3.0 && x >= x_min && x < x_max
+            });
+        }
+
         for y in 0..h {
             let dy = (y as f32) - (center_y as f32);
             let r = dy.abs();
@@ -318,7 +428,7 @@ impl ScenarioBuilder {
                     for dx in 0..plate_thickness {
                         let x = center_x + dx;
                         if x < w {
-                            cells.push((x, y, 1)); // Glass (ε ≈ 2.25)
+                            cells.push((x, y, 1, 1.0)); // Glass (ε ≈ 2.25)
                         }
                     }
                 }
@@ -344,5 +454,53 @@ mod tests {
     fn test_scenario_names() {
         assert_eq!(get_scenario_name(1), "Double Slit");
         assert_eq!(get_scenario_name(2), "Waveguide");
+        assert_eq!(get_scenario_name(8), "Photonic Crystal Cell");
+    }
+
+    #[test]
+    fn test_photonic_crystal_holes() {
+        let builder = ScenarioBuilder::new(512, 512);
+        let cells = builder.build_photonic_crystal();
+        // Only slab cells are emitted: the holes are cut out, not painted over
+        assert!(cells.iter().all(|c| c.2 == 5));
+        let has = |x, y| cells.iter().any(|c| c.0 == x && c.1 == y);
+        assert!(has(100, 170) && has(411, 330));
+        assert!(!has(99, 200) && !has(412, 200) && !has(200, 341));
+        // First hole centre (110, 180) and a cell at its rim
+        assert!(!has(110, 180) && !has(116, 180));
+        assert!(has(117, 180));
+    }
+
+    #[test]
+    fn test_photonic_crystal_cell() {
+        // Supercell of the preset lattice: period 20, two offset rows of pitch 20
+        let builder = ScenarioBuilder::new(20, 40);
+        let cells = builder.build_photonic_crystal_cell();
+        // Two holes of r = 0.3a in a 2a² cell leave 1 - 0.09π ≈ 72% dielectric
+        let fill = cells.len() as f32 / 800.0;
+        assert!((fill - 0.72).abs() < 0.05);
+        let has = |x, y| cells.iter().any(|c| c.0 == x && c.1 == y);
+        // Hole centres half a period apart on alternate rows, as in the preset
+        assert!(!has(5, 10) && !has(15, 30));
+        assert!(has(15, 10) && has(5, 30) && has(0, 0));
+        // Holes wrap around the left/right edges into the neighbouring cell
+        assert!(!has(19, 10) && !has(0, 30));
+    }
+
+    #[test]
+    fn test_subpixel_scenarios() {
+        let builder = ScenarioBuilder::new(20, 40).with_subpixel(true);
+        let cells = builder.build_photonic_crystal_cell();
+        // Fractional coverage matches the exact fill factor much more closely
+        let fill: f32 = cells.iter().map(|c| c.3).sum::<f32>() / 800.0;
+        let exact = 1.0 - 0.09 * std::f32::consts::PI;
+        assert!((fill - exact).abs() < 0.005, "{}", fill);
+        assert!(cells.iter().any(|c| c.3 < 1.0));
+
+        let builder = ScenarioBuilder::new(512, 512).with_subpixel(true);
+        for cells in [builder.build_lens(), builder.build_fresnel_lens()] {
+            assert!(cells.iter().any(|c| c.3 < 1.0));
+            assert!(cells.iter().all(|c| c.3 > 0.0 && c.3 <= 1.0));
+        }
     }
 }
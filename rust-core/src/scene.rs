@@ -0,0 +1,218 @@
+//! Retained Geometry Scene
+//!
+//! Shapes painted through the scene stay editable: every object keeps its
+//! geometry, material and z-order, and the grid re-rasterizes the affected
+//! area whenever an object is added, transformed, hidden or removed.
+//! Objects are drawn in ascending z-order (ties broken by creation order)
+//! on top of the cells painted directly with the brush tools.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::shapes::Shape;
+
+/// One retained shape of a grid's scene
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneObject {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub id: u32,
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub shape: Shape,
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub material_id: u32,
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub z_order: i32,
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub visible: bool,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl SceneObject {
+    /// Get the shape ID
+    pub fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    /// Get the shape type ("rect", "ellipse", "polygon", "stroke", "csg")
+    pub fn get_kind(&self) -> String {
+        self.shape.kind().to_string()
+    }
+
+    /// Get the defining parameters (layout depends on `get_kind`)
+    pub fn get_parameters(&self) -> Vec<f32> {
+        self.shape.parameters()
+    }
+
+    /// Get the bounding box [x_min, y_min, x_max, y_max] (empty if degenerate)
+    pub fn get_bounds(&self) -> Vec<f32> {
+        self.shape.bounds().map_or(Vec::new(), |b| b.to_vec())
+    }
+
+    /// Get the material ID
+    pub fn get_material_id(&self) -> u32 {
+        self.material_id
+    }
+
+    /// Get the z-order (higher is drawn later, on top)
+    pub fn get_z_order(&self) -> i32 {
+        self.z_order
+    }
+
+    /// Check if the shape is drawn
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+}
+
+/// Ordered collection of retained shapes
+#[derive(Clone, Debug, Default)]
+pub struct Scene {
+    objects: Vec<SceneObject>,
+    next_id: u32,
+}
+
+impl Scene {
+    /// Create an empty scene
+    pub fn new() -> Scene {
+        Scene::default()
+    }
+
+    /// Number of objects (visible or not)
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Check if the scene has no objects
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Add a visible shape on top of the existing ones and return its ID
+    pub fn add(&mut self, shape: Shape, material_id: u32) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        let z_order = self
+            .objects
+            .iter()
+            .map(|o| o.z_order)
+            .max()
+            .map_or(0, |z| z + 1);
+        self.objects.push(SceneObject {
+            id,
+            shape,
+            material_id,
+            z_order,
+            visible: true,
+        });
+        id
+    }
+
+    /// Get an object by ID
+    pub fn get(&self, id: u32) -> Option<&SceneObject> {
+        self.objects.iter().find(|o| o.id == id)
+    }
+
+    /// Get a mutable object by ID
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut SceneObject> {
+        self.objects.iter_mut().find(|o| o.id == id)
+    }
+
+    /// Remove an object and return it
+    pub fn remove(&mut self, id: u32) -> Option<SceneObject> {
+        let index = self.objects.iter().position(|o| o.id == id)?;
+        Some(self.objects.remove(index))
+    }
+
+    /// Put an object back (e.g. on undo) in creation order, replacing any
+    /// object with the same ID
+    pub fn insert(&mut self, object: SceneObject) {
+        self.next_id = self.next_id.max(object.id + 1);
+        match self.objects.iter().position(|o| o.id >= object.id) {
+            Some(i) if self.objects[i].id == object.id => self.objects[i] = object,
+            Some(i) => self.objects.insert(i, object),
+            None => self.objects.push(object),
+        }
+    }
+
+    /// Remove every object (IDs are not reused)
+    pub fn clear(&mut self) {
+        self.objects.clear();
+    }
+
+    /// All objects in creation order
+    pub fn iter(&self) -> impl Iterator<Item = &SceneObject> {
+        self.objects.iter()
+    }
+
+    /// Flag the materials of every object in `used` (indexed by material ID)
+    pub fn mark_materials(&self, used: &mut [bool]) {
+        for object in &self.objects {
+            for id in object.shape.materials(object.material_id) {
+                if let Some(flag) = used.get_mut(id as usize) {
+                    *flag = true;
+                }
+            }
+        }
+    }
+
+    /// All objects in drawing order
+    pub fn ordered(&self) -> Vec<&SceneObject> {
+        let mut objects: Vec<&SceneObject> = self.objects.iter().collect();
+        // Stable sort keeps creation order among equal z
+        objects.sort_by_key(|o| o.z_order);
+        objects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: f32) -> Shape {
+        Shape::Rect {
+            cx: x,
+            cy: 0.0,
+            width: 2.0,
+            height: 2.0,
+            angle: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_scene_ordering() {
+        let mut scene = Scene::new();
+        let a = scene.add(square(0.0), 1);
+        let b = scene.add(square(1.0), 2);
+        let c = scene.add(square(2.0), 3);
+        assert_eq!(scene.get(b).unwrap().z_order, 1);
+
+        scene.get_mut(c).unwrap().z_order = -1;
+        scene.get_mut(b).unwrap().z_order = 0;
+        let order: Vec<u32> = scene.ordered().iter().map(|o| o.id).collect();
+        assert_eq!(order, vec![c, a, b]);
+
+        assert_eq!(scene.remove(a).map(|o| o.material_id), Some(1));
+        assert!(scene.remove(a).is_none());
+        assert_eq!(scene.len(), 2);
+
+        // IDs stay unique after removals
+        scene.clear();
+        assert!(scene.is_empty());
+        assert_eq!(scene.add(square(0.0), 1), 3);
+
+        // Re-inserted objects keep their ID and creation order
+        let restored = SceneObject {
+            id: 1,
+            shape: square(5.0),
+            material_id: 4,
+            z_order: 0,
+            visible: true,
+        };
+        scene.insert(restored.clone());
+        scene.insert(restored);
+        let ids: Vec<u32> = scene.iter().map(|o| o.id).collect();
+        assert_eq!(ids, vec![1, 3]);
+        assert_eq!(scene.add(square(0.0), 1), 4);
+    }
+}
@@ -0,0 +1,474 @@
+//! Versioned Scene Files
+//!
+//! A scene file describes a whole simulation setup as JSON, so experiments
+//! can be kept under version control and shared between the web UI and
+//! batch runs:
+//! - grid size, polarization, Courant number and cell size
+//! - boundary condition of every side
+//! - edited presets (by ID) and user materials (appended after the
+//!   `MaterialTable::PRESET_COUNT` presets, in file order)
+//! - retained shapes and brush-painted cells
+//! - sources, probes and the run length
+//!
+//! Lengths and positions are in cells, times in steps and frequencies in
+//! cycles per step; `units.cell_size` maps them to SI units. Omitted
+//! sections take their defaults, so a minimal file only needs `version`
+//! and `grid`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::boundary::{BoundarySide, BoundarySpec, BoundaryType};
+use crate::cpml::CPMLParams;
+use crate::fdtd::Polarization;
+use crate::materials::Material;
+use crate::shapes::Shape;
+use crate::sources::SourceFunction;
+use crate::units::DEFAULT_CELL_SIZE;
+
+/// Scene file version written by this build (older versions are read too)
+pub const SCENE_VERSION: u32 = 1;
+
+/// Complete simulation setup
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneFile {
+    pub version: u32,
+    pub grid: GridSpec,
+    #[serde(default)]
+    pub units: UnitsSpec,
+    #[serde(default)]
+    pub boundaries: BoundariesSpec,
+    /// Presets whose properties differ from the built-in ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub presets: Vec<PresetSpec>,
+    /// User materials, added after the presets in this order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub materials: Vec<MaterialSpec>,
+    /// Retained shapes in creation order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shapes: Vec<ShapeSpec>,
+    /// Brush-painted cells as row-major runs [first index, length, material]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub painted: Vec<[u32; 3]>,
+    /// Interface cells of the painted layer as [index, other material, fraction]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interfaces: Vec<(u32, u32, f32)>,
+    #[serde(flatten)]
+    pub setup: SceneSetup,
+}
+
+/// Grid size and update settings
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GridSpec {
+    pub width: usize,
+    pub height: usize,
+    #[serde(default = "default_polarization")]
+    pub polarization: Polarization,
+    /// Courant number (default 0.5)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub courant: Option<f32>,
+    /// Smooth curved shapes (see `set_subpixel_smoothing`)
+    #[serde(default)]
+    pub subpixel: bool,
+}
+
+/// Physical scale of the grid
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UnitsSpec {
+    /// Cell size in metres
+    pub cell_size: f64,
+}
+
+impl Default for UnitsSpec {
+    fn default() -> Self {
+        UnitsSpec {
+            cell_size: DEFAULT_CELL_SIZE,
+        }
+    }
+}
+
+/// Boundary condition of every side (Mur where omitted)
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BoundariesSpec {
+    #[serde(default)]
+    pub left: SideSpec,
+    #[serde(default)]
+    pub right: SideSpec,
+    #[serde(default)]
+    pub bottom: SideSpec,
+    #[serde(default)]
+    pub top: SideSpec,
+    /// Bloch phases [kx·a, ky·a] of periodic axes (radians)
+    #[serde(default)]
+    pub bloch_phase: [f32; 2],
+}
+
+/// Boundary of one side, with optional CPML layer parameters
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SideSpec {
+    #[serde(rename = "type")]
+    pub boundary: BoundaryType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpml: Option<CPMLParams>,
+}
+
+impl Default for SideSpec {
+    fn default() -> Self {
+        SideSpec {
+            boundary: BoundaryType::Mur,
+            cpml: None,
+        }
+    }
+}
+
+impl BoundariesSpec {
+    /// Description of a grid's boundaries
+    pub fn from_spec(spec: &BoundarySpec) -> BoundariesSpec {
+        let side = |side| {
+            let boundary = spec.get_side(side);
+            SideSpec {
+                boundary,
+                cpml: (boundary == BoundaryType::CPML).then(|| spec.get_cpml(side)),
+            }
+        };
+        BoundariesSpec {
+            left: side(BoundarySide::Left),
+            right: side(BoundarySide::Right),
+            bottom: side(BoundarySide::Bottom),
+            top: side(BoundarySide::Top),
+            bloch_phase: [spec.get_bloch_x(), spec.get_bloch_y()],
+        }
+    }
+
+    /// Boundary spec of the description
+    pub fn to_spec(&self) -> BoundarySpec {
+        let mut spec = BoundarySpec::new();
+        let sides = [
+            (BoundarySide::Left, &self.left),
+            (BoundarySide::Right, &self.right),
+            (BoundarySide::Bottom, &self.bottom),
+            (BoundarySide::Top, &self.top),
+        ];
+        for (side, description) in sides {
+            match description.cpml {
+                Some(params) if description.boundary == BoundaryType::CPML => {
+                    spec.set_cpml(side, params)
+                }
+                _ => spec.set_side(side, description.boundary),
+            }
+        }
+        if spec.is_periodic_x() && self.bloch_phase[0] != 0.0 {
+            spec.set_bloch_x(self.bloch_phase[0]);
+        }
+        if spec.is_periodic_y() && self.bloch_phase[1] != 0.0 {
+            spec.set_bloch_y(self.bloch_phase[1]);
+        }
+        spec
+    }
+}
+
+/// Named user material
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MaterialSpec {
+    pub name: String,
+    #[serde(flatten)]
+    pub material: Material,
+}
+
+/// Replacement properties of a preset entry
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PresetSpec {
+    pub id: u32,
+    #[serde(flatten)]
+    pub material: Material,
+}
+
+/// Retained shape painted with a table material
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShapeSpec {
+    pub shape: Shape,
+    pub material: u32,
+    /// Drawing order (default: on top of the shapes before it)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub z_order: Option<i32>,
+    #[serde(default = "default_true")]
+    pub visible: bool,
+}
+
+/// Excitation, measurement and run length of a scene
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SceneSetup {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<SourceSpec>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub probes: Vec<ProbeSpec>,
+    #[serde(default)]
+    pub run: RunSpec,
+}
+
+/// Field source, injected before every step
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SourceSpec {
+    /// Point source, added to the field (or replacing it if `hard`)
+    Point {
+        x: usize,
+        y: usize,
+        waveform: WaveformSpec,
+        #[serde(default)]
+        hard: bool,
+    },
+    /// Plane wave launched from the column x = position (axis "x") or the
+    /// row y = position (axis "y")
+    Line {
+        axis: Axis,
+        position: usize,
+        waveform: WaveformSpec,
+    },
+    /// Beam launched from column x with a Gaussian profile of 1/e² half
+    /// width `waist` around row y
+    GaussianBeam {
+        x: usize,
+        y: usize,
+        waist: f32,
+        waveform: WaveformSpec,
+    },
+}
+
+/// Grid axis
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Axis {
+    X,
+    Y,
+}
+
+/// Time dependence of a source (see `SourceFunction`)
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WaveformSpec {
+    Sinusoidal {
+        frequency: f32,
+        #[serde(default = "default_amplitude")]
+        amplitude: f32,
+    },
+    Gaussian {
+        t0: f32,
+        tau: f32,
+        #[serde(default = "default_amplitude")]
+        amplitude: f32,
+    },
+    ModulatedGaussian {
+        frequency: f32,
+        t0: f32,
+        tau: f32,
+        #[serde(default = "default_amplitude")]
+        amplitude: f32,
+    },
+    Ricker {
+        t0: f32,
+        tau: f32,
+        #[serde(default = "default_amplitude")]
+        amplitude: f32,
+    },
+}
+
+impl WaveformSpec {
+    /// Source function of the waveform
+    pub fn function(&self) -> SourceFunction {
+        match *self {
+            WaveformSpec::Sinusoidal {
+                frequency,
+                amplitude,
+            } => SourceFunction::new_sinusoidal(frequency, amplitude),
+            WaveformSpec::Gaussian { t0, tau, amplitude } => {
+                SourceFunction::new_gaussian(t0, tau, amplitude)
+            }
+            WaveformSpec::ModulatedGaussian {
+                frequency,
+                t0,
+                tau,
+                amplitude,
+            } => SourceFunction::new_modulated_gaussian(frequency, t0, tau, amplitude),
+            WaveformSpec::Ricker { t0, tau, amplitude } => {
+                SourceFunction::new_ricker(t0, tau, amplitude)
+            }
+        }
+    }
+}
+
+/// Point where the out-of-plane field is recorded
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProbeSpec {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    pub x: usize,
+    pub y: usize,
+}
+
+/// Run length of a batch simulation
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RunSpec {
+    /// Maximum number of steps
+    #[serde(default = "default_steps")]
+    pub steps: u64,
+    /// Stop early once the field energy falls below this fraction of its peak
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decay: Option<f32>,
+    /// Steps between convergence checks
+    #[serde(default = "default_check_interval")]
+    pub check_interval: u64,
+    /// Frequencies (cycles per step) of the DFT field maps to record
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dft_frequencies: Vec<f32>,
+}
+
+impl Default for RunSpec {
+    fn default() -> Self {
+        RunSpec {
+            steps: default_steps(),
+            decay: None,
+            check_interval: default_check_interval(),
+            dft_frequencies: Vec::new(),
+        }
+    }
+}
+
+impl SceneFile {
+    /// Parse a scene from JSON (versions newer than `SCENE_VERSION` are rejected)
+    pub fn from_json(json: &str) -> Result<SceneFile, String> {
+        let scene: SceneFile =
+            serde_json::from_str(json).map_err(|e| format!("invalid scene file: {}", e))?;
+        if scene.version == 0 || scene.version > SCENE_VERSION {
+            return Err(format!(
+                "unsupported scene version {} (this build reads up to {})",
+                scene.version, SCENE_VERSION
+            ));
+        }
+        Ok(scene)
+    }
+
+    /// Pretty-printed JSON of the scene
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("scene files always serialize")
+    }
+}
+
+fn default_polarization() -> Polarization {
+    Polarization::TMz
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_amplitude() -> f32 {
+    1.0
+}
+
+fn default_steps() -> u64 {
+    1000
+}
+
+fn default_check_interval() -> u64 {
+    100
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::FillRule;
+
+    #[test]
+    fn test_minimal_scene_defaults() {
+        let scene =
+            SceneFile::from_json(r#"{"version": 1, "grid": {"width": 80, "height": 60}}"#).unwrap();
+        assert_eq!(scene.grid.polarization, Polarization::TMz);
+        assert_eq!(scene.units.cell_size, DEFAULT_CELL_SIZE);
+        assert_eq!(scene.boundaries.to_spec(), BoundarySpec::new());
+        assert_eq!(scene.setup.run, RunSpec::default());
+
+        // Defaults are written back out only where they carry information
+        let json = scene.to_json();
+        assert!(!json.contains("sources") && !json.contains("courant"));
+        assert_eq!(SceneFile::from_json(&json), Ok(scene));
+
+        assert!(
+            SceneFile::from_json(r#"{"version": 2, "grid": {"width": 8, "height": 8}}"#)
+                .unwrap_err()
+                .contains("version 2")
+        );
+        assert!(SceneFile::from_json(r#"{"version": 1}"#).is_err());
+    }
+
+    #[test]
+    fn test_scene_sections() {
+        let json = r#"{
+            "version": 1,
+            "grid": {"width": 100, "height": 50, "polarization": "TEz", "courant": 0.4},
+            "units": {"cell_size": 1e-8},
+            "boundaries": {
+                "left": {"type": "cpml", "cpml": {"thickness": 12}},
+                "right": {"type": "cpml"},
+                "top": {"type": "periodic"},
+                "bloch_phase": [0, 0.5]
+            },
+            "materials": [
+                {"name": "Gold", "epsilon_r": 9.5,
+                 "poles": [{"type": "drude", "omega": 0.3, "gamma": 0.002}]}
+            ],
+            "shapes": [
+                {"shape": {"type": "rect", "cx": 50, "cy": 25, "width": 10, "height": 4},
+                 "material": 7},
+                {"shape": {"type": "polygon", "points": [[0, 0], [4, 0], [0, 4]], "rule": "evenodd"},
+                 "material": 1, "visible": false}
+            ],
+            "sources": [
+                {"type": "point", "x": 10, "y": 25,
+                 "waveform": {"type": "modulated_gaussian", "frequency": 0.05, "t0": 60, "tau": 20}},
+                {"type": "line", "axis": "y", "position": 5,
+                 "waveform": {"type": "sinusoidal", "frequency": 0.1, "amplitude": 2}}
+            ],
+            "probes": [{"name": "transmitted", "x": 90, "y": 25}],
+            "run": {"steps": 5000, "decay": 1e-4, "dft_frequencies": [0.05]}
+        }"#;
+        let scene = SceneFile::from_json(json).unwrap();
+        assert_eq!(scene.grid.polarization, Polarization::TEz);
+
+        let spec = scene.boundaries.to_spec();
+        assert_eq!(spec.get_cpml(BoundarySide::Left).thickness, 12);
+        assert_eq!(
+            spec.get_cpml(BoundarySide::Right),
+            CPMLParams::default(),
+            "omitted layer parameters take the defaults"
+        );
+        assert!(spec.is_periodic_y());
+        assert_eq!(spec.get_bloch_y(), 0.5);
+        assert_eq!(BoundariesSpec::from_spec(&spec).to_spec(), spec);
+
+        let gold = &scene.materials[0].material;
+        assert_eq!((gold.mu_r, gold.sigma), (1.0, 0.0));
+        assert_eq!(gold.get_pole_count(), 1);
+
+        assert_eq!(scene.shapes[0].shape.kind(), "rect");
+        assert!(scene.shapes[0].visible);
+        assert!(matches!(
+            scene.shapes[1].shape,
+            Shape::Polygon {
+                rule: FillRule::EvenOdd,
+                ..
+            }
+        ));
+        assert!(!scene.shapes[1].visible);
+
+        let SourceSpec::Line { waveform, .. } = &scene.setup.sources[1] else {
+            panic!("expected a line source");
+        };
+        assert_eq!(waveform.function().get_amplitude(), 2.0);
+        assert_eq!(scene.setup.probes[0].name, "transmitted");
+        assert_eq!(scene.setup.run.decay, Some(1e-4));
+        assert_eq!(scene.setup.run.check_interval, 100);
+        assert_eq!(scene.setup.run.dft_frequencies, vec![0.05]);
+
+        assert_eq!(SceneFile::from_json(&scene.to_json()), Ok(scene));
+    }
+}
@@ -0,0 +1,473 @@
+//! Geometric Primitives for the Painting Tools
+//!
+//! Shapes are described in grid coordinates (cell centres at integers) and
+//! answer two questions for the rasterizer: an inclusive bounding box and a
+//! point-in-shape test. Angles are in radians, measured from +x towards +y.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::csg::CsgNode;
+
+/// Fill rule for self-intersecting polygons
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FillRule {
+    /// Inside when a ray crosses the outline an odd number of times
+    EvenOdd = 0,
+    /// Inside when the outline winds around the point at least once
+    NonZero = 1,
+}
+
+/// A paintable region of the grid
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Shape {
+    /// Closed polygon through the vertices
+    Polygon {
+        points: Vec<[f32; 2]>,
+        #[serde(default = "default_rule")]
+        rule: FillRule,
+    },
+    /// Rectangle of size width × height centred at (cx, cy), rotated by `angle`
+    Rect {
+        cx: f32,
+        cy: f32,
+        width: f32,
+        height: f32,
+        #[serde(default)]
+        angle: f32,
+    },
+    /// Ellipse with semi-axes rx, ry centred at (cx, cy), rotated by `angle`
+    Ellipse {
+        cx: f32,
+        cy: f32,
+        rx: f32,
+        ry: f32,
+        #[serde(default)]
+        angle: f32,
+    },
+    /// Open polyline stroked with round caps and joins
+    Stroke { points: Vec<[f32; 2]>, width: f32 },
+    /// Boolean combination of shapes (see `csg`)
+    Csg(Box<CsgNode>),
+}
+
+impl Shape {
+    /// Polygon from a flat [x0, y0, x1, y1, ...] vertex list
+    /// A trailing odd coordinate is ignored.
+    pub fn polygon(coords: &[f32], rule: FillRule) -> Shape {
+        Shape::Polygon {
+            points: pairs(coords),
+            rule,
+        }
+    }
+
+    /// Stroke along a chain of Bezier segments of the given degree
+    /// (2 = quadratic, 3 = cubic; 1 strokes the polyline itself)
+    ///
+    /// `coords` is a flat [x0, y0, x1, y1, ...] list of control points;
+    /// consecutive segments share their end points, so a path of n segments
+    /// has n·degree + 1 points. Incomplete trailing segments are ignored.
+    pub fn bezier_path(coords: &[f32], degree: usize, width: f32) -> Shape {
+        let control = pairs(coords);
+        let degree = degree.clamp(1, 3);
+        let mut points: Vec<[f32; 2]> = control.first().copied().into_iter().collect();
+        for segment in control.windows(degree + 1).step_by(degree) {
+            let length: f32 = segment.windows(2).map(|p| distance(p[0], p[1])).sum();
+            let samples = flatten(length, |t| de_casteljau(segment, t));
+            points.extend_from_slice(&samples[1..]);
+        }
+        Shape::Stroke { points, width }
+    }
+
+    /// Short name of the shape type
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Shape::Polygon { .. } => "polygon",
+            Shape::Rect { .. } => "rect",
+            Shape::Ellipse { .. } => "ellipse",
+            Shape::Stroke { .. } => "stroke",
+            Shape::Csg(_) => "csg",
+        }
+    }
+
+    /// Defining parameters as a flat list
+    /// - rect: [cx, cy, width, height, angle]
+    /// - ellipse: [cx, cy, rx, ry, angle]
+    /// - polygon: [x0, y0, x1, y1, ...]
+    /// - stroke: [width, x0, y0, x1, y1, ...]
+    /// - csg: [operation, operand count]
+    pub fn parameters(&self) -> Vec<f32> {
+        match self {
+            Shape::Polygon { points, .. } => points.concat(),
+            Shape::Rect {
+                cx,
+                cy,
+                width,
+                height,
+                angle,
+            } => vec![*cx, *cy, *width, *height, *angle],
+            Shape::Ellipse {
+                cx,
+                cy,
+                rx,
+                ry,
+                angle,
+            } => vec![*cx, *cy, *rx, *ry, *angle],
+            Shape::Stroke { points, width } => {
+                let mut params = vec![*width];
+                params.extend(points.iter().flatten());
+                params
+            }
+            Shape::Csg(node) => vec![node.op as u8 as f32, node.operands.len() as f32],
+        }
+    }
+
+    /// Apply a similarity transform: scale by `scale` and rotate by `angle`
+    /// about `pivot`, then translate by (dx, dy)
+    /// Non-positive or non-finite scales are ignored.
+    pub fn transform(&mut self, dx: f32, dy: f32, angle: f32, scale: f32, pivot: [f32; 2]) {
+        let scale = if scale.is_finite() && scale > 0.0 {
+            scale
+        } else {
+            1.0
+        };
+        let (sin, cos) = angle.sin_cos();
+        let map = |p: [f32; 2]| -> [f32; 2] {
+            let (u, v) = ((p[0] - pivot[0]) * scale, (p[1] - pivot[1]) * scale);
+            [
+                pivot[0] + u * cos - v * sin + dx,
+                pivot[1] + u * sin + v * cos + dy,
+            ]
+        };
+        match self {
+            Shape::Polygon { points, .. } => points.iter_mut().for_each(|p| *p = map(*p)),
+            Shape::Stroke { points, width } => {
+                points.iter_mut().for_each(|p| *p = map(*p));
+                *width *= scale;
+            }
+            Shape::Rect {
+                cx,
+                cy,
+                width: a,
+                height: b,
+                angle: rotation,
+            }
+            | Shape::Ellipse {
+                cx,
+                cy,
+                rx: a,
+                ry: b,
+                angle: rotation,
+            } => {
+                [*cx, *cy] = map([*cx, *cy]);
+                *a *= scale;
+                *b *= scale;
+                *rotation += angle;
+            }
+            Shape::Csg(node) => node
+                .operands
+                .iter_mut()
+                .for_each(|s| s.transform(dx, dy, angle, scale, pivot)),
+        }
+    }
+
+    /// Centre of the bounding box (pivot for rotations and scaling)
+    pub fn center(&self) -> Option<[f32; 2]> {
+        self.bounds()
+            .map(|[x0, y0, x1, y1]| [0.5 * (x0 + x1), 0.5 * (y0 + y1)])
+    }
+
+    /// Inclusive bounding box [x_min, y_min, x_max, y_max]
+    /// Degenerate shapes (no area) return None.
+    pub fn bounds(&self) -> Option<[f32; 4]> {
+        match self {
+            Shape::Polygon { points, .. } => {
+                if points.len() < 3 {
+                    return None;
+                }
+                Some(point_bounds(points, 0.0))
+            }
+            Shape::Rect {
+                cx,
+                cy,
+                width,
+                height,
+                angle,
+            } => {
+                if !(*width > 0.0 && *height > 0.0) {
+                    return None;
+                }
+                let (sin, cos) = angle.sin_cos();
+                let hx = 0.5 * (width * cos.abs() + height * sin.abs());
+                let hy = 0.5 * (width * sin.abs() + height * cos.abs());
+                Some([cx - hx, cy - hy, cx + hx, cy + hy])
+            }
+            Shape::Ellipse {
+                cx,
+                cy,
+                rx,
+                ry,
+                angle,
+            } => {
+                if !(*rx > 0.0 && *ry > 0.0) {
+                    return None;
+                }
+                let (sin, cos) = angle.sin_cos();
+                let hx = ((rx * cos).powi(2) + (ry * sin).powi(2)).sqrt();
+                let hy = ((rx * sin).powi(2) + (ry * cos).powi(2)).sqrt();
+                Some([cx - hx, cy - hy, cx + hx, cy + hy])
+            }
+            Shape::Stroke { points, width } => {
+                if points.is_empty() || width.is_nan() || *width <= 0.0 {
+                    return None;
+                }
+                Some(point_bounds(points, 0.5 * width))
+            }
+            Shape::Csg(node) => node.bounds(),
+        }
+    }
+
+    /// Check if a point lies inside the shape
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        match self {
+            Shape::Polygon { points, rule } => {
+                let winding = winding_number(points, x, y);
+                match rule {
+                    FillRule::EvenOdd => winding % 2 != 0,
+                    FillRule::NonZero => winding != 0,
+                }
+            }
+            Shape::Rect {
+                cx,
+                cy,
+                width,
+                height,
+                angle,
+            } => {
+                let [u, v] = to_local(x - cx, y - cy, *angle);
+                u.abs() <= 0.5 * width && v.abs() <= 0.5 * height
+            }
+            Shape::Ellipse {
+                cx,
+                cy,
+                rx,
+                ry,
+                angle,
+            } => {
+                let [u, v] = to_local(x - cx, y - cy, *angle);
+                (u / rx).powi(2) + (v / ry).powi(2) <= 1.0
+            }
+            Shape::Stroke { points, width } => {
+                let r2 = 0.25 * width * width;
+                match points.len() {
+                    0 => false,
+                    1 => distance_squared([x, y], points[0]) <= r2,
+                    _ => points
+                        .windows(2)
+                        .any(|s| segment_distance_squared([x, y], s[0], s[1]) <= r2),
+                }
+            }
+            Shape::Csg(node) => node.material_at(x, y, 0).is_some(),
+        }
+    }
+}
+
+/// Fill rule of polygons read without one
+fn default_rule() -> FillRule {
+    FillRule::NonZero
+}
+
+/// Group a flat coordinate list into points
+pub(crate) fn pairs(coords: &[f32]) -> Vec<[f32; 2]> {
+    coords.chunks_exact(2).map(|c| [c[0], c[1]]).collect()
+}
+
+/// Rotate a vector by -angle (world to shape-local axes)
+fn to_local(dx: f32, dy: f32, angle: f32) -> [f32; 2] {
+    let (sin, cos) = angle.sin_cos();
+    [dx * cos + dy * sin, -dx * sin + dy * cos]
+}
+
+fn distance_squared(a: [f32; 2], b: [f32; 2]) -> f32 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    distance_squared(a, b).sqrt()
+}
+
+/// Squared distance from p to the segment ab
+fn segment_distance_squared(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let (ab, ap) = ([b[0] - a[0], b[1] - a[1]], [p[0] - a[0], p[1] - a[1]]);
+    let length2 = ab[0] * ab[0] + ab[1] * ab[1];
+    let t = if length2 > 0.0 {
+        ((ap[0] * ab[0] + ap[1] * ab[1]) / length2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    distance_squared(p, [a[0] + t * ab[0], a[1] + t * ab[1]])
+}
+
+fn point_bounds(points: &[[f32; 2]], margin: f32) -> [f32; 4] {
+    let mut bounds = [
+        f32::INFINITY,
+        f32::INFINITY,
+        f32::NEG_INFINITY,
+        f32::NEG_INFINITY,
+    ];
+    for p in points {
+        bounds[0] = bounds[0].min(p[0]);
+        bounds[1] = bounds[1].min(p[1]);
+        bounds[2] = bounds[2].max(p[0]);
+        bounds[3] = bounds[3].max(p[1]);
+    }
+    [
+        bounds[0] - margin,
+        bounds[1] - margin,
+        bounds[2] + margin,
+        bounds[3] + margin,
+    ]
+}
+
+/// Signed number of times a closed polygon winds around (x, y)
+fn winding_number(points: &[[f32; 2]], x: f32, y: f32) -> i32 {
+    let mut winding = 0;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        // Side of (x, y) relative to the edge a → b
+        let side = (b[0] - a[0]) * (y - a[1]) - (x - a[0]) * (b[1] - a[1]);
+        if a[1] <= y {
+            if b[1] > y && side > 0.0 {
+                winding += 1;
+            }
+        } else if b[1] <= y && side < 0.0 {
+            winding -= 1;
+        }
+    }
+    winding
+}
+
+/// Point at parameter t of the Bezier curve with the given control points
+pub(crate) fn de_casteljau(control: &[[f32; 2]], t: f32) -> [f32; 2] {
+    let mut points = control.to_vec();
+    for n in (1..points.len()).rev() {
+        for i in 0..n {
+            let (a, b) = (points[i], points[i + 1]);
+            points[i] = [a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])];
+        }
+    }
+    points[0]
+}
+
+/// Sample a curve t ∈ [0, 1] into segments about one cell long
+pub(crate) fn flatten(length: f32, curve: impl Fn(f32) -> [f32; 2]) -> Vec<[f32; 2]> {
+    let segments = if length.is_finite() {
+        (length.ceil() as usize).clamp(1, 1024)
+    } else {
+        1
+    };
+    (0..=segments)
+        .map(|i| curve(i as f32 / segments as f32))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polygon_fill_rules() {
+        // Pentagram: the centre is wound twice
+        let star: Vec<f32> = (0..5)
+            .flat_map(|i| {
+                let a = i as f32 * 4.0 * std::f32::consts::PI / 5.0;
+                [10.0 * a.cos(), 10.0 * a.sin()]
+            })
+            .collect();
+        let even_odd = Shape::polygon(&star, FillRule::EvenOdd);
+        let non_zero = Shape::polygon(&star, FillRule::NonZero);
+        assert!(!even_odd.contains(0.0, 0.0));
+        assert!(non_zero.contains(0.0, 0.0));
+        // A tip is covered once under both rules
+        assert!(even_odd.contains(8.0, 0.0) && non_zero.contains(8.0, 0.0));
+        assert!(!non_zero.contains(11.0, 0.0));
+        assert_eq!(
+            Shape::polygon(&[0.0, 0.0, 1.0, 1.0], FillRule::EvenOdd).bounds(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rotated_shapes() {
+        let angle = std::f32::consts::FRAC_PI_4;
+        let rect = Shape::Rect {
+            cx: 0.0,
+            cy: 0.0,
+            width: 10.0,
+            height: 2.0,
+            angle,
+        };
+        // The long axis now runs along the diagonal
+        assert!(rect.contains(3.0, 3.0));
+        assert!(!rect.contains(3.0, -3.0));
+        let [x0, _, x1, _] = rect.bounds().unwrap();
+        assert!((x1 - x0 - 12.0 * angle.cos()).abs() < 1e-4);
+
+        let ellipse = Shape::Ellipse {
+            cx: 5.0,
+            cy: 5.0,
+            rx: 8.0,
+            ry: 2.0,
+            angle: std::f32::consts::FRAC_PI_2,
+        };
+        assert!(ellipse.contains(5.0, 12.0));
+        assert!(!ellipse.contains(12.0, 5.0));
+    }
+
+    #[test]
+    fn test_transform() {
+        let mut rect = Shape::Rect {
+            cx: 4.0,
+            cy: 2.0,
+            width: 4.0,
+            height: 2.0,
+            angle: 0.0,
+        };
+        let half_turn = std::f32::consts::PI;
+        rect.transform(1.0, 0.0, half_turn, 2.0, [0.0, 0.0]);
+        let p = rect.parameters();
+        assert!((p[0] + 7.0).abs() < 1e-5 && (p[1] + 4.0).abs() < 1e-5);
+        assert_eq!((p[2], p[3], p[4]), (8.0, 4.0, half_turn));
+
+        let mut triangle = Shape::polygon(&[0.0, 0.0, 2.0, 0.0, 0.0, 2.0], FillRule::NonZero);
+        triangle.transform(3.0, 3.0, 0.0, 1.0, [0.0, 0.0]);
+        assert_eq!(triangle.parameters(), vec![3.0, 3.0, 5.0, 3.0, 3.0, 5.0]);
+        assert_eq!(triangle.center(), Some([4.0, 4.0]));
+        assert_eq!(triangle.kind(), "polygon");
+    }
+
+    #[test]
+    fn test_bezier_strokes() {
+        let curve = Shape::bezier_path(&[0.0, 0.0, 10.0, 20.0, 20.0, 0.0], 2, 2.0);
+        // Midpoint of the curve is (10, 10)
+        assert!(curve.contains(10.0, 10.0));
+        assert!(curve.contains(10.0, 10.9));
+        assert!(!curve.contains(10.0, 12.0));
+        assert!(!curve.contains(10.0, 20.0));
+
+        // Two cubic segments: an arch and its mirror image below the axis
+        let coords = [
+            0.0, 0.0, 0.0, 10.0, 10.0, 10.0, 10.0, 0.0, 10.0, -10.0, 20.0, -10.0, 20.0, 0.0,
+        ];
+        let s = Shape::bezier_path(&coords, 3, 1.0);
+        assert!(s.contains(5.0, 7.5));
+        assert!(s.contains(15.0, -7.5));
+        assert!(s.contains(10.0, 0.0));
+        assert!(!s.contains(5.0, 0.0));
+    }
+}
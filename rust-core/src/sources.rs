@@ -4,13 +4,19 @@
 //! - Point source (impulse or continuous)
 //! - Plane wave (uniform injection along a line)
 //! - Gaussian pulse (time-domain wavepacket)
-
+//!
+//! Times are in steps and frequencies in cycles per step; the `_hz` /
+//! `_seconds` variants convert SI values through a `UnitSystem`.
+//!
 //! Author: Mehmet Gümüş (github.com/SpaceEngineerSS)
 
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
+use crate::units::UnitSystem;
+
 /// Source type enumeration
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum SourceType {
@@ -29,7 +35,7 @@ pub enum SourceType {
 }
 
 /// Time-domain source function
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[derive(Clone)]
 pub struct SourceFunction {
     /// Source waveform type
@@ -45,7 +51,7 @@ pub struct SourceFunction {
 }
 
 /// Waveform types
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Waveform {
@@ -61,10 +67,10 @@ pub enum Waveform {
     Step,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl SourceFunction {
     /// Create a continuous sinusoidal source
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new_sinusoidal(frequency: f32, amplitude: f32) -> SourceFunction {
         SourceFunction {
             waveform: Waveform::Sinusoidal,
@@ -115,6 +121,59 @@ impl SourceFunction {
         }
     }
 
+    /// Create a continuous sinusoidal source with the frequency in Hz
+    pub fn new_sinusoidal_hz(
+        frequency_hz: f64,
+        amplitude: f32,
+        units: &UnitSystem,
+    ) -> SourceFunction {
+        SourceFunction::new_sinusoidal(units.hz_to_frequency(frequency_hz), amplitude)
+    }
+
+    /// Create a Gaussian pulse source with center time and width in seconds
+    pub fn new_gaussian_seconds(
+        t0: f64,
+        tau: f64,
+        amplitude: f32,
+        units: &UnitSystem,
+    ) -> SourceFunction {
+        SourceFunction::new_gaussian(
+            units.seconds_to_steps(t0),
+            units.seconds_to_steps(tau),
+            amplitude,
+        )
+    }
+
+    /// Create a modulated Gaussian with the carrier in Hz and times in seconds
+    pub fn new_modulated_gaussian_hz(
+        frequency_hz: f64,
+        t0: f64,
+        tau: f64,
+        amplitude: f32,
+        units: &UnitSystem,
+    ) -> SourceFunction {
+        SourceFunction::new_modulated_gaussian(
+            units.hz_to_frequency(frequency_hz),
+            units.seconds_to_steps(t0),
+            units.seconds_to_steps(tau),
+            amplitude,
+        )
+    }
+
+    /// Create a Ricker wavelet with center time and width in seconds
+    pub fn new_ricker_seconds(
+        t0: f64,
+        tau: f64,
+        amplitude: f32,
+        units: &UnitSystem,
+    ) -> SourceFunction {
+        SourceFunction::new_ricker(
+            units.seconds_to_steps(t0),
+            units.seconds_to_steps(tau),
+            amplitude,
+        )
+    }
+
     /// Evaluate source function at time t
     pub fn evaluate(&self, t: f32) -> f32 {
         match self.waveform {
@@ -146,6 +205,16 @@ impl SourceFunction {
         }
     }
 
+    /// Evaluate source function at a time given in seconds
+    pub fn evaluate_seconds(&self, t: f64, units: &UnitSystem) -> f32 {
+        self.evaluate(units.seconds_to_steps(t))
+    }
+
+    /// Get carrier frequency in Hz (0 for baseband pulses)
+    pub fn get_frequency_hz(&self, units: &UnitSystem) -> f64 {
+        units.frequency_to_hz(self.frequency)
+    }
+
     /// Get amplitude
     pub fn get_amplitude(&self) -> f32 {
         self.amplitude
@@ -158,7 +227,7 @@ impl SourceFunction {
 }
 
 /// Plane wave source configuration
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct PlaneWaveSource {
     /// Position of the source line
     position: usize,
@@ -170,10 +239,10 @@ pub struct PlaneWaveSource {
     courant: f32,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl PlaneWaveSource {
     /// Create a new vertical plane wave source at x = position
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new_vertical(position: usize, frequency: f32, courant: f32) -> PlaneWaveSource {
         PlaneWaveSource {
             position,
@@ -239,7 +308,7 @@ impl PlaneWaveSource {
 // ============================================================================
 
 /// Single element in a phased array
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[derive(Clone, Copy)]
 pub struct SourceElement {
     pub x: usize,
@@ -248,9 +317,9 @@ pub struct SourceElement {
     pub amplitude: f32,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl SourceElement {
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new(x: usize, y: usize, phase_offset: f32, amplitude: f32) -> SourceElement {
         SourceElement {
             x,
@@ -263,17 +332,17 @@ impl SourceElement {
 
 /// Phased Array Source for beamforming applications
 /// E(t) = Σ A_n * sin(ωt + φ_n) where φ_n is the phase offset for element n
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct PhasedArraySource {
     elements: Vec<SourceElement>,
     frequency: f32,
     courant: f32,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl PhasedArraySource {
     /// Create a linear phased array along y-axis at position x
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new_linear(
         x: usize,
         y_start: usize,
@@ -338,7 +407,7 @@ impl PhasedArraySource {
 
 /// Gaussian Beam Source with spatial intensity profile
 /// I(y) = I_0 * exp(-2(y-y_c)²/w²) where w is beam waist
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct GaussianBeamSource {
     x: usize,
     y_center: usize,
@@ -348,9 +417,9 @@ pub struct GaussianBeamSource {
     courant: f32,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl GaussianBeamSource {
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new(
         x: usize,
         y_center: usize,
@@ -410,7 +479,7 @@ impl GaussianBeamSource {
 }
 
 /// Probe for measuring field values at a specific point
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct Probe {
     x: usize,
     y: usize,
@@ -422,10 +491,10 @@ pub struct Probe {
     capacity: usize,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Probe {
     /// Create a new probe at position (x, y)
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new(x: usize, y: usize, buffer_size: usize) -> Probe {
         Probe {
             x,
@@ -459,17 +528,31 @@ impl Probe {
     pub fn record(&mut self, ez: &[f32], width: usize) {
         let idx = self.y * width + self.x;
         if idx < ez.len() {
-            self.buffer[self.write_pos] = ez[idx];
-            self.write_pos = (self.write_pos + 1) % self.capacity;
+            self.record_value(ez[idx]);
         }
     }
 
+    /// Record an externally sampled value (e.g. from a 3D grid)
+    pub fn record_value(&mut self, value: f32) {
+        self.buffer[self.write_pos] = value;
+        self.write_pos = (self.write_pos + 1) % self.capacity;
+    }
+
     /// Get the recorded buffer for visualization
     /// Returns values in chronological order (oldest first)
+    #[cfg(feature = "wasm")]
     pub fn get_buffer_ptr(&self) -> *const f32 {
         self.buffer.as_ptr()
     }
 
+    /// Get recorded values in chronological order (oldest first)
+    pub fn get_samples(&self) -> Vec<f32> {
+        let mut samples = Vec::with_capacity(self.capacity);
+        samples.extend_from_slice(&self.buffer[self.write_pos..]);
+        samples.extend_from_slice(&self.buffer[..self.write_pos]);
+        samples
+    }
+
     /// Get buffer size
     pub fn get_buffer_size(&self) -> usize {
         self.capacity
@@ -485,6 +568,14 @@ impl Probe {
         self.buffer[prev_pos]
     }
 
+    /// Get sample times in seconds for `get_samples` (oldest sample at 0)
+    /// Assumes one sample per time step
+    pub fn get_time_axis(&self, units: &UnitSystem) -> Vec<f64> {
+        (0..self.capacity)
+            .map(|i| units.steps_to_seconds(i as f64))
+            .collect()
+    }
+
     /// Get write position for proper buffer reading
     pub fn get_write_pos(&self) -> usize {
         self.write_pos
@@ -505,7 +596,7 @@ use rustfft::{num_complex::Complex, FftPlanner};
 
 /// Spectrum Analyzer using FFT for frequency domain analysis
 /// Uses Hann windowing to reduce spectral leakage
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct SpectrumAnalyzer {
     /// Input buffer size (must be power of 2 for FFT)
     size: usize,
@@ -517,18 +608,17 @@ pub struct SpectrumAnalyzer {
     scratch: Vec<Complex<f32>>,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl SpectrumAnalyzer {
     /// Create a new spectrum analyzer
     /// size: FFT size (should be power of 2, e.g., 256, 512, 1024)
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new(size: usize) -> SpectrumAnalyzer {
         let size = size.next_power_of_two();
 
         let mut window = vec![0.0; size];
-        for i in 0..size {
-            window[i] =
-                0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / (size - 1) as f32).cos());
+        for (i, w) in window.iter_mut().enumerate() {
+            *w = 0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / (size - 1) as f32).cos());
         }
 
         SpectrumAnalyzer {
@@ -554,12 +644,12 @@ impl SpectrumAnalyzer {
     pub fn compute(&mut self, samples: &[f32]) -> Vec<f32> {
         let n = self.size.min(samples.len());
 
-        for i in 0..self.size {
-            if i < n {
-                self.scratch[i] = Complex::new(samples[i] * self.window[i], 0.0);
+        for (i, bin) in self.scratch.iter_mut().enumerate() {
+            *bin = if i < n {
+                Complex::new(samples[i] * self.window[i], 0.0)
             } else {
-                self.scratch[i] = Complex::new(0.0, 0.0);
-            }
+                Complex::new(0.0, 0.0)
+            };
         }
 
         let mut planner = FftPlanner::new();
@@ -580,6 +670,7 @@ impl SpectrumAnalyzer {
     }
 
     /// Get spectrum pointer for JS access
+    #[cfg(feature = "wasm")]
     pub fn get_spectrum_ptr(&self) -> *const f32 {
         self.spectrum.as_ptr()
     }
@@ -597,21 +688,100 @@ impl SpectrumAnalyzer {
         max_idx
     }
 
+    /// Find all local maxima within `range_db` of the strongest bin
+    /// Bin 0 (DC) is never reported
+    pub fn find_peak_bins(&self, range_db: f32) -> Vec<usize> {
+        let floor = self.spectrum[self.find_peak_bin()] - range_db.abs();
+        let mut peaks = Vec::new();
+
+        for i in 1..self.spectrum.len().saturating_sub(1) {
+            let val = self.spectrum[i];
+            if val >= floor && val > self.spectrum[i - 1] && val >= self.spectrum[i + 1] {
+                peaks.push(i);
+            }
+        }
+        peaks
+    }
+
+    /// Refine a peak bin to a fractional bin index
+    /// Fits a parabola through the dB magnitudes of the bin and its neighbours
+    pub fn interpolate_peak(&self, bin: usize) -> f32 {
+        if bin == 0 || bin + 1 >= self.spectrum.len() {
+            return bin as f32;
+        }
+        let (a, b, c) = (
+            self.spectrum[bin - 1],
+            self.spectrum[bin],
+            self.spectrum[bin + 1],
+        );
+        let denom = a - 2.0 * b + c;
+        if denom.abs() < 1e-12 {
+            return bin as f32;
+        }
+        bin as f32 + (0.5 * (a - c) / denom).clamp(-0.5, 0.5)
+    }
+
     /// Convert bin index to normalized frequency
     pub fn bin_to_frequency(&self, bin: usize) -> f32 {
         bin as f32 / self.size as f32
     }
+
+    /// Convert bin index to frequency in Hz (one sample per time step)
+    pub fn bin_to_hz(&self, bin: usize, units: &UnitSystem) -> f64 {
+        units.frequency_to_hz(self.bin_to_frequency(bin))
+    }
+
+    /// Interpolated peak frequency in Hz around a local maximum bin
+    pub fn interpolate_peak_hz(&self, bin: usize, units: &UnitSystem) -> f64 {
+        units.frequency_to_hz(self.interpolate_peak(bin) / self.size as f32)
+    }
+
+    /// Frequency spacing between bins in Hz
+    pub fn get_resolution_hz(&self, units: &UnitSystem) -> f64 {
+        units.frequency_to_hz(1.0 / self.size as f32)
+    }
+}
+
+impl SpectrumAnalyzer {
+    /// Last computed spectrum (dB, N/2 bins)
+    pub fn spectrum(&self) -> &[f32] {
+        &self.spectrum
+    }
+
+    /// Compute the power-averaged spectrum of several signals (in dB)
+    /// Useful when several probes watch the same resonances
+    pub fn compute_averaged(&mut self, signals: &[Vec<f32>]) -> Vec<f32> {
+        let mut power = vec![0.0f32; self.size / 2];
+        for samples in signals {
+            self.compute(samples);
+            for (p, &db) in power.iter_mut().zip(&self.spectrum) {
+                *p += 10.0f32.powf(db / 10.0);
+            }
+        }
+
+        let count = signals.len().max(1) as f32;
+        for (val, &p) in self.spectrum.iter_mut().zip(&power) {
+            let mean = p / count;
+            *val = if mean > 1e-20 {
+                10.0 * mean.log10()
+            } else {
+                -200.0
+            };
+        }
+
+        self.spectrum.clone()
+    }
 }
 
 /// Helper function to create a Gaussian pulse at specific parameters
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub fn gaussian_pulse(t: f32, t0: f32, tau: f32) -> f32 {
     let arg = (t - t0) / tau;
     (-arg * arg).exp()
 }
 
 /// Helper function for modulated Gaussian
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub fn modulated_gaussian(t: f32, frequency: f32, t0: f32, tau: f32) -> f32 {
     let arg = (t - t0) / tau;
     let envelope = (-arg * arg).exp();
@@ -648,4 +818,56 @@ mod tests {
         probe.record(&ez, 200);
         assert!((probe.get_current_value() - 0.5).abs() < 1e-6);
     }
+
+    #[test]
+    fn test_probe_samples_chronological() {
+        let mut probe = Probe::new(0, 0, 3);
+        for v in [1.0, 2.0, 3.0, 4.0] {
+            probe.record(&[v], 1);
+        }
+        assert_eq!(probe.get_samples(), vec![2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_spectrum_peaks() {
+        let mut analyzer = SpectrumAnalyzer::new(512);
+        let samples: Vec<f32> = (0..512)
+            .map(|t| {
+                let t = t as f32;
+                (2.0 * std::f32::consts::PI * 0.05 * t).sin()
+                    + 0.5 * (2.0 * std::f32::consts::PI * 0.2 * t).sin()
+            })
+            .collect();
+        analyzer.compute(&samples);
+
+        let peaks = analyzer.find_peak_bins(20.0);
+        assert_eq!(peaks.len(), 2);
+        let f0 = analyzer.interpolate_peak(peaks[0]) / analyzer.get_size() as f32;
+        assert!((f0 - 0.05).abs() < 0.002);
+        assert!((analyzer.bin_to_frequency(peaks[1]) - 0.2).abs() < 0.003);
+    }
+
+    #[test]
+    fn test_si_units() {
+        // 1 mm cells at S = 0.5: 30 GHz ≈ 0.05 cycles per step
+        let units = UnitSystem::new(1e-3, 0.5);
+        let src = SourceFunction::new_sinusoidal_hz(30e9, 1.0, &units);
+        assert!((src.get_frequency_hz(&units) - 30e9).abs() / 30e9 < 1e-6);
+
+        let pulse = SourceFunction::new_gaussian_seconds(100e-12, 20e-12, 1.0, &units);
+        assert!((pulse.evaluate_seconds(100e-12, &units) - 1.0).abs() < 1e-5);
+
+        let mut analyzer = SpectrumAnalyzer::new(512);
+        let samples: Vec<f32> = (0..512).map(|t| src.evaluate(t as f32)).collect();
+        analyzer.compute(&samples);
+        let peak = analyzer.find_peak_bin();
+        let hz = analyzer.interpolate_peak_hz(peak, &units);
+        assert!((hz - 30e9).abs() < analyzer.get_resolution_hz(&units));
+        assert!((analyzer.bin_to_hz(1, &units) - analyzer.get_resolution_hz(&units)).abs() < 1.0);
+
+        let probe = Probe::new(0, 0, 4);
+        let times = probe.get_time_axis(&units);
+        assert_eq!(times.len(), 4);
+        assert!((times[3] - 3.0 * units.get_dt()).abs() < 1e-20);
+    }
 }
@@ -0,0 +1,746 @@
+//! SVG Drawing Import
+//!
+//! Reads the filled shapes of an SVG document: `path`, `rect`, `circle`,
+//! `ellipse`, `polygon` and `polyline` elements, with `transform`,
+//! `fill` and `fill-rule` inherited through groups (attributes or `style`).
+//! Each shape is keyed by its fill colour; unfilled shapes (`fill="none"`)
+//! are outlines and are skipped, as is everything inside `defs`, masks,
+//! patterns and elements with `display="none"`.
+//!
+//! Coordinates are SVG user units; viewBox and physical units (mm, in)
+//! are not interpreted, so the import scale converts user units to cells.
+//! Rounded rectangle corners are ignored.
+
+use crate::import::{
+    apply, arc_points, compose, contours_shape, ellipse_shape, rect_shape, Affine, ImportedShape,
+    IDENTITY,
+};
+use crate::shapes::{de_casteljau, flatten, FillRule, Shape};
+
+/// Containers whose content is never drawn directly
+const HIDDEN_CONTAINERS: [&str; 8] = [
+    "defs",
+    "clippath",
+    "mask",
+    "pattern",
+    "marker",
+    "symbol",
+    "lineargradient",
+    "radialgradient",
+];
+
+/// Named colours accepted besides hex and rgb() notation
+const NAMED_COLORS: [(&str, &str); 18] = [
+    ("black", "#000000"),
+    ("white", "#ffffff"),
+    ("red", "#ff0000"),
+    ("lime", "#00ff00"),
+    ("green", "#008000"),
+    ("blue", "#0000ff"),
+    ("yellow", "#ffff00"),
+    ("cyan", "#00ffff"),
+    ("aqua", "#00ffff"),
+    ("magenta", "#ff00ff"),
+    ("fuchsia", "#ff00ff"),
+    ("gray", "#808080"),
+    ("grey", "#808080"),
+    ("silver", "#c0c0c0"),
+    ("maroon", "#800000"),
+    ("navy", "#000080"),
+    ("orange", "#ffa500"),
+    ("purple", "#800080"),
+];
+
+/// Inherited presentation state of an element
+#[derive(Clone, Debug)]
+struct Style {
+    transform: Affine,
+    /// Fill colour key, None for `fill="none"`
+    fill: Option<String>,
+    rule: FillRule,
+    hidden: bool,
+}
+
+/// Closed shapes of an SVG document placed on the grid by `placement`
+pub fn parse_svg(text: &str, placement: &Affine) -> Result<Vec<ImportedShape>, String> {
+    let mut stack = vec![Style {
+        transform: *placement,
+        fill: Some("#000000".to_string()),
+        rule: FillRule::NonZero,
+        hidden: false,
+    }];
+    let mut shapes = Vec::new();
+    let mut found_root = false;
+    let mut rest = text;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        // Comments, processing instructions, DOCTYPE and CDATA
+        let skip_to = |rest: &str, end: &str| {
+            rest.find(end)
+                .map(|i| i + end.len())
+                .ok_or_else(|| "unterminated markup in SVG".to_string())
+        };
+        if rest.starts_with("<!--") {
+            rest = &rest[skip_to(rest, "-->")?..];
+            continue;
+        }
+        if rest.starts_with("<![CDATA[") {
+            rest = &rest[skip_to(rest, "]]>")?..];
+            continue;
+        }
+        if rest.starts_with("<?") || rest.starts_with("<!") {
+            rest = &rest[skip_to(rest, ">")?..];
+            continue;
+        }
+        if rest.starts_with("</") {
+            rest = &rest[skip_to(rest, ">")?..];
+            if stack.len() > 1 {
+                stack.pop();
+            }
+            continue;
+        }
+
+        let (tag, used) = parse_tag(rest)?;
+        rest = &rest[used..];
+        let name = tag.name.to_lowercase();
+        let name = name.rsplit(':').next().unwrap_or_default();
+        found_root |= name == "svg";
+
+        // The root style is never popped
+        let style = element_style(&tag, &stack[stack.len() - 1], name)?;
+        if !style.hidden {
+            if let Some(fill) = &style.fill {
+                if let Some(shape) = element_shape(&tag, name, &style)? {
+                    shapes.push(ImportedShape {
+                        layer: fill.clone(),
+                        shape,
+                    });
+                }
+            }
+        }
+        if !tag.self_closing {
+            stack.push(style);
+        }
+    }
+
+    if !found_root {
+        return Err("not an SVG document (no <svg> element)".to_string());
+    }
+    Ok(shapes)
+}
+
+/// Normalize a colour to "#rrggbb" (hex, rgb() or a basic colour name)
+pub fn parse_color(value: &str) -> Option<String> {
+    let value = value.trim().to_lowercase();
+    if let Some(hex) = value.strip_prefix('#') {
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        return match hex.len() {
+            3 => Some(hex.chars().flat_map(|c| [c, c]).collect()),
+            6 => Some(hex.to_string()),
+            _ => None,
+        }
+        .map(|hex: String| format!("#{}", hex));
+    }
+    if let Some(args) = value.strip_prefix("rgb(").and_then(|v| v.strip_suffix(')')) {
+        let channels: Vec<u8> = args
+            .split(',')
+            .map(|c| {
+                let c = c.trim();
+                match c.strip_suffix('%') {
+                    Some(p) => p.trim().parse::<f32>().ok().map(|p| p * 2.55),
+                    None => c.parse::<f32>().ok(),
+                }
+                .map(|v| v.round().clamp(0.0, 255.0) as u8)
+            })
+            .collect::<Option<_>>()?;
+        return match channels[..] {
+            [r, g, b] => Some(format!("#{:02x}{:02x}{:02x}", r, g, b)),
+            _ => None,
+        };
+    }
+    NAMED_COLORS
+        .iter()
+        .find(|(name, _)| *name == value)
+        .map(|(_, hex)| hex.to_string())
+}
+
+/// Start tag with its attributes
+struct Tag<'a> {
+    name: &'a str,
+    attributes: Vec<(&'a str, &'a str)>,
+    self_closing: bool,
+}
+
+impl Tag<'_> {
+    fn get(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|&(_, v)| v)
+    }
+
+    /// Numeric attribute (units such as "px" are ignored), 0 if missing
+    fn number(&self, name: &str) -> Result<f64, String> {
+        match self.get(name) {
+            Some(value) => {
+                let mut reader = NumberReader::new(value);
+                reader
+                    .number()
+                    .ok_or_else(|| format!("invalid {} attribute '{}'", name, value))
+            }
+            None => Ok(0.0),
+        }
+    }
+}
+
+/// Parse a start tag at the beginning of `text`; returns it and its length
+fn parse_tag(text: &str) -> Result<(Tag<'_>, usize), String> {
+    let bytes = text.as_bytes();
+    let is_name = |b: u8| !(b.is_ascii_whitespace() || b == b'/' || b == b'>' || b == b'=');
+    let mut i = 1;
+    while i < bytes.len() && is_name(bytes[i]) {
+        i += 1;
+    }
+    let name = &text[1..i];
+    let mut attributes = Vec::new();
+
+    loop {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        match bytes.get(i) {
+            None => return Err(format!("unterminated <{}> tag", name)),
+            Some(b'>') => {
+                let tag = Tag {
+                    name,
+                    attributes,
+                    self_closing: false,
+                };
+                return Ok((tag, i + 1));
+            }
+            Some(b'/') => {
+                if bytes.get(i + 1) != Some(&b'>') {
+                    return Err(format!("malformed <{}> tag", name));
+                }
+                let tag = Tag {
+                    name,
+                    attributes,
+                    self_closing: true,
+                };
+                return Ok((tag, i + 2));
+            }
+            Some(_) => {}
+        }
+
+        let start = i;
+        while i < bytes.len() && is_name(bytes[i]) {
+            i += 1;
+        }
+        let attribute = &text[start..i];
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if bytes.get(i) != Some(&b'=') {
+            return Err(format!(
+                "attribute '{}' of <{}> has no value",
+                attribute, name
+            ));
+        }
+        i += 1;
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let quote = match bytes.get(i) {
+            Some(&q) if q == b'"' || q == b'\'' => q,
+            _ => return Err(format!("unquoted attribute '{}' of <{}>", attribute, name)),
+        };
+        let end = text[i + 1..]
+            .find(quote as char)
+            .ok_or_else(|| format!("unterminated attribute '{}' of <{}>", attribute, name))?;
+        attributes.push((attribute, &text[i + 1..i + 1 + end]));
+        i += end + 2;
+    }
+}
+
+/// Presentation state of an element from its parent's
+fn element_style(tag: &Tag, parent: &Style, name: &str) -> Result<Style, String> {
+    let mut style = parent.clone();
+    style.hidden |= HIDDEN_CONTAINERS.contains(&name);
+
+    // Attributes first, then the style property (which takes precedence)
+    let mut properties: Vec<(&str, &str)> = tag.attributes.clone();
+    if let Some(css) = tag.get("style") {
+        properties.extend(css.split(';').filter_map(|declaration| {
+            let (key, value) = declaration.split_once(':')?;
+            Some((key.trim(), value.trim()))
+        }));
+    }
+    for (key, value) in properties {
+        match key {
+            "fill" => {
+                style.fill = match value.trim() {
+                    "none" | "transparent" => None,
+                    "inherit" => parent.fill.clone(),
+                    other => Some(parse_color(other).unwrap_or_else(|| other.to_lowercase())),
+                };
+            }
+            "fill-rule" => {
+                style.rule = match value.trim() {
+                    "evenodd" => FillRule::EvenOdd,
+                    _ => FillRule::NonZero,
+                };
+            }
+            "display" if value.trim() == "none" => style.hidden = true,
+            "visibility" if value.trim() == "hidden" => style.hidden = true,
+            _ => {}
+        }
+    }
+    if let Some(transform) = tag.get("transform") {
+        style.transform = compose(&parent.transform, &parse_transform(transform)?);
+    }
+    Ok(style)
+}
+
+/// Parse a transform list such as "translate(10 20) rotate(45)"
+fn parse_transform(text: &str) -> Result<Affine, String> {
+    let mut m = IDENTITY;
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let open = rest
+            .find('(')
+            .ok_or_else(|| format!("invalid transform '{}'", text))?;
+        let close = rest[open..]
+            .find(')')
+            .map(|c| open + c)
+            .ok_or_else(|| format!("invalid transform '{}'", text))?;
+        let name = rest[..open].trim_matches(|c: char| c.is_whitespace() || c == ',');
+        let mut reader = NumberReader::new(&rest[open + 1..close]);
+        let args: Vec<f64> = std::iter::from_fn(|| reader.number()).collect();
+        let arg = |i: usize| args.get(i).copied().unwrap_or(0.0);
+        let radians = arg(0).to_radians();
+        let step = match (name, args.len()) {
+            ("matrix", 6) => [arg(0), arg(1), arg(2), arg(3), arg(4), arg(5)],
+            ("translate", 1 | 2) => [1.0, 0.0, 0.0, 1.0, arg(0), arg(1)],
+            ("scale", 1) => [arg(0), 0.0, 0.0, arg(0), 0.0, 0.0],
+            ("scale", 2) => [arg(0), 0.0, 0.0, arg(1), 0.0, 0.0],
+            ("rotate", 1 | 3) => {
+                let (sin, cos) = radians.sin_cos();
+                let rotation = [cos, sin, -sin, cos, 0.0, 0.0];
+                let (cx, cy) = (arg(1), arg(2));
+                let to = [1.0, 0.0, 0.0, 1.0, cx, cy];
+                let from = [1.0, 0.0, 0.0, 1.0, -cx, -cy];
+                compose(&to, &compose(&rotation, &from))
+            }
+            ("skewX", 1) => [1.0, 0.0, radians.tan(), 1.0, 0.0, 0.0],
+            ("skewY", 1) => [1.0, radians.tan(), 0.0, 1.0, 0.0, 0.0],
+            _ => return Err(format!("invalid transform '{}'", &rest[..=close])),
+        };
+        m = compose(&m, &step);
+        rest = rest[close + 1..].trim_start();
+    }
+    Ok(m)
+}
+
+/// Grid shape of a drawable element (None for other elements and empty shapes)
+fn element_shape(tag: &Tag, name: &str, style: &Style) -> Result<Option<Shape>, String> {
+    let m = &style.transform;
+    let shape = match name {
+        "rect" => {
+            let (width, height) = (tag.number("width")?, tag.number("height")?);
+            if width <= 0.0 || height <= 0.0 {
+                return Ok(None);
+            }
+            rect_shape(m, tag.number("x")?, tag.number("y")?, width, height)
+        }
+        "circle" | "ellipse" => {
+            let center = [tag.number("cx")?, tag.number("cy")?];
+            let (rx, ry) = if name == "circle" {
+                (tag.number("r")?, tag.number("r")?)
+            } else {
+                (tag.number("rx")?, tag.number("ry")?)
+            };
+            if rx <= 0.0 || ry <= 0.0 {
+                return Ok(None);
+            }
+            ellipse_shape(m, center, rx, ry, 0.0)
+        }
+        "polygon" | "polyline" => {
+            let mut reader = NumberReader::new(tag.get("points").unwrap_or_default());
+            let coords: Vec<f64> = std::iter::from_fn(|| reader.number()).collect();
+            let points = coords.chunks_exact(2).map(|p| apply(m, [p[0], p[1]]));
+            match contours_shape(&[points.collect()], style.rule) {
+                Some(shape) => shape,
+                None => return Ok(None),
+            }
+        }
+        "path" => {
+            let contours = path_contours(tag.get("d").unwrap_or_default(), m)?;
+            match contours_shape(&contours, style.rule) {
+                Some(shape) => shape,
+                None => return Ok(None),
+            }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(shape))
+}
+
+/// Reader for the number lists of path data, points and transforms
+struct NumberReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> NumberReader<'a> {
+    fn new(text: &'a str) -> NumberReader<'a> {
+        NumberReader {
+            bytes: text.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|&b| b.is_ascii_whitespace() || b == b',')
+        {
+            self.pos += 1;
+        }
+    }
+
+    /// Next byte after separators, not consumed
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_separators();
+        self.bytes.get(self.pos).copied()
+    }
+
+    /// Next number ("-1.5e3", ".5", "1.5.5" reads as 1.5 then .5)
+    fn number(&mut self) -> Option<f64> {
+        self.skip_separators();
+        let start = self.pos;
+        let digits = |reader: &mut Self| {
+            let from = reader.pos;
+            while reader.bytes.get(reader.pos).is_some_and(u8::is_ascii_digit) {
+                reader.pos += 1;
+            }
+            reader.pos > from
+        };
+        if matches!(self.bytes.get(self.pos), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        let mut any = digits(self);
+        if self.bytes.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            any |= digits(self);
+        }
+        if !any {
+            self.pos = start;
+            return None;
+        }
+        if matches!(self.bytes.get(self.pos), Some(b'e' | b'E')) {
+            let mark = self.pos;
+            self.pos += 1;
+            if matches!(self.bytes.get(self.pos), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if !digits(self) {
+                self.pos = mark;
+            }
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    /// Arc flag, which may be written without a separator ("a5 5 0 115 5")
+    fn flag(&mut self) -> Option<bool> {
+        match self.peek()? {
+            b'0' => {
+                self.pos += 1;
+                Some(false)
+            }
+            b'1' => {
+                self.pos += 1;
+                Some(true)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Closed contours of path data in grid coordinates (open subpaths are
+/// closed implicitly, as SVG fills them)
+fn path_contours(d: &str, m: &Affine) -> Result<Vec<Vec<[f32; 2]>>, String> {
+    let invalid = || {
+        format!(
+            "invalid path data near '{}'",
+            d.chars().take(40).collect::<String>()
+        )
+    };
+    let mut reader = NumberReader::new(d);
+    let mut contours: Vec<Vec<[f32; 2]>> = Vec::new();
+    let mut contour: Vec<[f32; 2]> = Vec::new();
+    let mut command: Option<u8> = None;
+    let (mut current, mut start) = ([0.0f64; 2], [0.0f64; 2]);
+    // Second control point of the previous cubic / control of the previous quadratic
+    let mut last_cubic: Option<[f64; 2]> = None;
+    let mut last_quad: Option<[f64; 2]> = None;
+
+    while let Some(next) = reader.peek() {
+        if next.is_ascii_alphabetic() {
+            reader.pos += 1;
+            command = Some(next);
+        }
+        let c = command.ok_or_else(invalid)?;
+        let relative = c.is_ascii_lowercase();
+        let origin = if relative { current } else { [0.0, 0.0] };
+        let point = |reader: &mut NumberReader| -> Result<[f64; 2], String> {
+            let x = reader.number().ok_or_else(invalid)?;
+            let y = reader.number().ok_or_else(invalid)?;
+            Ok([origin[0] + x, origin[1] + y])
+        };
+        if contour.is_empty() && !matches!(c, b'M' | b'm' | b'Z' | b'z') {
+            contour.push(apply(m, current));
+        }
+
+        let (mut cubic, mut quad) = (None, None);
+        let upper = c.to_ascii_uppercase();
+        match upper {
+            b'M' => {
+                let p = point(&mut reader)?;
+                if contour.len() > 1 {
+                    contours.push(std::mem::take(&mut contour));
+                }
+                contour.clear();
+                contour.push(apply(m, p));
+                current = p;
+                start = p;
+                // Further coordinate pairs are implicit line-tos
+                command = Some(if relative { b'l' } else { b'L' });
+            }
+            b'L' => {
+                current = point(&mut reader)?;
+                contour.push(apply(m, current));
+            }
+            b'H' | b'V' => {
+                let value = reader.number().ok_or_else(invalid)?;
+                let axis = usize::from(upper == b'V');
+                current[axis] = value + if relative { current[axis] } else { 0.0 };
+                contour.push(apply(m, current));
+            }
+            b'C' | b'S' => {
+                let c1 = if upper == b'C' {
+                    point(&mut reader)?
+                } else {
+                    last_cubic.map_or(current, |p| {
+                        [2.0 * current[0] - p[0], 2.0 * current[1] - p[1]]
+                    })
+                };
+                let c2 = point(&mut reader)?;
+                let end = point(&mut reader)?;
+                push_bezier(&mut contour, m, &[current, c1, c2, end]);
+                current = end;
+                cubic = Some(c2);
+            }
+            b'Q' | b'T' => {
+                let control = if upper == b'Q' {
+                    point(&mut reader)?
+                } else {
+                    last_quad.map_or(current, |p| {
+                        [2.0 * current[0] - p[0], 2.0 * current[1] - p[1]]
+                    })
+                };
+                let end = point(&mut reader)?;
+                push_bezier(&mut contour, m, &[current, control, end]);
+                current = end;
+                quad = Some(control);
+            }
+            b'A' => {
+                let rx = reader.number().ok_or_else(invalid)?;
+                let ry = reader.number().ok_or_else(invalid)?;
+                let rotation = reader.number().ok_or_else(invalid)?.to_radians();
+                let large = reader.flag().ok_or_else(invalid)?;
+                let sweep = reader.flag().ok_or_else(invalid)?;
+                let end = point(&mut reader)?;
+                match arc_center(current, end, [rx, ry], rotation, large, sweep) {
+                    Some((center, radii, theta, delta)) => {
+                        let points = arc_points(m, center, radii, rotation, theta, delta);
+                        contour.extend_from_slice(&points[1..]);
+                    }
+                    None => contour.push(apply(m, end)),
+                }
+                current = end;
+            }
+            b'Z' => {
+                if contour.len() > 1 {
+                    contours.push(std::mem::take(&mut contour));
+                }
+                contour.clear();
+                current = start;
+                command = None;
+            }
+            _ => return Err(invalid()),
+        }
+        last_cubic = cubic;
+        last_quad = quad;
+    }
+    if contour.len() > 1 {
+        contours.push(contour);
+    }
+    Ok(contours)
+}
+
+/// Append a flattened Bezier segment (the start point is already in place)
+fn push_bezier(contour: &mut Vec<[f32; 2]>, m: &Affine, control: &[[f64; 2]]) {
+    let control: Vec<[f32; 2]> = control.iter().map(|&p| apply(m, p)).collect();
+    let length: f32 = control
+        .windows(2)
+        .map(|p| ((p[1][0] - p[0][0]).powi(2) + (p[1][1] - p[0][1]).powi(2)).sqrt())
+        .sum();
+    let samples = flatten(length, |t| de_casteljau(&control, t));
+    contour.extend_from_slice(&samples[1..]);
+}
+
+/// Centre parametrization of an SVG endpoint arc: centre, radii (enlarged
+/// if too small to reach the end point), start angle and sweep
+/// None when the arc degenerates to a straight line.
+fn arc_center(
+    from: [f64; 2],
+    to: [f64; 2],
+    radii: [f64; 2],
+    rotation: f64,
+    large: bool,
+    sweep: bool,
+) -> Option<([f64; 2], [f64; 2], f64, f64)> {
+    let (mut rx, mut ry) = (radii[0].abs(), radii[1].abs());
+    if rx == 0.0 || ry == 0.0 || from == to {
+        return None;
+    }
+    let (sin, cos) = rotation.sin_cos();
+    // Half-chord in the ellipse's axes
+    let (hx, hy) = (0.5 * (from[0] - to[0]), 0.5 * (from[1] - to[1]));
+    let (x1, y1) = (cos * hx + sin * hy, -sin * hx + cos * hy);
+
+    let lambda = (x1 / rx).powi(2) + (y1 / ry).powi(2);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+    let numerator = (rx * ry).powi(2) - (rx * y1).powi(2) - (ry * x1).powi(2);
+    let denominator = (rx * y1).powi(2) + (ry * x1).powi(2);
+    let mut k = (numerator / denominator).max(0.0).sqrt();
+    if large == sweep {
+        k = -k;
+    }
+    let (cx1, cy1) = (k * rx * y1 / ry, -k * ry * x1 / rx);
+    let center = [
+        cos * cx1 - sin * cy1 + 0.5 * (from[0] + to[0]),
+        sin * cx1 + cos * cy1 + 0.5 * (from[1] + to[1]),
+    ];
+
+    let angle = |ux: f64, uy: f64| uy.atan2(ux);
+    let theta = angle((x1 - cx1) / rx, (y1 - cy1) / ry);
+    let end = angle((-x1 - cx1) / rx, (-y1 - cy1) / ry);
+    let mut delta = end - theta;
+    if sweep && delta < 0.0 {
+        delta += std::f64::consts::TAU;
+    } else if !sweep && delta > 0.0 {
+        delta -= std::f64::consts::TAU;
+    }
+    Some((center, [rx, ry], theta, delta))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shapes(svg: &str) -> Vec<ImportedShape> {
+        parse_svg(svg, &IDENTITY).unwrap()
+    }
+
+    #[test]
+    fn test_svg_elements() {
+        let svg = r##"<?xml version="1.0"?>
+            <!-- A lens and a mirror -->
+            <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 100 100">
+              <defs><rect id="unused" width="5" height="5"/></defs>
+              <g fill="#00f" transform="translate(10, 0)">
+                <circle cx="20" cy="20" r="5"/>
+                <rect x="0" y="40" width="10" height="4" style="fill: red"/>
+                <rect x="0" y="60" width="10" height="4" fill="none" stroke="black"/>
+              </g>
+              <polygon points="50,50 60,50 55,58"/>
+              <ellipse cx="80" cy="20" rx="8" ry="3" transform="skewX(30)"/>
+            </svg>"##;
+        let shapes = shapes(svg);
+        let layers: Vec<&str> = shapes.iter().map(|s| s.layer.as_str()).collect();
+        assert_eq!(layers, vec!["#0000ff", "#ff0000", "#000000", "#000000"]);
+        assert!(shapes[0].shape.contains(30.0, 20.0));
+        assert!(shapes[1].shape.contains(15.0, 42.0));
+        assert!(shapes[2].shape.contains(55.0, 52.0));
+        // Skewed ellipse is flattened into a polygon
+        assert_eq!(shapes[3].shape.kind(), "polygon");
+        let shift = 20.0 * 30f32.to_radians().tan();
+        assert!(shapes[3].shape.contains(80.0 + shift, 20.0));
+
+        assert!(parse_svg("<html></html>", &IDENTITY).is_err());
+        assert!(parse_svg("<svg><rect width=5></svg>", &IDENTITY).is_err());
+    }
+
+    #[test]
+    fn test_svg_paths() {
+        // Ring with a hole (even-odd) drawn with relative commands
+        let svg = r#"<svg><path fill-rule="evenodd"
+            d="M0 0h20v20H0z m5 5 l10 0 0 10 -10 0z"/></svg>"#;
+        let ring = &shapes(svg)[0].shape;
+        assert!(ring.contains(2.0, 10.0));
+        assert!(!ring.contains(10.0, 10.0));
+
+        // Two semicircular arcs (packed flags) form a disc of radius 10
+        let svg = r#"<svg><path d="M0,10a10,10 0 1120,0A10 10 0 1 1 0 10Z"/></svg>"#;
+        let disc = &shapes(svg)[0].shape;
+        assert!(disc.contains(10.0, 10.0));
+        assert!(disc.contains(10.0, 19.5) && disc.contains(10.0, 0.5));
+        assert!(!disc.contains(10.0, 21.0));
+
+        // Cubic with a smooth continuation, quadratic with T
+        let svg = r#"<svg><path d="M0 0 C 0 10 10 10 10 0 S 20 -10 20 0 L 20 20 L 0 20 Z"/>
+            <path d="M0 0 Q 10 10 20 0 T 40 0 Z"/></svg>"#;
+        let shapes = shapes(svg);
+        assert!(shapes[0].shape.contains(5.0, 10.0));
+        assert!(!shapes[0].shape.contains(5.0, 5.0));
+        assert!(shapes[0].shape.contains(15.0, -5.0));
+        assert!(shapes[1].shape.contains(10.0, 2.0));
+        assert!(shapes[1].shape.contains(30.0, -2.0));
+
+        assert!(parse_svg(r#"<svg><path d="M0 0 L 5"/></svg>"#, &IDENTITY).is_err());
+    }
+
+    #[test]
+    fn test_colors_and_transforms() {
+        assert_eq!(parse_color("#AbC").as_deref(), Some("#aabbcc"));
+        assert_eq!(parse_color("rgb(255, 0, 50%)").as_deref(), Some("#ff0080"));
+        assert_eq!(parse_color("Orange").as_deref(), Some("#ffa500"));
+        assert_eq!(parse_color("url(#grad)"), None);
+
+        let m = parse_transform("translate(10) rotate(90, 5, 5) scale(2)").unwrap();
+        let p = apply(&m, [1.0, 0.0]);
+        assert!(
+            (p[0] - 20.0).abs() < 1e-5 && (p[1] - 2.0).abs() < 1e-5,
+            "{:?}",
+            p
+        );
+        assert!(parse_transform("shear(1)").is_err());
+        // Stray ')' before the '(' is an error, not a panic
+        assert!(parse_transform("rotate 30 5 5) scale(2)").is_err());
+        assert!(parse_transform(")otate(30) scale(2)").is_err());
+        assert!(parse_transform("scale(2").is_err());
+        let svg = r#"<svg><rect width="2" height="2" transform="rotate 30 5 5) scale(2)"/></svg>"#;
+        assert!(parse_svg(svg, &IDENTITY).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! Physical Units for Normalized FDTD Quantities
+//!
+//! The solvers work in normalized units: the cell size is 1, time is counted
+//! in steps and frequencies in cycles per step. A `UnitSystem` ties these to
+//! SI values through the cell size Δx (metres) and the Courant number S:
+//! - Δt = S·Δx/c (seconds per step)
+//! - f [Hz] = f_norm / Δt
+//! - t [s] = n·Δt
+//!
+//! Conversions use f64 so that times and frequencies round-trip cleanly
+//! across many orders of magnitude (µm cells give femtosecond steps).
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Speed of light in vacuum (m/s)
+pub const SPEED_OF_LIGHT: f64 = 299_792_458.0;
+
+/// Default cell size of a new grid (1 mm)
+pub const DEFAULT_CELL_SIZE: f64 = 1e-3;
+
+/// Mapping between normalized grid units and SI units
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UnitSystem {
+    cell_size: f64,
+    courant: f64,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl UnitSystem {
+    /// Create a unit system from a cell size (m) and a Courant number
+    /// Non-positive values fall back to the defaults (1 mm, 0.5)
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(cell_size: f64, courant: f64) -> UnitSystem {
+        UnitSystem {
+            cell_size: positive_or(cell_size, DEFAULT_CELL_SIZE),
+            courant: positive_or(courant, 0.5),
+        }
+    }
+
+    /// Get cell size in metres
+    pub fn get_cell_size(&self) -> f64 {
+        self.cell_size
+    }
+
+    /// Get the Courant number S = cΔt/Δx
+    pub fn get_courant(&self) -> f64 {
+        self.courant
+    }
+
+    /// Get the time step in seconds
+    pub fn get_dt(&self) -> f64 {
+        self.courant * self.cell_size / SPEED_OF_LIGHT
+    }
+
+    /// Convert a normalized frequency (cycles per step) to Hz
+    pub fn frequency_to_hz(&self, frequency: f32) -> f64 {
+        frequency as f64 / self.get_dt()
+    }
+
+    /// Convert a frequency in Hz to cycles per step
+    pub fn hz_to_frequency(&self, hz: f64) -> f32 {
+        (hz * self.get_dt()) as f32
+    }
+
+    /// Convert a duration in time steps to seconds
+    pub fn steps_to_seconds(&self, steps: f64) -> f64 {
+        steps * self.get_dt()
+    }
+
+    /// Convert a duration in seconds to time steps
+    pub fn seconds_to_steps(&self, seconds: f64) -> f32 {
+        (seconds / self.get_dt()) as f32
+    }
+
+    /// Convert a length in cells to metres
+    pub fn cells_to_metres(&self, cells: f32) -> f64 {
+        cells as f64 * self.cell_size
+    }
+
+    /// Convert a length in metres to cells
+    pub fn metres_to_cells(&self, metres: f64) -> f32 {
+        (metres / self.cell_size) as f32
+    }
+
+    /// Free-space wavelength in cells of a frequency given in Hz
+    pub fn wavelength_in_cells(&self, hz: f64) -> f32 {
+        if hz <= 0.0 {
+            return f32::INFINITY;
+        }
+        self.metres_to_cells(SPEED_OF_LIGHT / hz)
+    }
+}
+
+impl Default for UnitSystem {
+    fn default() -> Self {
+        UnitSystem::new(DEFAULT_CELL_SIZE, 0.5)
+    }
+}
+
+impl UnitSystem {
+    /// Create a unit system, rejecting the values `new` would replace
+    pub fn try_new(cell_size: f64, courant: f64) -> Result<UnitSystem, String> {
+        let positive = |v: f64| v.is_finite() && v > 0.0;
+        if !positive(cell_size) {
+            return Err(format!("invalid cell size {}", cell_size));
+        }
+        if !positive(courant) {
+            return Err(format!("invalid Courant number {}", courant));
+        }
+        Ok(UnitSystem { cell_size, courant })
+    }
+
+    /// Same mapping with a different Courant number (cell size kept)
+    pub fn with_courant(self, courant: f64) -> UnitSystem {
+        UnitSystem::new(self.cell_size, courant)
+    }
+}
+
+fn positive_or(value: f64, fallback: f64) -> f64 {
+    if value.is_finite() && value > 0.0 {
+        value
+    } else {
+        fallback
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_step_from_courant() {
+        let units = UnitSystem::new(1e-3, 0.5);
+        // Δt = 0.5 mm / c ≈ 1.668 ps
+        assert!((units.get_dt() - 1.6678e-12).abs() < 1e-15);
+        // Halving the Courant number halves Δt
+        let slower = units.with_courant(0.25);
+        assert!((slower.get_dt() * 2.0 - units.get_dt()).abs() < 1e-20);
+    }
+
+    #[test]
+    fn test_frequency_round_trip() {
+        let units = UnitSystem::new(1e-3, 0.5);
+        let f = units.hz_to_frequency(30e9);
+        assert!((f - 0.05).abs() < 1e-4);
+        assert!((units.frequency_to_hz(f) - 30e9).abs() / 30e9 < 1e-6);
+
+        // 30 GHz is a 10 mm wavelength = 10 cells
+        assert!((units.wavelength_in_cells(30e9) - 9.993).abs() < 1e-2);
+        assert!((units.seconds_to_steps(units.steps_to_seconds(400.0)) - 400.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_invalid_values_fall_back() {
+        let units = UnitSystem::new(-1.0, f64::NAN);
+        assert_eq!(units, UnitSystem::default());
+
+        // The checked constructor reports them instead
+        assert!(UnitSystem::try_new(-1.0, 0.5).is_err());
+        assert!(UnitSystem::try_new(1e-3, f64::NAN).is_err());
+        assert_eq!(UnitSystem::try_new(1e-3, 0.5), Ok(UnitSystem::default()));
+    }
+}